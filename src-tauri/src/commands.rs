@@ -1,7 +1,20 @@
-use crate::models::{Document, Settings};
-use tauri::{AppHandle, Manager};
+use crate::db::Database;
+use crate::diff;
+use crate::events::{self, ChangeEvent};
+use crate::export::{self, ExportFormat};
+use crate::import;
+use crate::links::{self, DanglingLinks};
+use crate::merge;
+use crate::ordering::{self, SortMode};
+use crate::models::{Backlink, Breadcrumb, BrokenLink, DeleteImpact, Document, DocumentPage, DocumentVersion, Folder, FolderTree, ImportResult, LinkGraph, MergeResult, NamedSnapshot, SearchError, SearchHit, Settings, Tag, TrashItem, VaultImportReport, VersionDiff, VersionStorageReport, WorkspaceExportReport, WriteError, DEFAULT_TRASH_RETENTION_DAYS};
+use crate::repo::{DocumentRepo, FolderDeleteMode, FolderRepo, RetentionPolicy, SnapshotPolicy, SnapshotRepo, TagRepo, TrashRepo, VersionRepo};
+use crate::search;
+use tauri::{AppHandle, Manager, State};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// 搜索最多返回的结果数
+const SEARCH_RESULT_LIMIT: usize = 100;
 
 fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
     let app_dir = app.path().app_data_dir()
@@ -39,63 +52,43 @@ pub fn get_db_path(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(app_dir.join("ai_notes.db"))
 }
 
+/// 读取设置，失败时记录并使用默认设置；用于保存文档、启动时清理等不能因为设置文件损坏而失败的操作
+fn settings_or_default(app: &AppHandle) -> Settings {
+    get_settings_sync(app).unwrap_or_else(|e| {
+        eprintln!("⚠️ 读取设置失败，使用默认设置: {}", e);
+        Settings::default()
+    })
+}
+
 fn get_settings_sync(app: &AppHandle) -> Result<Settings, String> {
     let settings_path = get_settings_path(app)?;
     
-    println!("📋 Settings path: {:?}", settings_path);
-    println!("📋 Settings file exists: {}", settings_path.exists());
-    
     if settings_path.exists() {
         let content = fs::read_to_string(&settings_path)
             .map_err(|e| format!("Failed to read settings: {}", e))?;
         
-        println!("📋 Settings file content length: {}", content.len());
-        
         // 先解析为 Value，然后手动提取字段（忽略未知字段）
         let value: serde_json::Value = serde_json::from_str(&content)
-            .map_err(|e| {
-                println!("❌ Failed to parse JSON: {}", e);
-                format!("Failed to parse settings JSON: {}", e)
-            })?;
-        
-        println!("📋 Parsed JSON successfully");
-        println!("📋 ai_providers: {:?}", value["ai_providers"]);
+            .map_err(|e| format!("Failed to parse settings JSON: {}", e))?;
         
-        let ai_providers_result: Result<Vec<crate::models::AIProviderConfig>, _> = 
-            serde_json::from_value(value["ai_providers"].clone());
-        
-        let ai_providers = match ai_providers_result {
-            Ok(providers) => {
-                println!("✅ Parsed {} AI providers", providers.len());
-                providers
-            },
+        let ai_providers = match serde_json::from_value::<Vec<crate::models::AIProviderConfig>>(value["ai_providers"].clone()) {
+            Ok(providers) => providers,
             Err(e) => {
                 println!("⚠️ Failed to parse ai_providers: {}, using defaults", e);
                 Settings::default().ai_providers
             }
         };
         
-        let custom_models_result: Result<Vec<crate::models::CustomModel>, _> = 
-            serde_json::from_value(value["custom_models"].clone());
+        let custom_models = serde_json::from_value::<Vec<crate::models::CustomModel>>(value["custom_models"].clone())
+            .unwrap_or_default();
         
-        let custom_models = match custom_models_result {
-            Ok(models) => {
-                println!("✅ Parsed {} custom models", models.len());
-                Some(models)
-            },
-            Err(_) => {
-                println!("⚠️ No custom models found, using empty list");
-                Some(vec![])
-            }
-        };
-        
-        let settings = Settings {
+        Ok(Settings {
             ai_providers,
             default_provider: value["default_provider"].as_str()
                 .unwrap_or("poe").to_string(),
             default_model: value["default_model"].as_str()
                 .unwrap_or("Claude-Sonnet-4.5").to_string(),
-            custom_models,
+            custom_models: Some(custom_models),
             theme: value["theme"].as_str()
                 .unwrap_or("light").to_string(),
             auto_save: value["auto_save"].as_bool()
@@ -104,269 +97,157 @@ fn get_settings_sync(app: &AppHandle) -> Result<Settings, String> {
                 .unwrap_or(2000) as i32,
             database_path: value["database_path"].as_str()
                 .map(|s| s.to_string()),
-        };
-        
-        println!("✅ Settings loaded successfully");
-        println!("📋 POE key present: {}", settings.ai_providers.iter()
-            .find(|p| p.provider_type == "poe")
-            .and_then(|p| p.poe_api_key.as_ref())
-            .map(|k| !k.is_empty())
-            .unwrap_or(false));
-        
-        Ok(settings)
+            trash_retention_days: value["trash_retention_days"].as_i64()
+                .map(|days| days as i32),
+            version_interval_minutes: value["version_interval_minutes"].as_i64()
+                .map(|minutes| minutes as i32),
+            version_min_change_chars: value["version_min_change_chars"].as_i64()
+                .map(|chars| chars as i32),
+            version_keep_all_hours: value["version_keep_all_hours"].as_i64()
+                .map(|hours| hours as i32),
+            version_hourly_days: value["version_hourly_days"].as_i64()
+                .map(|days| days as i32),
+            version_daily_days: value["version_daily_days"].as_i64()
+                .map(|days| days as i32),
+        })
     } else {
-        println!("⚠️ Settings file not found, using defaults");
         Ok(Settings::default())
     }
 }
 
+/// 修改文档的标题、标记等，然后广播修改后的文档
+fn update_and_emit(
+    app: &AppHandle,
+    db: &Database,
+    id: &str,
+    update: impl FnOnce(&DocumentRepo) -> Result<(), String>,
+) -> Result<(), String> {
+    let document = db.write(|conn| {
+        let documents = DocumentRepo::new(conn);
+        update(&documents)?;
+        documents.get(id)
+    })?;
+    events::emit(app, ChangeEvent::DocumentUpdated { document });
+    Ok(())
+}
+
 #[tauri::command]
-pub async fn get_all_documents(app: AppHandle) -> Result<Vec<Document>, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 确保表存在
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS documents (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            content TEXT NOT NULL,
-            created_at INTEGER NOT NULL,
-            updated_at INTEGER NOT NULL
-        )",
-        [],
-    ).map_err(|e| format!("Failed to create table: {}", e))?;
-    
-    // 安全地添加新字段 - 检查字段是否存在
-    let check_column = |table: &str, column: &str| -> bool {
-        conn.query_row(
-            &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name='{}'", table, column),
-            [],
-            |row| row.get::<_, i32>(0)
-        ).unwrap_or(0) > 0
-    };
-    
-    if !check_column("documents", "context_summary") {
-        conn.execute("ALTER TABLE documents ADD COLUMN context_summary TEXT", [])
-            .map_err(|e| format!("Failed to add context_summary: {}", e))?;
-    }
-    
-    if !check_column("documents", "folder_id") {
-        conn.execute("ALTER TABLE documents ADD COLUMN folder_id TEXT", [])
-            .map_err(|e| format!("Failed to add folder_id: {}", e))?;
-    }
-    
-    if !check_column("documents", "is_pinned") {
-        conn.execute("ALTER TABLE documents ADD COLUMN is_pinned INTEGER DEFAULT 0", [])
-            .map_err(|e| format!("Failed to add is_pinned: {}", e))?;
-    }
-    
-    if !check_column("documents", "is_important") {
-        conn.execute("ALTER TABLE documents ADD COLUMN is_important INTEGER DEFAULT 0", [])
-            .map_err(|e| format!("Failed to add is_important: {}", e))?;
-    }
-    
-    // 创建文档版本历史表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS document_versions (
-            id TEXT PRIMARY KEY,
-            document_id TEXT NOT NULL,
-            content TEXT NOT NULL,
-            created_at INTEGER NOT NULL,
-            version_number INTEGER NOT NULL,
-            FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
-        )",
-        [],
-    ).map_err(|e| format!("Failed to create versions table: {}", e))?;
-    
-    // 创建索引（如果不存在）
-    conn.execute("CREATE INDEX IF NOT EXISTS idx_versions_document_id ON document_versions(document_id)", [])
-        .map_err(|e| format!("Failed to create index: {}", e))?;
-    conn.execute("CREATE INDEX IF NOT EXISTS idx_versions_created_at ON document_versions(created_at DESC)", [])
-        .map_err(|e| format!("Failed to create index: {}", e))?;
-    
-    let mut stmt = conn.prepare("SELECT id, title, content, created_at, updated_at, context_summary, folder_id, is_pinned, is_important FROM documents ORDER BY is_pinned DESC, updated_at DESC")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-    
-    let documents = stmt.query_map([], |row| {
-        Ok(Document {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            content: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-            context_summary: row.get(5)?,
-            folder_id: row.get(6)?,
-            is_pinned: row.get(7).ok(),
-            is_important: row.get(8).ok(),
-        })
-    })
-    .map_err(|e| format!("Failed to query documents: {}", e))?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| format!("Failed to collect documents: {}", e))?;
-    
-    Ok(documents)
+pub async fn get_all_documents(db: State<'_, Database>) -> Result<Vec<Document>, String> {
+    db.read(|conn| DocumentRepo::new(conn).list_all())
 }
 
+/// 文档摘要列表（不含内容），按游标分页；`folder_id` 为空字符串时只取根目录
 #[tauri::command]
-pub async fn get_document(app: AppHandle, id: String) -> Result<Document, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let mut stmt = conn.prepare("SELECT id, title, content, created_at, updated_at, context_summary, folder_id, is_pinned, is_important FROM documents WHERE id = ?")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-    
-    let document = stmt.query_row([&id], |row| {
-        Ok(Document {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            content: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-            context_summary: row.get(5)?,
-            folder_id: row.get(6)?,
-            is_pinned: row.get(7).ok(),
-            is_important: row.get(8).ok(),
-        })
-    })
-    .map_err(|e| format!("Document not found: {}", e))?;
-    
+pub async fn list_documents(
+    db: State<'_, Database>,
+    folder_id: Option<String>,
+    cursor: Option<String>,
+    limit: Option<usize>,
+) -> Result<DocumentPage, String> {
+    db.read(|conn| DocumentRepo::new(conn).list_page(folder_id.as_deref(), cursor.as_deref(), limit))
+}
+
+#[tauri::command]
+pub async fn get_document(db: State<'_, Database>, id: String) -> Result<Document, String> {
+    db.read(|conn| DocumentRepo::new(conn).get(&id))
+}
+
+#[tauri::command]
+pub async fn create_document(app: AppHandle, db: State<'_, Database>, title: String, folder_id: Option<String>) -> Result<Document, String> {
+    let document = db.write(|conn| DocumentRepo::new(conn).create(&title, folder_id.as_deref()))?;
+    events::emit(&app, ChangeEvent::DocumentCreated { document: document.clone() });
     Ok(document)
 }
 
+/// 保存内容，返回新的修订号；按设置中的策略先把被覆盖的内容保存为历史版本。
+/// 传了 `expected_revision` 而文档已被其他写入修改时返回冲突，不写入；文档已移入回收站时报错
 #[tauri::command]
-pub async fn create_document(app: AppHandle, title: String, folder_id: Option<String>) -> Result<Document, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 确保表存在
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS documents (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            content TEXT NOT NULL,
-            created_at INTEGER NOT NULL,
-            updated_at INTEGER NOT NULL
-        )",
-        [],
-    ).map_err(|e| format!("Failed to create table: {}", e))?;
-    
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().timestamp();
-    let empty_content = r#"{"type":"doc","content":[]}"#;
-    
-    conn.execute(
-        "INSERT INTO documents (id, title, content, created_at, updated_at, context_summary, folder_id, is_pinned, is_important) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        rusqlite::params![&id, &title, empty_content, &now, &now, "", &folder_id, 0, 0],
-    ).map_err(|e| format!("Failed to insert document: {}", e))?;
-    
-    Ok(Document {
-        id,
-        title,
-        content: empty_content.to_string(),
-        created_at: now,
-        updated_at: now,
-        context_summary: None,
-        folder_id,
-        is_pinned: Some(false),
-        is_important: Some(false),
-    })
+pub async fn update_document(
+    app: AppHandle,
+    db: State<'_, Database>,
+    id: String,
+    content: String,
+    expected_revision: Option<i64>,
+) -> Result<i64, WriteError> {
+    let settings = settings_or_default(&app);
+    let policy = SnapshotPolicy::from_settings(&settings);
+    let document = db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        if let Some(expected) = expected_revision {
+            DocumentRepo::new(&tx).check_revision(&id, expected)?;
+        }
+        if let Some(policy) = policy {
+            let versions = VersionRepo::new(&tx);
+            if versions.snapshot_before_update(&id, &content, policy)? {
+                versions.prune(&id, RetentionPolicy::from_settings(&settings))?;
+            }
+        }
+        DocumentRepo::new(&tx).update_content(&id, &content)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        DocumentRepo::new(conn).get(&id).map_err(WriteError::from)
+    })?;
+    let revision = document.revision;
+    events::emit(&app, ChangeEvent::DocumentUpdated { document });
+    Ok(revision)
 }
 
 #[tauri::command]
-pub async fn update_document(app: AppHandle, id: String, content: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let now = chrono::Utc::now().timestamp();
-    
-    conn.execute(
-        "UPDATE documents SET content = ?1, updated_at = ?2 WHERE id = ?3",
-        [&content, &now.to_string(), &id],
-    ).map_err(|e| format!("Failed to update document: {}", e))?;
-    
-    Ok(())
+pub async fn update_document_title(app: AppHandle, db: State<'_, Database>, id: String, title: String) -> Result<(), String> {
+    update_and_emit(&app, &db, &id, |documents| documents.update_title(&id, &title))
 }
 
+/// 把文档移入回收站；`links` 为 "unlink"（去掉指向它的链接）或 "tombstone"（默认，保留链接，恢复后重新生效）。
+/// 返回受影响的文档
 #[tauri::command]
-pub async fn update_document_title(app: AppHandle, id: String, title: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let now = chrono::Utc::now().timestamp();
-    
-    conn.execute(
-        "UPDATE documents SET title = ?1, updated_at = ?2 WHERE id = ?3",
-        [&title, &now.to_string(), &id],
-    ).map_err(|e| format!("Failed to update document title: {}", e))?;
-    
-    Ok(())
+pub async fn delete_document(app: AppHandle, db: State<'_, Database>, id: String, links: Option<String>) -> Result<DeleteImpact, String> {
+    let mode = DanglingLinks::parse(links.as_deref().unwrap_or("tombstone"))?;
+    let (impact, unlinked) = db.write(|conn| -> Result<_, String> {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let impact = links::delete_impact(&tx, &id)?;
+        let unlinked = match mode {
+            DanglingLinks::Unlink => links::unlink_target(&tx, &id)?,
+            DanglingLinks::Tombstone => Vec::new(),
+        };
+        TrashRepo::new(&tx).trash_document(&id)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        let documents = DocumentRepo::new(conn);
+        let unlinked = unlinked.iter().map(|source_id| documents.get(source_id)).collect::<Result<Vec<_>, _>>()?;
+        Ok((impact, unlinked))
+    })?;
+    events::emit(&app, ChangeEvent::DocumentTrashed { id });
+    for document in unlinked {
+        events::emit(&app, ChangeEvent::DocumentUpdated { document });
+    }
+    Ok(impact)
 }
 
 #[tauri::command]
-pub async fn delete_document(app: AppHandle, id: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    conn.execute(
-        "DELETE FROM documents WHERE id = ?1",
-        [&id],
-    ).map_err(|e| format!("Failed to delete document: {}", e))?;
-    
-    Ok(())
+pub async fn get_delete_impact(db: State<'_, Database>, id: String) -> Result<DeleteImpact, String> {
+    db.read(|conn| links::delete_impact(conn, &id))
 }
 
 #[tauri::command]
-pub async fn search_documents(app: AppHandle, query: String) -> Result<Vec<Document>, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let search_pattern = format!("%{}%", query);
-    let mut stmt = conn.prepare(
-        "SELECT id, title, content, created_at, updated_at, context_summary, folder_id, is_pinned, is_important FROM documents 
-         WHERE title LIKE ?1 OR content LIKE ?1 
-         ORDER BY is_pinned DESC, updated_at DESC"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
-    
-    let documents = stmt.query_map([&search_pattern], |row| {
-        Ok(Document {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            content: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-            context_summary: row.get(5)?,
-            folder_id: row.get(6)?,
-            is_pinned: row.get(7).ok(),
-            is_important: row.get(8).ok(),
-        })
-    })
-    .map_err(|e| format!("Failed to query documents: {}", e))?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| format!("Failed to collect documents: {}", e))?;
-    
-    Ok(documents)
+pub async fn search_documents(db: State<'_, Database>, query: String) -> Result<Vec<SearchHit>, SearchError> {
+    db.read(|conn| search::search(conn, &query, SEARCH_RESULT_LIMIT))
+}
+
+#[tauri::command]
+pub async fn get_backlinks(db: State<'_, Database>, document_id: String) -> Result<Vec<Backlink>, String> {
+    db.read(|conn| links::backlinks(conn, &document_id))
+}
+
+#[tauri::command]
+pub async fn get_link_graph(db: State<'_, Database>) -> Result<LinkGraph, String> {
+    db.read(links::graph)
+}
+
+#[tauri::command]
+pub async fn get_broken_links(db: State<'_, Database>) -> Result<Vec<BrokenLink>, String> {
+    db.read(links::broken_links)
 }
 
 #[tauri::command]
@@ -384,6 +265,7 @@ pub async fn update_settings(app: AppHandle, settings: Settings) -> Result<(), S
     fs::write(&settings_path, content)
         .map_err(|e| format!("Failed to write settings: {}", e))?;
     
+    events::emit(&app, ChangeEvent::SettingsUpdated { settings });
     Ok(())
 }
 
@@ -397,20 +279,52 @@ pub async fn reset_settings(app: AppHandle) -> Result<(), String> {
             .map_err(|e| format!("Failed to delete settings: {}", e))?;
     }
     
+    events::emit(&app, ChangeEvent::SettingsUpdated { settings: get_settings_sync(&app)? });
     Ok(())
 }
 
 #[tauri::command]
-pub async fn export_document(app: AppHandle, id: String, format: String) -> Result<String, String> {
-    let document = get_document(app, id).await?;
-    
-    match format.as_str() {
-        "json" => {
-            serde_json::to_string_pretty(&document)
-                .map_err(|e| format!("Failed to serialize document: {}", e))
-        }
-        _ => Err(format!("Unsupported format: {}", format))
+pub async fn export_document(db: State<'_, Database>, id: String, format: String) -> Result<String, String> {
+    let format = ExportFormat::parse(&format)?;
+    let document = db.read(|conn| DocumentRepo::new(conn).get(&id))?;
+    export::export_document(&document, format)
+}
+
+/// 导出全部文档为 Markdown 目录树；`zip` 为 true 时 `path` 是要写入的 zip 文件
+#[tauri::command]
+pub async fn export_workspace(db: State<'_, Database>, path: String, zip: bool) -> Result<WorkspaceExportReport, String> {
+    let workspace = db.read(export::workspace::build)?;
+    let target = Path::new(&path);
+    if zip {
+        workspace.write_zip(target)?;
+    } else {
+        workspace.write_dir(target)?;
+    }
+    Ok(workspace.report(target))
+}
+
+#[tauri::command]
+pub async fn import_markdown(app: AppHandle, db: State<'_, Database>, title: String, markdown: String, folder_id: Option<String>) -> Result<ImportResult, String> {
+    let result = db.write(|conn| import::import_markdown(conn, &title, &markdown, folder_id.as_deref()))?;
+    events::emit(&app, ChangeEvent::DocumentCreated { document: result.document.clone() });
+    Ok(result)
+}
+
+/// 导入整个 Markdown 目录（如 Obsidian 仓库）；dry_run 只返回报告，不写数据库
+#[tauri::command]
+pub async fn import_vault(app: AppHandle, db: State<'_, Database>, path: String, folder_id: Option<String>, dry_run: bool) -> Result<VaultImportReport, String> {
+    let report = db.write(|conn| -> Result<_, String> {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let report = import::vault::import_vault(&tx, Path::new(&path), folder_id.as_deref(), dry_run)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(report)
+    })?;
+    if !dry_run {
+        events::emit(&app, ChangeEvent::VaultImported { report: report.clone() });
     }
+    Ok(report)
 }
 
 #[tauri::command]
@@ -422,35 +336,18 @@ pub async fn get_default_db_path(app: AppHandle) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub async fn get_current_db_path(app: AppHandle) -> Result<String, String> {
-    let path = get_db_path(&app)?;
-    Ok(path.to_string_lossy().to_string())
+pub async fn get_current_db_path(db: State<'_, Database>) -> Result<String, String> {
+    Ok(db.path().to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub async fn update_context_summary(app: AppHandle, id: String, summary: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let now = chrono::Utc::now().timestamp();
-    
-    conn.execute(
-        "UPDATE documents SET context_summary = ?1, updated_at = ?2 WHERE id = ?3",
-        [&summary, &now.to_string(), &id],
-    ).map_err(|e| format!("Failed to update context summary: {}", e))?;
-    
-    Ok(())
+pub async fn update_context_summary(app: AppHandle, db: State<'_, Database>, id: String, summary: String) -> Result<(), String> {
+    update_and_emit(&app, &db, &id, |documents| documents.update_context_summary(&id, &summary))
 }
 
 #[tauri::command]
-pub async fn change_database_path(app: AppHandle, new_path: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    // 获取当前数据库路径
-    let old_path = get_db_path(&app)?;
+pub async fn change_database_path(app: AppHandle, db: State<'_, Database>, new_path: String) -> Result<(), String> {
+    let old_path = db.path();
     let new_path_buf = PathBuf::from(&new_path);
     
     // 确保新路径的父目录存在
@@ -459,37 +356,18 @@ pub async fn change_database_path(app: AppHandle, new_path: String) -> Result<()
             .map_err(|e| format!("Failed to create directory: {}", e))?;
     }
     
-    // 如果旧数据库存在，复制到新位置
-    if old_path.exists() && old_path != new_path_buf {
-        // 先测试新路径是否可写
-        let test_conn = Connection::open(&new_path_buf)
-            .map_err(|e| format!("Cannot create database at new location: {}", e))?;
-        test_conn.close()
-            .map_err(|e| format!("Failed to close test connection: {:?}", e))?;
-        
-        // 复制数据库文件
-        fs::copy(&old_path, &new_path_buf)
-            .map_err(|e| format!("Failed to copy database: {}", e))?;
-        
-        println!("✅ Database copied from {:?} to {:?}", old_path, new_path_buf);
-    } else if !new_path_buf.exists() {
-        // 如果新位置不存在数据库，创建一个新的
-        let conn = Connection::open(&new_path_buf)
-            .map_err(|e| format!("Failed to create database: {}", e))?;
-        
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS documents (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                content TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-            [],
-        ).map_err(|e| format!("Failed to create table: {}", e))?;
-        
-        println!("✅ New database created at {:?}", new_path_buf);
-    }
+    // 如果旧数据库存在，复制到新位置；新位置可能是全新文件，也可能是旧版本留下的数据库，
+    // 重新打开时统一迁移到最新结构
+    db.replace(&new_path_buf, || {
+        if old_path.exists() && old_path != new_path_buf {
+            fs::copy(&old_path, &new_path_buf)
+                .map_err(|e| format!("Failed to copy database: {}", e))?;
+            println!("✅ Database copied from {:?} to {:?}", old_path, new_path_buf);
+        } else if !new_path_buf.exists() {
+            println!("✅ New database created at {:?}", new_path_buf);
+        }
+        Ok(())
+    })?;
     
     // 更新设置中的数据库路径
     let mut settings = get_settings_sync(&app)?;
@@ -502,6 +380,8 @@ pub async fn change_database_path(app: AppHandle, new_path: String) -> Result<()
     fs::write(&settings_path, content)
         .map_err(|e| format!("Failed to write settings: {}", e))?;
     
+    events::emit(&app, ChangeEvent::DatabaseReplaced { path: new_path_buf.to_string_lossy().to_string() });
+    events::emit(&app, ChangeEvent::SettingsUpdated { settings });
     Ok(())
 }
 
@@ -521,109 +401,182 @@ pub async fn save_file(path: String, content: String) -> Result<(), String> {
 // ==================== 文件夹相关命令 ====================
 
 #[tauri::command]
-pub async fn get_all_folders(app: AppHandle) -> Result<Vec<crate::models::Folder>, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let mut stmt = conn.prepare("SELECT id, name, parent_id, created_at, updated_at FROM folders ORDER BY name ASC")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-    
-    let folders = stmt.query_map([], |row| {
-        Ok(crate::models::Folder {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            parent_id: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-        })
-    })
-    .map_err(|e| format!("Failed to query folders: {}", e))?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| format!("Failed to collect folders: {}", e))?;
-    
-    Ok(folders)
+pub async fn get_all_folders(db: State<'_, Database>) -> Result<Vec<Folder>, String> {
+    db.read(|conn| FolderRepo::new(conn).list_all())
 }
 
+/// 嵌套文件夹树，附带文档数和最新修改时间
 #[tauri::command]
-pub async fn create_folder(app: AppHandle, name: String, parent_id: Option<String>) -> Result<crate::models::Folder, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().timestamp();
-    
-    conn.execute(
-        "INSERT INTO folders (id, name, parent_id, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-        rusqlite::params![&id, &name, &parent_id, &now, &now],
-    ).map_err(|e| format!("Failed to insert folder: {}", e))?;
-    
-    Ok(crate::models::Folder {
-        id,
-        name,
-        parent_id,
-        created_at: now,
-        updated_at: now,
-    })
+pub async fn get_folder_tree(db: State<'_, Database>) -> Result<FolderTree, String> {
+    db.read(|conn| FolderRepo::new(conn).tree())
 }
 
+/// 文档或文件夹从根开始的路径
 #[tauri::command]
-pub async fn update_folder(app: AppHandle, id: String, name: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let now = chrono::Utc::now().timestamp();
-    
-    conn.execute(
-        "UPDATE folders SET name = ?1, updated_at = ?2 WHERE id = ?3",
-        rusqlite::params![&name, &now, &id],
-    ).map_err(|e| format!("Failed to update folder: {}", e))?;
-    
+pub async fn get_breadcrumbs(db: State<'_, Database>, id: String) -> Result<Vec<Breadcrumb>, String> {
+    db.read(|conn| FolderRepo::new(conn).breadcrumbs(&id))
+}
+
+#[tauri::command]
+pub async fn create_folder(app: AppHandle, db: State<'_, Database>, name: String, parent_id: Option<String>) -> Result<Folder, String> {
+    let folder = db.write(|conn| FolderRepo::new(conn).create(&name, parent_id.as_deref()))?;
+    events::emit(&app, ChangeEvent::FolderCreated { folder: folder.clone() });
+    Ok(folder)
+}
+
+#[tauri::command]
+pub async fn update_folder(app: AppHandle, db: State<'_, Database>, id: String, name: String) -> Result<(), String> {
+    db.write(|conn| FolderRepo::new(conn).rename(&id, &name))?;
+    events::emit(&app, ChangeEvent::FolderRenamed { id, name });
     Ok(())
 }
 
+/// 手动排序：把文档移到同一文件夹中 `before_id` 的前面，`before_id` 为空时移到末尾
 #[tauri::command]
-pub async fn delete_folder(app: AppHandle, id: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 删除文件夹（级联删除子文件夹和文档）
-    conn.execute("DELETE FROM folders WHERE id = ?1", rusqlite::params![&id])
-        .map_err(|e| format!("Failed to delete folder: {}", e))?;
-    
-    // 将该文件夹下的文档移到根目录
-    conn.execute("UPDATE documents SET folder_id = NULL WHERE folder_id = ?1", rusqlite::params![&id])
-        .map_err(|e| format!("Failed to update documents: {}", e))?;
-    
+pub async fn reorder_document(app: AppHandle, db: State<'_, Database>, id: String, before_id: Option<String>) -> Result<(), String> {
+    db.write(|conn| DocumentRepo::new(conn).reorder(&id, before_id.as_deref()))?;
+    events::emit(&app, ChangeEvent::DocumentReordered { id, before_id });
     Ok(())
 }
 
+/// 手动排序：把文件夹移到同级 `before_id` 的前面，`before_id` 为空时移到末尾
 #[tauri::command]
-pub async fn move_document(app: AppHandle, document_id: String, folder_id: Option<String>) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let now = chrono::Utc::now().timestamp();
-    
-    conn.execute(
-        "UPDATE documents SET folder_id = ?1, updated_at = ?2 WHERE id = ?3",
-        rusqlite::params![&folder_id, &now, &document_id],
-    ).map_err(|e| format!("Failed to move document: {}", e))?;
-    
+pub async fn reorder_folder(app: AppHandle, db: State<'_, Database>, id: String, before_id: Option<String>) -> Result<(), String> {
+    db.write(|conn| FolderRepo::new(conn).reorder(&id, before_id.as_deref()))?;
+    events::emit(&app, ChangeEvent::FolderReordered { id, before_id });
+    Ok(())
+}
+
+/// 文件夹（`folder_id` 为空时为根目录）的排序方式："manual"、"title"、"created" 或 "updated"
+#[tauri::command]
+pub async fn get_folder_sort_mode(db: State<'_, Database>, folder_id: Option<String>) -> Result<String, String> {
+    db.read(|conn| ordering::sort_mode(conn, folder_id.as_deref()).map(|mode| mode.as_str().to_string()))
+}
+
+#[tauri::command]
+pub async fn set_folder_sort_mode(app: AppHandle, db: State<'_, Database>, folder_id: Option<String>, mode: String) -> Result<(), String> {
+    let sort_mode = SortMode::parse(&mode)?;
+    db.write(|conn| ordering::set_sort_mode(conn, folder_id.as_deref(), sort_mode))?;
+    events::emit(&app, ChangeEvent::FolderSortModeChanged { folder_id, mode });
+    Ok(())
+}
+
+/// 把文件夹移到 `parent_id` 下（为空时移到根目录）
+#[tauri::command]
+pub async fn move_folder(app: AppHandle, db: State<'_, Database>, id: String, parent_id: Option<String>) -> Result<(), String> {
+    db.write(|conn| FolderRepo::new(conn).move_to(&id, parent_id.as_deref()))?;
+    events::emit(&app, ChangeEvent::FolderMoved { id, parent_id });
+    Ok(())
+}
+
+/// 在一个事务里把多个文件夹和文档移到同一个文件夹下
+#[tauri::command]
+pub async fn move_items(
+    app: AppHandle,
+    db: State<'_, Database>,
+    folder_ids: Vec<String>,
+    document_ids: Vec<String>,
+    target_folder_id: Option<String>,
+) -> Result<(), String> {
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        FolderRepo::new(&tx).move_items(&folder_ids, &document_ids, target_folder_id.as_deref())?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))
+    })?;
+    for id in folder_ids {
+        events::emit(&app, ChangeEvent::FolderMoved { id, parent_id: target_folder_id.clone() });
+    }
+    for id in document_ids {
+        events::emit(&app, ChangeEvent::DocumentMoved { id, folder_id: target_folder_id.clone() });
+    }
+    Ok(())
+}
+
+/// 把文件夹移入回收站；`mode` 为 "parent"（内容移到上级）、"root"（内容移到根目录）
+/// 或 "recursive"（默认，连同整棵子树一起删除）
+#[tauri::command]
+pub async fn delete_folder(app: AppHandle, db: State<'_, Database>, id: String, mode: Option<String>) -> Result<(), String> {
+    let mode = mode.unwrap_or_else(|| "recursive".to_string());
+    let delete_mode = FolderDeleteMode::parse(&mode)?;
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        FolderRepo::new(&tx).trash(&id, delete_mode)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))
+    })?;
+    events::emit(&app, ChangeEvent::FolderTrashed { id, mode });
+    Ok(())
+}
+
+// ==================== 回收站相关命令 ====================
+
+#[tauri::command]
+pub async fn list_trash(db: State<'_, Database>) -> Result<Vec<TrashItem>, String> {
+    db.read(|conn| TrashRepo::new(conn).list())
+}
+
+/// 恢复回收站中的文档或文件夹到原来的位置
+#[tauri::command]
+pub async fn restore_from_trash(app: AppHandle, db: State<'_, Database>, id: String) -> Result<(), String> {
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        TrashRepo::new(&tx).restore(&id)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))
+    })?;
+    events::emit(&app, ChangeEvent::TrashRestored { id });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn purge_from_trash(app: AppHandle, db: State<'_, Database>, id: String) -> Result<(), String> {
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        TrashRepo::new(&tx).purge(&id)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))
+    })?;
+    events::emit(&app, ChangeEvent::TrashPurged { id });
+    Ok(())
+}
+
+/// 清空回收站，返回永久删除的条目数
+#[tauri::command]
+pub async fn empty_trash(app: AppHandle, db: State<'_, Database>) -> Result<usize, String> {
+    let purged = db.write(|conn| -> Result<_, String> {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let purged = TrashRepo::new(&tx).empty()?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(purged)
+    })?;
+    events::emit(&app, ChangeEvent::TrashEmptied { count: purged });
+    Ok(purged)
+}
+
+/// 永久删除超过保留期的回收站条目（启动时调用），返回条目数
+pub fn purge_expired_trash(app: &AppHandle, db: &Database) -> Result<usize, String> {
+    let days = settings_or_default(app).trash_retention_days.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let purged = TrashRepo::new(&tx).purge_expired(days as i64)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(purged)
+    })
+}
+
+#[tauri::command]
+pub async fn move_document(app: AppHandle, db: State<'_, Database>, document_id: String, folder_id: Option<String>) -> Result<(), String> {
+    db.write(|conn| FolderRepo::new(conn).move_document(&document_id, folder_id.as_deref()))?;
+    events::emit(&app, ChangeEvent::DocumentMoved { id: document_id, folder_id });
     Ok(())
 }
 
@@ -631,122 +584,58 @@ pub async fn move_document(app: AppHandle, document_id: String, folder_id: Optio
 
 #[tauri::command]
 pub async fn get_tags_by_document(
-    app: AppHandle,
+    db: State<'_, Database>,
     document_id: String,
-) -> Result<Vec<crate::models::Tag>, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let mut stmt = conn.prepare("SELECT id, document_id, text, selected_text, position, ai_block_id, color, created_at, updated_at FROM tags WHERE document_id = ? ORDER BY position ASC")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
-    
-    let tags = stmt.query_map([&document_id], |row| {
-        Ok(crate::models::Tag {
-            id: row.get(0)?,
-            document_id: row.get(1)?,
-            text: row.get(2)?,
-            selected_text: row.get(3)?,
-            position: row.get(4)?,
-            ai_block_id: row.get(5)?,
-            color: row.get(6)?,
-            created_at: row.get(7)?,
-            updated_at: row.get(8)?,
-        })
-    })
-    .map_err(|e| format!("Failed to query tags: {}", e))?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| format!("Failed to collect tags: {}", e))?;
-    
-    Ok(tags)
+) -> Result<Vec<Tag>, String> {
+    db.read(|conn| TagRepo::new(conn).list_by_document(&document_id))
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_tag(
     app: AppHandle,
+    db: State<'_, Database>,
     document_id: String,
     text: String,
     selected_text: String,
     position: i32,
     ai_block_id: Option<String>,
     color: Option<String>,
-) -> Result<crate::models::Tag, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().timestamp_millis();
-    
-    conn.execute(
-        "INSERT INTO tags (id, document_id, text, selected_text, position, ai_block_id, color, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        rusqlite::params![
-            &id,
+) -> Result<Tag, String> {
+    let tag = db.write(|conn| {
+        TagRepo::new(conn).create(
             &document_id,
             &text,
             &selected_text,
-            &position,
-            &ai_block_id,
-            &color,
-            &now,
-            &now,
-        ],
-    )
-    .map_err(|e| format!("Failed to create tag: {}", e))?;
-    
-    Ok(crate::models::Tag {
-        id,
-        document_id,
-        text,
-        selected_text,
-        position,
-        ai_block_id,
-        color,
-        created_at: now,
-        updated_at: now,
-    })
+            position,
+            ai_block_id.as_deref(),
+            color.as_deref(),
+        )
+    })?;
+    events::emit(&app, ChangeEvent::TagCreated { tag: tag.clone() });
+    Ok(tag)
 }
 
 #[tauri::command]
 pub async fn update_tag(
     app: AppHandle,
+    db: State<'_, Database>,
     id: String,
     text: String,
 ) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let now = chrono::Utc::now().timestamp_millis();
-    conn.execute(
-        "UPDATE tags SET text = ?1, updated_at = ?2 WHERE id = ?3",
-        rusqlite::params![&text, &now, &id],
-    )
-    .map_err(|e| format!("Failed to update tag: {}", e))?;
-    
+    db.write(|conn| TagRepo::new(conn).update_text(&id, &text))?;
+    events::emit(&app, ChangeEvent::TagUpdated { id, text });
     Ok(())
 }
 
 #[tauri::command]
 pub async fn delete_tag(
     app: AppHandle,
+    db: State<'_, Database>,
     id: String,
 ) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    conn.execute("DELETE FROM tags WHERE id = ?1", rusqlite::params![&id])
-        .map_err(|e| format!("Failed to delete tag: {}", e))?;
-    
+    db.write(|conn| TagRepo::new(conn).delete(&id))?;
+    events::emit(&app, ChangeEvent::TagDeleted { id });
     Ok(())
 }
 
@@ -889,142 +778,173 @@ pub async fn check_stt_service() -> Result<bool, String> {
 
 
 #[tauri::command]
-pub async fn toggle_pin_document(app: AppHandle, id: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 切换置顶状态
-    conn.execute(
-        "UPDATE documents SET is_pinned = CASE WHEN is_pinned = 1 THEN 0 ELSE 1 END WHERE id = ?1",
-        [&id],
-    ).map_err(|e| format!("Failed to toggle pin: {}", e))?;
-    
+pub async fn toggle_pin_document(app: AppHandle, db: State<'_, Database>, id: String) -> Result<(), String> {
+    update_and_emit(&app, &db, &id, |documents| documents.toggle_pinned(&id))
+}
+
+#[tauri::command]
+pub async fn toggle_important_document(app: AppHandle, db: State<'_, Database>, id: String) -> Result<(), String> {
+    update_and_emit(&app, &db, &id, |documents| documents.toggle_important(&id))
+}
+
+#[tauri::command]
+pub async fn save_document_version(app: AppHandle, db: State<'_, Database>, document_id: String, content: String) -> Result<(), String> {
+    let policy = RetentionPolicy::from_settings(&settings_or_default(&app));
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let versions = VersionRepo::new(&tx);
+        versions.save(&document_id, &content)?;
+        versions.prune(&document_id, policy)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))
+    })?;
+    events::emit(&app, ChangeEvent::VersionSaved { document_id });
     Ok(())
 }
 
 #[tauri::command]
-pub async fn toggle_important_document(app: AppHandle, id: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 切换重要标记
-    conn.execute(
-        "UPDATE documents SET is_important = CASE WHEN is_important = 1 THEN 0 ELSE 1 END WHERE id = ?1",
-        [&id],
-    ).map_err(|e| format!("Failed to toggle important: {}", e))?;
-    
+pub async fn get_document_versions(db: State<'_, Database>, document_id: String) -> Result<Vec<DocumentVersion>, String> {
+    db.read(|conn| VersionRepo::new(conn).list(&document_id))
+}
+
+/// 设置版本的标签和备注，标签为空时取消标签
+#[tauri::command]
+pub async fn label_document_version(
+    app: AppHandle,
+    db: State<'_, Database>,
+    document_id: String,
+    version_id: String,
+    label: Option<String>,
+    note: Option<String>,
+) -> Result<(), String> {
+    db.write(|conn| VersionRepo::new(conn).set_label(&document_id, &version_id, label.as_deref(), note.as_deref()))?;
+    events::emit(&app, ChangeEvent::VersionLabeled { document_id, version_id, label, note });
     Ok(())
 }
 
 #[tauri::command]
-pub async fn save_document_version(app: AppHandle, document_id: String, content: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    use uuid::Uuid;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 获取当前版本号
-    let version_number: i32 = conn.query_row(
-        "SELECT COALESCE(MAX(version_number), 0) + 1 FROM document_versions WHERE document_id = ?1",
-        [&document_id],
-        |row| row.get(0),
-    ).unwrap_or(1);
-    
-    let id = Uuid::new_v4().to_string();
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-    
-    conn.execute(
-        "INSERT INTO document_versions (id, document_id, content, created_at, version_number) VALUES (?1, ?2, ?3, ?4, ?5)",
-        [&id, &document_id, &content, &now.to_string(), &version_number.to_string()],
-    ).map_err(|e| format!("Failed to save version: {}", e))?;
-    
-    // 只保留最近20个版本
-    conn.execute(
-        "DELETE FROM document_versions WHERE document_id = ?1 AND version_number < (
-            SELECT MAX(version_number) - 20 FROM document_versions WHERE document_id = ?1
-        )",
-        [&document_id],
-    ).ok();
-    
+pub async fn list_snapshots(db: State<'_, Database>) -> Result<Vec<NamedSnapshot>, String> {
+    db.read(|conn| SnapshotRepo::new(conn).list())
+}
+
+/// 给文件夹（`folder_id` 为空时整个工作区）的全部文档创建命名快照
+#[tauri::command]
+pub async fn create_snapshot(
+    app: AppHandle,
+    db: State<'_, Database>,
+    label: String,
+    note: Option<String>,
+    folder_id: Option<String>,
+) -> Result<NamedSnapshot, String> {
+    let snapshot = db.write(|conn| -> Result<_, String> {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let snapshot = SnapshotRepo::new(&tx).create(&label, note.as_deref(), folder_id.as_deref())?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(snapshot)
+    })?;
+    events::emit(&app, ChangeEvent::SnapshotCreated { snapshot: snapshot.clone() });
+    Ok(snapshot)
+}
+
+/// 恢复命名快照，返回内容被恢复的文档数
+#[tauri::command]
+pub async fn restore_snapshot(app: AppHandle, db: State<'_, Database>, id: String) -> Result<usize, String> {
+    let restored = db.write(|conn| -> Result<_, String> {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let restored = SnapshotRepo::new(&tx).restore(&id)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(restored)
+    })?;
+    events::emit(&app, ChangeEvent::SnapshotRestored { id, restored });
+    Ok(restored)
+}
+
+#[tauri::command]
+pub async fn delete_snapshot(app: AppHandle, db: State<'_, Database>, id: String) -> Result<(), String> {
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        SnapshotRepo::new(&tx).delete(&id)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))
+    })?;
+    events::emit(&app, ChangeEvent::SnapshotDeleted { id });
     Ok(())
 }
 
+/// 历史版本占用的空间
 #[tauri::command]
-pub async fn get_document_versions(app: AppHandle, document_id: String) -> Result<Vec<crate::models::DocumentVersion>, String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    let mut stmt = conn.prepare(
-        "SELECT id, document_id, content, created_at, version_number FROM document_versions 
-         WHERE document_id = ?1 ORDER BY version_number DESC LIMIT 20"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
-    
-    let versions = stmt.query_map([&document_id], |row| {
-        Ok(crate::models::DocumentVersion {
-            id: row.get(0)?,
-            document_id: row.get(1)?,
-            content: row.get(2)?,
-            created_at: row.get(3)?,
-            version_number: row.get(4)?,
-        })
+pub async fn get_version_storage(db: State<'_, Database>) -> Result<VersionStorageReport, String> {
+    db.read(|conn| VersionRepo::new(conn).storage_report())
+}
+
+/// 按保留策略删除所有文档的旧版本（启动时调用），返回删除的版本数
+pub fn prune_versions(app: &AppHandle, db: &Database) -> Result<usize, String> {
+    let policy = RetentionPolicy::from_settings(&settings_or_default(app));
+    db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let pruned = VersionRepo::new(&tx).prune_all(policy)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(pruned)
     })
-    .map_err(|e| format!("Failed to query versions: {}", e))?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| format!("Failed to collect versions: {}", e))?;
-    
-    Ok(versions)
 }
 
+/// 比较两个版本；`to_version_id` 为空时与当前内容比较
 #[tauri::command]
-pub async fn restore_document_version(app: AppHandle, document_id: String, version_id: String) -> Result<(), String> {
-    use rusqlite::Connection;
-    
-    let db_path = get_db_path(&app)?;
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 获取版本内容
-    let content: String = conn.query_row(
-        "SELECT content FROM document_versions WHERE id = ?1",
-        [&version_id],
-        |row| row.get(0),
-    ).map_err(|e| format!("Failed to get version: {}", e))?;
-    
-    // 更新文档
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-    
-    conn.execute(
-        "UPDATE documents SET content = ?1, updated_at = ?2 WHERE id = ?3",
-        [&content, &now.to_string(), &document_id],
-    ).map_err(|e| format!("Failed to restore version: {}", e))?;
-    
+pub async fn diff_versions(
+    db: State<'_, Database>,
+    document_id: String,
+    from_version_id: String,
+    to_version_id: Option<String>,
+) -> Result<VersionDiff, String> {
+    db.read(|conn| {
+        let versions = VersionRepo::new(conn);
+        let old = versions.get(&document_id, &from_version_id)?.content;
+        let new = match to_version_id {
+            Some(to_version_id) => versions.get(&document_id, &to_version_id)?.content,
+            None => DocumentRepo::new(conn).get(&document_id)?.content,
+        };
+        diff::diff_contents(&old, &new)
+    })
+}
+
+/// 三方合并文档内容，例如保存冲突时以冲突前的内容为 `base` 合并本地修改和后端的当前内容
+#[tauri::command]
+pub async fn merge_document_contents(base: String, ours: String, theirs: String) -> Result<MergeResult, String> {
+    merge::merge_contents(&base, &ours, &theirs)
+}
+
+#[tauri::command]
+pub async fn restore_document_version(app: AppHandle, db: State<'_, Database>, document_id: String, version_id: String) -> Result<(), String> {
+    let policy = RetentionPolicy::from_settings(&settings_or_default(&app));
+    let document = db.write(|conn| {
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let versions = VersionRepo::new(&tx);
+        versions.restore(&document_id, &version_id)?;
+        versions.prune(&document_id, policy)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        DocumentRepo::new(conn).get(&document_id)
+    })?;
+    events::emit(&app, ChangeEvent::DocumentUpdated { document });
     Ok(())
 }
 
 // ==================== 备份相关命令 ====================
 
 #[tauri::command]
-pub async fn create_backup(app: AppHandle) -> Result<String, String> {
+pub async fn create_backup(app: AppHandle, db: State<'_, Database>) -> Result<String, String> {
     use std::time::SystemTime;
     
-    let db_path = get_db_path(&app)?;
+    let db_path = db.path();
     
     // 创建备份目录
     let app_dir = app.path().app_data_dir()
@@ -1042,6 +962,7 @@ pub async fn create_backup(app: AppHandle) -> Result<String, String> {
     let backup_path = backup_dir.join(&backup_filename);
     
     // 复制数据库文件
+    db.checkpoint()?;
     fs::copy(&db_path, &backup_path)
         .map_err(|e| format!("Failed to create backup: {}", e))?;
     
@@ -1079,7 +1000,7 @@ pub async fn list_backups(app: AppHandle) -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-pub async fn restore_backup(app: AppHandle, backup_filename: String) -> Result<(), String> {
+pub async fn restore_backup(app: AppHandle, db: State<'_, Database>, backup_filename: String) -> Result<(), String> {
     let app_dir = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
     let backup_path = app_dir.join("backups").join(&backup_filename);
@@ -1088,21 +1009,29 @@ pub async fn restore_backup(app: AppHandle, backup_filename: String) -> Result<(
         return Err("Backup file not found".to_string());
     }
     
-    let db_path = get_db_path(&app)?;
+    // 拒绝恢复比当前应用更新的数据库
+    crate::migrate::check_compatible(&backup_path)?;
     
-    // 在恢复前创建当前数据库的备份
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let safety_backup = db_path.parent().unwrap().join(format!("ai_notes_before_restore_{}.db", timestamp));
-    fs::copy(&db_path, &safety_backup)
-        .map_err(|e| format!("Failed to create safety backup: {}", e))?;
-    
-    // 恢复备份
-    fs::copy(&backup_path, &db_path)
-        .map_err(|e| format!("Failed to restore backup: {}", e))?;
+    let db_path = db.path();
     
+    // 关闭连接后再覆盖文件，重新打开时旧备份会被迁移到最新结构
+    db.replace(&db_path, || {
+        // 在恢复前创建当前数据库的备份
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let safety_backup = db_path.parent().unwrap().join(format!("ai_notes_before_restore_{}.db", timestamp));
+        fs::copy(&db_path, &safety_backup)
+            .map_err(|e| format!("Failed to create safety backup: {}", e))?;
+        
+        // 恢复备份
+        fs::copy(&backup_path, &db_path)
+            .map_err(|e| format!("Failed to restore backup: {}", e))?;
+        
+        Ok(())
+    })?;
+    events::emit(&app, ChangeEvent::DatabaseReplaced { path: db_path.to_string_lossy().to_string() });
     Ok(())
 }
 
@@ -1121,8 +1050,8 @@ pub async fn delete_backup(app: AppHandle, backup_filename: String) -> Result<()
 }
 
 #[tauri::command]
-pub async fn export_database(app: AppHandle) -> Result<String, String> {
-    let db_path = get_db_path(&app)?;
+pub async fn export_database(app: AppHandle, db: State<'_, Database>) -> Result<String, String> {
+    let db_path = db.path();
     
     // 使用 tauri-plugin-dialog
     use tauri_plugin_dialog::DialogExt;
@@ -1136,6 +1065,7 @@ pub async fn export_database(app: AppHandle) -> Result<String, String> {
     
     if let Some(file_path) = save_path {
         let path_buf = file_path.into_path().map_err(|e| format!("Failed to get path: {:?}", e))?;
+        db.checkpoint()?;
         fs::copy(&db_path, &path_buf)
             .map_err(|e| format!("Failed to export database: {}", e))?;
         Ok(path_buf.to_string_lossy().to_string())
@@ -1145,7 +1075,7 @@ pub async fn export_database(app: AppHandle) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub async fn import_database(app: AppHandle) -> Result<(), String> {
+pub async fn import_database(app: AppHandle, db: State<'_, Database>) -> Result<(), String> {
     // 使用 tauri-plugin-dialog
     use tauri_plugin_dialog::DialogExt;
     
@@ -1158,21 +1088,26 @@ pub async fn import_database(app: AppHandle) -> Result<(), String> {
     
     if let Some(file_path) = import_path {
         let path_buf = file_path.into_path().map_err(|e| format!("Failed to get path: {:?}", e))?;
-        let db_path = get_db_path(&app)?;
-        
-        // 创建当前数据库的备份
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let backup_path = db_path.parent().unwrap().join(format!("ai_notes_before_import_{}.db", timestamp));
-        fs::copy(&db_path, &backup_path)
-            .map_err(|e| format!("Failed to create backup before import: {}", e))?;
-        
-        // 导入数据库
-        fs::copy(&path_buf, &db_path)
-            .map_err(|e| format!("Failed to import database: {}", e))?;
+        crate::migrate::check_compatible(&path_buf)?;
+        let db_path = db.path();
         
+        db.replace(&db_path, || {
+            // 创建当前数据库的备份
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let backup_path = db_path.parent().unwrap().join(format!("ai_notes_before_import_{}.db", timestamp));
+            fs::copy(&db_path, &backup_path)
+                .map_err(|e| format!("Failed to create backup before import: {}", e))?;
+            
+            // 导入数据库
+            fs::copy(&path_buf, &db_path)
+                .map_err(|e| format!("Failed to import database: {}", e))?;
+            
+            Ok(())
+        })?;
+        events::emit(&app, ChangeEvent::DatabaseReplaced { path: db_path.to_string_lossy().to_string() });
         Ok(())
     } else {
         Err("Import cancelled".to_string())
@@ -1192,13 +1127,11 @@ pub async fn get_backup_info(app: AppHandle) -> Result<serde_json::Value, String
         let entries = fs::read_dir(&backup_dir)
             .map_err(|e| format!("Failed to read backup directory: {}", e))?;
         
-        for entry in entries {
-            if let Ok(entry) = entry {
-                if let Ok(metadata) = entry.metadata() {
-                    if entry.path().extension().and_then(|s| s.to_str()) == Some("db") {
-                        total_size += metadata.len();
-                        backup_count += 1;
-                    }
+        for entry in entries.flatten() {
+            if let Ok(metadata) = entry.metadata() {
+                if entry.path().extension().and_then(|s| s.to_str()) == Some("db") {
+                    total_size += metadata.len();
+                    backup_count += 1;
                 }
             }
         }
@@ -1212,11 +1145,11 @@ pub async fn get_backup_info(app: AppHandle) -> Result<serde_json::Value, String
 }
 
 #[tauri::command]
-pub async fn get_database_hash(app: AppHandle) -> Result<String, String> {
+pub async fn get_database_hash(db: State<'_, Database>) -> Result<String, String> {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
     
-    let db_path = get_db_path(&app)?;
+    let db_path = db.path();
     
     if !db_path.exists() {
         return Ok(String::new());
@@ -1251,15 +1184,13 @@ pub async fn clean_old_backups(app: AppHandle, keep_count: usize) -> Result<(),
     let entries = fs::read_dir(&backup_dir)
         .map_err(|e| format!("Failed to read backup directory: {}", e))?;
     
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("db") {
-                if let Ok(metadata) = entry.metadata() {
-                    if let Ok(modified) = metadata.modified() {
-                        if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
-                            backups.push((filename.to_string(), modified));
-                        }
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("db") {
+            if let Ok(metadata) = entry.metadata() {
+                if let Ok(modified) = metadata.modified() {
+                    if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
+                        backups.push((filename.to_string(), modified));
                     }
                 }
             }
@@ -1267,7 +1198,7 @@ pub async fn clean_old_backups(app: AppHandle, keep_count: usize) -> Result<(),
     }
     
     // 按时间排序（最新的在前）
-    backups.sort_by(|a, b| b.1.cmp(&a.1));
+    backups.sort_by_key(|b| std::cmp::Reverse(b.1));
     
     // 删除超过保留数量的备份
     for (filename, _) in backups.iter().skip(keep_count) {
@@ -1,57 +1,130 @@
-use tauri_plugin_sql::{Migration, MigrationKind};
-
-pub fn get_migrations() -> Vec<Migration> {
-    vec![
-        Migration {
-            version: 1,
-            description: "create initial tables",
-            sql: "
-                CREATE TABLE IF NOT EXISTS documents (
-                    id TEXT PRIMARY KEY,
-                    title TEXT NOT NULL,
-                    content TEXT NOT NULL,
-                    created_at INTEGER NOT NULL,
-                    updated_at INTEGER NOT NULL
-                );
-                
-                CREATE TABLE IF NOT EXISTS settings (
-                    key TEXT PRIMARY KEY,
-                    value TEXT NOT NULL
-                );
-                
-                CREATE INDEX IF NOT EXISTS idx_documents_updated_at ON documents(updated_at DESC);
-                CREATE INDEX IF NOT EXISTS idx_documents_title ON documents(title);
-            ",
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 2,
-            description: "add context_summary to documents",
-            sql: "
-                ALTER TABLE documents ADD COLUMN context_summary TEXT;
-            ",
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 3,
-            description: "create tags table",
-            sql: "
-                CREATE TABLE IF NOT EXISTS tags (
-                    id TEXT PRIMARY KEY,
-                    document_id TEXT NOT NULL,
-                    text TEXT NOT NULL,
-                    selected_text TEXT NOT NULL,
-                    position INTEGER NOT NULL,
-                    color TEXT,
-                    created_at INTEGER NOT NULL,
-                    updated_at INTEGER NOT NULL,
-                    FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
-                );
-                
-                CREATE INDEX IF NOT EXISTS idx_tags_document_id ON tags(document_id);
-                CREATE INDEX IF NOT EXISTS idx_tags_position ON tags(position);
-            ",
-            kind: MigrationKind::Up,
-        },
-    ]
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+/// 应用级数据库状态：一个写连接 + 一个只读连接（WAL 模式下读写互不阻塞），
+/// 通过 `tauri::Manager::manage` 注册，命令通过 `State<Database>` 访问
+pub struct Database {
+    path: RwLock<PathBuf>,
+    writer: Mutex<Connection>,
+    reader: Mutex<Connection>,
+}
+
+impl Database {
+    /// 打开数据库文件，先迁移到最新结构，再建立读连接
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let (writer, reader) = open_connections(path)?;
+        Ok(Self {
+            path: RwLock::new(path.to_path_buf()),
+            writer: Mutex::new(writer),
+            reader: Mutex::new(reader),
+        })
+    }
+
+    /// 路径只在 `replace` 成功后整体替换，锁中毒时其中的值仍然有效
+    pub fn path(&self) -> PathBuf {
+        self.path.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 错误类型由闭包决定，只要能从锁错误的 String 转换过来（如 `SearchError`）
+    pub fn read<T, E: From<String>>(&self, f: impl FnOnce(&Connection) -> Result<T, E>) -> Result<T, E> {
+        let conn = self.reader.lock().map_err(|_| "Database reader lock poisoned".to_string())?;
+        f(&conn)
+    }
+
+    pub fn write<T, E: From<String>>(&self, f: impl FnOnce(&mut Connection) -> Result<T, E>) -> Result<T, E> {
+        let mut conn = self.writer.lock().map_err(|_| "Database writer lock poisoned".to_string())?;
+        f(&mut conn)
+    }
+
+    /// 把 WAL 中的内容写回主文件，复制 .db 文件（备份、导出）前必须调用
+    pub fn checkpoint(&self) -> Result<(), String> {
+        self.write(|conn| {
+            conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")
+                .map_err(|e| format!("Failed to checkpoint database: {}", e))
+        })
+    }
+
+    /// 关闭当前连接后执行 `f`（例如用备份覆盖数据库文件），再重新打开 `path`（可以是新位置）。
+    /// 重新打开失败时回退到原来的文件
+    pub fn replace(&self, path: &Path, f: impl FnOnce() -> Result<(), String>) -> Result<(), String> {
+        let mut writer = self.writer.lock().map_err(|_| "Database writer lock poisoned".to_string())?;
+        let mut reader = self.reader.lock().map_err(|_| "Database reader lock poisoned".to_string())?;
+        let mut current_path = self.path.write().map_err(|_| "Database path lock poisoned".to_string())?;
+
+        writer.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")
+            .map_err(|e| format!("Failed to checkpoint database: {}", e))?;
+
+        // 用内存连接占位，让旧文件上的连接真正关闭
+        let placeholder = || Connection::open_in_memory()
+            .map_err(|e| format!("Failed to open placeholder connection: {}", e));
+        drop(std::mem::replace(&mut *reader, placeholder()?));
+        drop(std::mem::replace(&mut *writer, placeholder()?));
+
+        let result = f().and_then(|_| open_connections(path));
+        match result {
+            Ok((new_writer, new_reader)) => {
+                *writer = new_writer;
+                *reader = new_reader;
+                *current_path = path.to_path_buf();
+                Ok(())
+            }
+            Err(e) => {
+                let (old_writer, old_reader) = open_connections(&current_path)?;
+                *writer = old_writer;
+                *reader = old_reader;
+                Err(e)
+            }
+        }
+    }
+}
+
+fn open_connections(path: &Path) -> Result<(Connection, Connection), String> {
+    let mut writer = Connection::open(path)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    writer.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| format!("Failed to configure database: {}", e))?;
+    writer.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+        .map_err(|e| format!("Failed to configure database: {}", e))?;
+
+    crate::migrate::migrate(&mut writer)?;
+
+    let reader = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open database for reading: {}", e))?;
+    reader.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| format!("Failed to configure database: {}", e))?;
+
+    Ok((writer, reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::DocumentRepo;
+
+    #[test]
+    fn reader_sees_writes_and_replace_switches_files() {
+        let dir = std::env::temp_dir().join(format!("ai_notes_db_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.db");
+        let second = dir.join("second.db");
+
+        let db = Database::open(&first).unwrap();
+        let doc = db.write(|conn| DocumentRepo::new(conn).create("Hello", None)).unwrap();
+        assert_eq!(db.read(|conn| DocumentRepo::new(conn).get(&doc.id)).unwrap().title, "Hello");
+
+        db.replace(&second, || {
+            std::fs::copy(&first, &second).map(|_| ()).map_err(|e| e.to_string())
+        })
+        .unwrap();
+        assert_eq!(db.path(), second);
+        assert_eq!(db.read(|conn| DocumentRepo::new(conn).list_all()).unwrap().len(), 1);
+
+        drop(db);
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -0,0 +1,120 @@
+//! 历史版本的压缩存储：完整快照为 deflate 压缩的内容，
+//! 增量为相对某个完整快照的操作序列（复制快照中的一段、插入新文本），序列化为 JSON 后同样压缩。
+//! 内容按 '{' 切成片段（大致每个节点一段）再做 LCS，所以改一个段落只产生一段插入
+
+use crate::diff::{lcs, Op};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// 增量中的一步
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Step {
+    /// 复制快照的第 `.0` 个片段起共 `.1` 个片段
+    Copy(usize, usize),
+    Insert(String),
+}
+
+pub fn compress(text: &str) -> Result<Vec<u8>, String> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())
+        .map_err(|e| format!("Failed to compress: {}", e))?;
+    encoder.finish().map_err(|e| format!("Failed to compress: {}", e))
+}
+
+pub fn decompress(data: &[u8]) -> Result<String, String> {
+    let mut text = String::new();
+    DeflateDecoder::new(data)
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to decompress: {}", e))?;
+    Ok(text)
+}
+
+/// 由 `base` 得到 `target` 的压缩增量
+pub fn encode(base: &str, target: &str) -> Result<Vec<u8>, String> {
+    let base_pieces = pieces(base);
+    let target_pieces = pieces(target);
+    let mut steps: Vec<Step> = Vec::new();
+    for op in lcs(&base_pieces, &target_pieces, |a, b| a == b) {
+        match (op, steps.last_mut()) {
+            (Op::Equal(i, _), Some(Step::Copy(start, count))) if *start + *count == i => *count += 1,
+            (Op::Equal(i, _), _) => steps.push(Step::Copy(i, 1)),
+            (Op::Insert(j), Some(Step::Insert(text))) => text.push_str(target_pieces[j]),
+            (Op::Insert(j), _) => steps.push(Step::Insert(target_pieces[j].to_string())),
+            (Op::Delete(_), _) => {}
+        }
+    }
+    let json = serde_json::to_string(&steps).map_err(|e| format!("Failed to encode delta: {}", e))?;
+    compress(&json)
+}
+
+/// 把 `encode` 生成的增量应用到 `base`
+pub fn apply(base: &str, delta: &[u8]) -> Result<String, String> {
+    let base_pieces = pieces(base);
+    let steps: Vec<Step> = serde_json::from_str(&decompress(delta)?)
+        .map_err(|e| format!("Failed to decode delta: {}", e))?;
+    let mut out = String::with_capacity(base.len());
+    for step in steps {
+        match step {
+            Step::Copy(start, count) => {
+                let slice = base_pieces.get(start..start + count).ok_or("Delta does not match its base snapshot")?;
+                slice.iter().for_each(|piece| out.push_str(piece));
+            }
+            Step::Insert(text) => out.push_str(&text),
+        }
+    }
+    Ok(out)
+}
+
+/// 在每个 '{' 之前切开
+fn pieces(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for (i, _) in text.match_indices('{') {
+        if i > start {
+            out.push(&text[start..i]);
+            start = i;
+        }
+    }
+    if start < text.len() {
+        out.push(&text[start..]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_compressed_text() {
+        let text = r#"{"type":"doc","content":[{"type":"paragraph"}]}"#.repeat(20);
+        let data = compress(&text).unwrap();
+        assert!(data.len() < text.len());
+        assert_eq!(decompress(&data).unwrap(), text);
+    }
+
+    #[test]
+    fn deltas_reproduce_the_target() {
+        let paragraph = |text: &str| format!(r#"{{"type":"paragraph","content":[{{"type":"text","text":"{}"}}]}}"#, text);
+        let doc = |parts: &[String]| format!(r#"{{"type":"doc","content":[{}]}}"#, parts.join(","));
+        let body: Vec<String> = (0..200).map(|i| paragraph(&format!("paragraph number {}", i))).collect();
+        let base = doc(&body);
+
+        let mut edited = body.clone();
+        edited[50] = paragraph("changed");
+        edited.remove(120);
+        edited.push(paragraph("appended"));
+        let target = doc(&edited);
+
+        let delta = encode(&base, &target).unwrap();
+        assert_eq!(apply(&base, &delta).unwrap(), target);
+        assert!(delta.len() * 2 < compress(&target).unwrap().len(), "{} bytes", delta.len());
+
+        assert_eq!(apply("", &encode("", "plain").unwrap()).unwrap(), "plain");
+        assert!(apply("{}", &encode(&base, &target).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! 两个文档版本之间的结构化差异。
+//!
+//! 先把文档拆成块（顶层节点，列表展开成列表项），按节点是否完全相同做块级 LCS；
+//! 相邻的删除、插入中类型相同的块配对为修改，再对它们的文本做词级 LCS。
+//! CJK 字符逐字比较，其余按连续的字母数字、空白和单个标点切分
+
+use crate::models::{BlockDiff, VersionDiff, WordDiff};
+use crate::search::is_cjk;
+use crate::tiptap::{self, Node};
+
+/// LCS 表的最大格数，超过时整体视为删除再插入
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+struct Block {
+    node: Node,
+    block_type: String,
+    text: String,
+}
+
+/// 比较两份 `Document.content`；内容不是合法 JSON 时报错
+pub fn diff_contents(old: &str, new: &str) -> Result<VersionDiff, String> {
+    let old = blocks(&tiptap::parse(old)?);
+    let new = blocks(&tiptap::parse(new)?);
+
+    let mut diff = VersionDiff { blocks: Vec::new(), insertions: 0, deletions: 0, modifications: 0 };
+    let ops = lcs(&old, &new, |a, b| a.node == b.node);
+    let mut start = 0;
+    while start < ops.len() {
+        if let Op::Equal(i, j) = ops[start] {
+            diff.blocks.push(block_diff("equal", &new[j], Some(i), Some(j), Vec::new()));
+            start += 1;
+            continue;
+        }
+        let end = ops[start..].iter().position(|op| matches!(op, Op::Equal(..))).map_or(ops.len(), |n| start + n);
+        push_changes(&mut diff, &ops[start..end], &old, &new);
+        start = end;
+    }
+    Ok(diff)
+}
+
+/// 两份内容之间改动的字符数：删除和插入的块计全部文字，修改的块计删除和插入的词
+pub fn changed_chars(old: &str, new: &str) -> Result<usize, String> {
+    let diff = diff_contents(old, new)?;
+    let changed = diff.blocks.iter()
+        .map(|block| match block.kind.as_str() {
+            "insert" | "delete" => block.text.chars().count(),
+            _ => block.words.iter().filter(|w| w.kind != "equal").map(|w| w.text.chars().count()).sum(),
+        })
+        .sum();
+    Ok(changed)
+}
+
+/// 一段连续的删除和插入：类型相同的块按顺序配对为修改，其余保持删除、插入
+fn push_changes(diff: &mut VersionDiff, ops: &[Op], old: &[Block], new: &[Block]) {
+    let deletes: Vec<usize> = ops.iter().filter_map(|op| match op { Op::Delete(i) => Some(*i), _ => None }).collect();
+    let inserts: Vec<usize> = ops.iter().filter_map(|op| match op { Op::Insert(j) => Some(*j), _ => None }).collect();
+
+    // partner[d] 为与第 d 个删除配对的插入下标，配对保持两边的顺序
+    let mut partner = vec![None; deletes.len()];
+    let mut matched = vec![false; inserts.len()];
+    let mut next = 0;
+    for (d, &i) in deletes.iter().enumerate() {
+        if let Some(n) = inserts[next..].iter().position(|&j| new[j].block_type == old[i].block_type) {
+            partner[d] = Some(next + n);
+            matched[next + n] = true;
+            next += n + 1;
+        }
+    }
+
+    let (mut d, mut n) = (0, 0);
+    while d < deletes.len() || n < inserts.len() {
+        if d < deletes.len() && partner[d].is_none() {
+            let i = deletes[d];
+            diff.blocks.push(block_diff("delete", &old[i], Some(i), None, Vec::new()));
+            diff.deletions += 1;
+            d += 1;
+        } else if n < inserts.len() && !matched[n] {
+            let j = inserts[n];
+            diff.blocks.push(block_diff("insert", &new[j], None, Some(j), Vec::new()));
+            diff.insertions += 1;
+            n += 1;
+        } else {
+            let (i, j) = (deletes[d], inserts[n]);
+            let words = diff_words(&old[i].text, &new[j].text);
+            diff.blocks.push(block_diff("modify", &new[j], Some(i), Some(j), words));
+            diff.modifications += 1;
+            d += 1;
+            n += 1;
+        }
+    }
+}
+
+fn block_diff(kind: &str, block: &Block, old_index: Option<usize>, new_index: Option<usize>, words: Vec<WordDiff>) -> BlockDiff {
+    BlockDiff {
+        kind: kind.to_string(),
+        block_type: block.block_type.clone(),
+        old_index,
+        new_index,
+        text: block.text.clone(),
+        words,
+    }
+}
+
+/// 两段文本的词级差异，相邻的同类片段合并
+fn diff_words(old: &str, new: &str) -> Vec<WordDiff> {
+    let old = tokens(old);
+    let new = tokens(new);
+    let mut words: Vec<WordDiff> = Vec::new();
+    for op in lcs(&old, &new, |a, b| a == b) {
+        let (kind, text) = match op {
+            Op::Equal(_, j) => ("equal", new[j]),
+            Op::Delete(i) => ("delete", old[i]),
+            Op::Insert(j) => ("insert", new[j]),
+        };
+        match words.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(text),
+            _ => words.push(WordDiff { kind: kind.to_string(), text: text.to_string() }),
+        }
+    }
+    words
+}
+
+/// 文档的块序列：顶层节点，列表展开成列表项
+fn blocks(doc: &Node) -> Vec<Block> {
+    let mut out = Vec::new();
+    for node in doc.children() {
+        match node {
+            Node::BulletList(_) | Node::OrderedList(_) | Node::TaskList(_) => {
+                out.extend(node.children().iter().map(block));
+            }
+            _ => out.push(block(node)),
+        }
+    }
+    out
+}
+
+fn block(node: &Node) -> Block {
+    let text = tiptap::plain_text(node);
+    let text = match (text.body.is_empty(), text.ai.is_empty()) {
+        (_, true) => text.body,
+        (true, false) => text.ai,
+        (false, false) => format!("{}\n{}", text.body, text.ai),
+    };
+    Block { node: node.clone(), block_type: node_type(node), text }
+}
+
+fn node_type(node: &Node) -> String {
+    serde_json::to_value(node)
+        .ok()
+        .and_then(|value| value.get("type").and_then(|t| t.as_str()).map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// 切分为可比较的词：连续的字母数字、连续的空白、单个 CJK 字符或标点
+fn tokens(text: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Single,
+    }
+    let class = |c: char| {
+        if is_cjk(c) {
+            Class::Single
+        } else if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Single
+        }
+    };
+
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut current: Option<Class> = None;
+    for (i, c) in text.char_indices() {
+        let next = class(c);
+        let joins = current.as_ref().is_some_and(|cur| *cur == next && next != Class::Single);
+        if !joins && i > start {
+            out.push(&text[start..i]);
+            start = i;
+        }
+        current = Some(next);
+    }
+    if start < text.len() {
+        out.push(&text[start..]);
+    }
+    out
+}
+
+/// 最长公共子序列，去掉公共前后缀后再建表；表过大时中间部分整体删除再插入
+pub(crate) fn lcs<T>(a: &[T], b: &[T], eq: impl Fn(&T, &T) -> bool) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| eq(x, y)).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| eq(x, y)).count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a_mid.len(), b_mid.len());
+
+    let mut ops: Vec<Op> = (0..prefix).map(|k| Op::Equal(k, k)).collect();
+    if n * m > MAX_LCS_CELLS {
+        ops.extend((0..n).map(|i| Op::Delete(prefix + i)));
+        ops.extend((0..m).map(|j| Op::Insert(prefix + j)));
+    } else {
+        // table[i][j] 为 a_mid[i..] 与 b_mid[j..] 的 LCS 长度
+        let width = m + 1;
+        let mut table = vec![0u32; (n + 1) * width];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                table[i * width + j] = if eq(&a_mid[i], &b_mid[j]) {
+                    table[(i + 1) * width + j + 1] + 1
+                } else {
+                    table[(i + 1) * width + j].max(table[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && eq(&a_mid[i], &b_mid[j]) {
+                ops.push(Op::Equal(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || table[(i + 1) * width + j] >= table[i * width + j + 1]) {
+                ops.push(Op::Delete(prefix + i));
+                i += 1;
+            } else {
+                ops.push(Op::Insert(prefix + j));
+                j += 1;
+            }
+        }
+    }
+    ops.extend((0..suffix).map(|k| Op::Equal(a.len() - suffix + k, b.len() - suffix + k)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraph(text: &str) -> String {
+        format!(r#"{{"type":"paragraph","content":[{{"type":"text","text":"{}"}}]}}"#, text)
+    }
+
+    fn doc(blocks: &[String]) -> String {
+        format!(r#"{{"type":"doc","content":[{}]}}"#, blocks.join(","))
+    }
+
+    #[test]
+    fn classifies_block_changes() {
+        let heading = r#"{"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"Title"}]}"#.to_string();
+        let old = doc(&[heading.clone(), paragraph("keep"), paragraph("the quick fox"), paragraph("gone")]);
+        let new = doc(&[heading, paragraph("keep"), paragraph("the slow fox"), paragraph("added")]);
+
+        let diff = diff_contents(&old, &new).unwrap();
+        let kinds: Vec<_> = diff.blocks.iter().map(|b| b.kind.as_str()).collect();
+        assert_eq!(kinds, ["equal", "equal", "modify", "modify"]);
+        assert_eq!((diff.insertions, diff.deletions, diff.modifications), (0, 0, 2));
+
+        let words: Vec<_> = diff.blocks[2].words.iter().map(|w| (w.kind.as_str(), w.text.as_str())).collect();
+        assert_eq!(words, [("equal", "the "), ("delete", "quick"), ("insert", "slow"), ("equal", " fox")]);
+    }
+
+    #[test]
+    fn unmatched_types_are_inserts_and_deletes() {
+        let rule = r#"{"type":"horizontalRule"}"#.to_string();
+        let list = r#"{"type":"bulletList","content":[
+            {"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"one"}]}]},
+            {"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"two"}]}]}
+        ]}"#.to_string();
+        let old = doc(&[paragraph("a"), rule]);
+        let new = doc(&[paragraph("a"), list]);
+
+        let diff = diff_contents(&old, &new).unwrap();
+        let kinds: Vec<_> = diff.blocks.iter().map(|b| (b.kind.as_str(), b.block_type.as_str())).collect();
+        assert_eq!(kinds, [("equal", "paragraph"), ("delete", "horizontalRule"), ("insert", "listItem"), ("insert", "listItem")]);
+        assert_eq!(diff.blocks[3].text, "two");
+        assert_eq!((diff.insertions, diff.deletions, diff.modifications), (2, 1, 0));
+    }
+
+    #[test]
+    fn counts_changed_characters() {
+        let old = doc(&[paragraph("keep"), paragraph("the quick fox"), paragraph("gone")]);
+        let new = doc(&[paragraph("keep"), paragraph("the brown fox"), r#"{"type":"horizontalRule"}"#.to_string()]);
+        assert_eq!(changed_chars(&old, &new).unwrap(), "quick".len() + "brown".len() + "gone".len());
+        assert_eq!(changed_chars(&old, &old).unwrap(), 0);
+    }
+
+    #[test]
+    fn cjk_text_diffs_per_character() {
+        let words: Vec<_> = diff_words("今天天气很好", "今天天气不好")
+            .into_iter()
+            .map(|w| (w.kind, w.text))
+            .collect();
+        assert_eq!(
+            words,
+            [("equal".into(), "今天天气".into()), ("delete".into(), "很".into()), ("insert".into(), "不".into()), ("equal".into(), "好".into())]
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! 数据变更事件：修改数据的命令成功后向所有窗口广播，各个视图据此同步而不必重新拉取。
+//! 事件名为 `实体:动作`，载荷为对应变体的字段（camelCase）
+
+use crate::models::{Document, Folder, NamedSnapshot, Settings, Tag, VaultImportReport};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged, rename_all_fields = "camelCase")]
+pub enum ChangeEvent {
+    DocumentCreated { document: Document },
+    /// 内容、标题、上下文总结、置顶或重要标记变化，载荷为修改后的完整文档
+    DocumentUpdated { document: Document },
+    DocumentMoved { id: String, folder_id: Option<String> },
+    DocumentReordered { id: String, before_id: Option<String> },
+    DocumentTrashed { id: String },
+    FolderCreated { folder: Folder },
+    FolderRenamed { id: String, name: String },
+    FolderMoved { id: String, parent_id: Option<String> },
+    FolderReordered { id: String, before_id: Option<String> },
+    FolderSortModeChanged { folder_id: Option<String>, mode: String },
+    /// `mode` 同 `delete_folder`，不是 "recursive" 时其中的内容移到了别处
+    FolderTrashed { id: String, mode: String },
+    TrashRestored { id: String },
+    TrashPurged { id: String },
+    TrashEmptied { count: usize },
+    TagCreated { tag: Tag },
+    TagUpdated { id: String, text: String },
+    TagDeleted { id: String },
+    VersionSaved { document_id: String },
+    VersionLabeled { document_id: String, version_id: String, label: Option<String>, note: Option<String> },
+    SnapshotCreated { snapshot: NamedSnapshot },
+    /// 快照中的文档内容被恢复，`restored` 为内容有变化的文档数
+    SnapshotRestored { id: String, restored: usize },
+    SnapshotDeleted { id: String },
+    VaultImported { report: VaultImportReport },
+    SettingsUpdated { settings: Settings },
+    /// 恢复备份、导入数据库或切换数据库位置后整个数据库都变了
+    DatabaseReplaced { path: String },
+}
+
+impl ChangeEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeEvent::DocumentCreated { .. } => "document:created",
+            ChangeEvent::DocumentUpdated { .. } => "document:updated",
+            ChangeEvent::DocumentMoved { .. } => "document:moved",
+            ChangeEvent::DocumentReordered { .. } => "document:reordered",
+            ChangeEvent::DocumentTrashed { .. } => "document:trashed",
+            ChangeEvent::FolderCreated { .. } => "folder:created",
+            ChangeEvent::FolderRenamed { .. } => "folder:renamed",
+            ChangeEvent::FolderMoved { .. } => "folder:moved",
+            ChangeEvent::FolderReordered { .. } => "folder:reordered",
+            ChangeEvent::FolderSortModeChanged { .. } => "folder:sortModeChanged",
+            ChangeEvent::FolderTrashed { .. } => "folder:trashed",
+            ChangeEvent::TrashRestored { .. } => "trash:restored",
+            ChangeEvent::TrashPurged { .. } => "trash:purged",
+            ChangeEvent::TrashEmptied { .. } => "trash:emptied",
+            ChangeEvent::TagCreated { .. } => "tag:created",
+            ChangeEvent::TagUpdated { .. } => "tag:updated",
+            ChangeEvent::TagDeleted { .. } => "tag:deleted",
+            ChangeEvent::VersionSaved { .. } => "version:saved",
+            ChangeEvent::VersionLabeled { .. } => "version:labeled",
+            ChangeEvent::SnapshotCreated { .. } => "snapshot:created",
+            ChangeEvent::SnapshotRestored { .. } => "snapshot:restored",
+            ChangeEvent::SnapshotDeleted { .. } => "snapshot:deleted",
+            ChangeEvent::VaultImported { .. } => "vault:imported",
+            ChangeEvent::SettingsUpdated { .. } => "settings:updated",
+            ChangeEvent::DatabaseReplaced { .. } => "database:replaced",
+        }
+    }
+}
+
+/// 向所有窗口广播；发送失败只记录，修改本身已经完成
+pub fn emit(app: &AppHandle, event: ChangeEvent) {
+    let name = event.name();
+    if let Err(e) = app.emit(name, event) {
+        eprintln!("⚠️ 发送事件 {} 失败: {}", name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_are_camel_case_fields() {
+        let event = ChangeEvent::DocumentMoved { id: "doc-1".into(), folder_id: Some("folder-1".into()) };
+        assert_eq!(event.name(), "document:moved");
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "id": "doc-1", "folderId": "folder-1" })
+        );
+    }
+}
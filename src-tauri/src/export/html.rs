@@ -0,0 +1,258 @@
+use super::{code_text, is_inline_content};
+use crate::tiptap::{AiBlockAttrs, Element, ImageAttrs, Mark, Node};
+use pulldown_cmark::{Event, Options, Parser};
+
+/// 导出页面的内嵌样式，不依赖外部资源，离线也能正常显示
+const STYLE: &str = "body { margin: 0; background: #f7f7f8; color: #1f2328; font: 16px/1.7 -apple-system, BlinkMacSystemFont, \"Segoe UI\", \"PingFang SC\", \"Microsoft YaHei\", sans-serif; }
+article { max-width: 760px; margin: 40px auto; padding: 48px 56px; background: #fff; border-radius: 12px; box-shadow: 0 1px 3px rgba(0, 0, 0, 0.08); }
+h1, h2, h3, h4, h5, h6 { line-height: 1.3; margin: 1.4em 0 0.6em; }
+article > h1:first-child { margin-top: 0; }
+a { color: #2563eb; }
+a.document-link { color: #7c3aed; text-decoration: none; border-bottom: 1px dashed currentColor; }
+mark.tag { background: #fef08a; border-radius: 3px; padding: 0 2px; }
+code { font-family: \"SFMono-Regular\", Consolas, \"Liberation Mono\", monospace; font-size: 0.9em; background: #f3f4f6; border-radius: 4px; padding: 0.1em 0.3em; }
+pre { background: #1f2937; color: #f9fafb; border-radius: 8px; padding: 16px; overflow-x: auto; }
+pre code { background: none; padding: 0; color: inherit; }
+blockquote { margin: 1em 0; padding: 0.2em 1em; border-left: 4px solid #d1d5db; color: #4b5563; }
+blockquote.ai-block { border-left-color: #8b5cf6; background: #f5f3ff; border-radius: 0 8px 8px 0; color: inherit; }
+.ai-role { font-weight: 600; margin-bottom: 0.2em; }
+ul.task-list { list-style: none; padding-left: 1.2em; }
+ul.task-list input { margin-right: 0.5em; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #d1d5db; padding: 6px 12px; text-align: left; vertical-align: top; }
+th { background: #f3f4f6; }
+img { max-width: 100%; }
+hr { border: none; border-top: 1px solid #e5e7eb; margin: 2em 0; }
+";
+
+pub fn render(title: &str, doc: &Node) -> String {
+    let title = escape(title);
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"zh-CN\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title}</title>
+<style>
+{STYLE}</style>
+</head>
+<body>
+<article>
+<h1>{title}</h1>
+{}</article>
+</body>
+</html>
+",
+        render_blocks(doc.children())
+    )
+}
+
+/// 每个块单独一行
+fn render_blocks(nodes: &[Node]) -> String {
+    nodes.iter().map(render_block).collect()
+}
+
+fn render_block(node: &Node) -> String {
+    match node {
+        Node::Paragraph(e) => {
+            let inner = render_inline(e.children());
+            if inner.is_empty() { String::new() } else { format!("<p>{}</p>\n", inner) }
+        }
+        Node::Heading(e) => {
+            let level = e.attrs.as_ref().map_or(1, |a| a.level).clamp(1, 6);
+            format!("<h{level}>{}</h{level}>\n", render_inline(e.children()))
+        }
+        Node::BulletList(e) => format!("<ul>\n{}</ul>\n", render_items(e.children())),
+        Node::OrderedList(e) => match e.attrs.as_ref().map_or(1, |a| a.start) {
+            1 => format!("<ol>\n{}</ol>\n", render_items(e.children())),
+            start => format!("<ol start=\"{}\">\n{}</ol>\n", start, render_items(e.children())),
+        },
+        Node::TaskList(e) => format!("<ul class=\"task-list\">\n{}</ul>\n", render_items(e.children())),
+        Node::CodeBlock(e) => {
+            let code = escape(&code_text(node));
+            match e.attrs.as_ref().and_then(|a| a.language.as_deref()) {
+                Some(language) => format!("<pre><code class=\"language-{}\">{}</code></pre>\n", escape(language), code),
+                None => format!("<pre><code>{}</code></pre>\n", code),
+            }
+        }
+        Node::Blockquote(e) => format!("<blockquote>\n{}</blockquote>\n", render_blocks(e.children())),
+        Node::HorizontalRule(_) => "<hr>\n".to_string(),
+        Node::Image(e) => format!("<p>{}</p>\n", render_image(e)),
+        Node::Table(e) => render_table(e),
+        Node::AiBlock(e) => e.attrs.as_ref().map(render_ai_block).unwrap_or_default(),
+        Node::ContextSeparator(_) | Node::Raw(_) => String::new(),
+        Node::Text(_) | Node::HardBreak(_) => format!("<p>{}</p>\n", render_inline(std::slice::from_ref(node))),
+        other if is_inline_content(other.children()) => format!("<p>{}</p>\n", render_inline(other.children())),
+        other => render_blocks(other.children()),
+    }
+}
+
+/// 只有一个段落的列表项直接输出行内内容，和编辑器里的紧凑列表一致
+fn render_items(items: &[Node]) -> String {
+    items.iter()
+        .map(|item| {
+            let children = item.children();
+            let body = match children {
+                [Node::Paragraph(p)] => render_inline(p.children()),
+                _ => format!("\n{}", render_blocks(children)),
+            };
+            match item {
+                Node::TaskItem(task) => {
+                    let checked = task.attrs.as_ref().is_some_and(|a| a.checked);
+                    let checkbox = if checked { "<input type=\"checkbox\" disabled checked>" } else { "<input type=\"checkbox\" disabled>" };
+                    format!("<li class=\"task-item\">{}{}</li>\n", checkbox, body)
+                }
+                _ => format!("<li>{}</li>\n", body),
+            }
+        })
+        .collect()
+}
+
+fn render_table(table: &Element) -> String {
+    let mut out = String::from("<table>\n");
+    for row in table.children() {
+        out.push_str("<tr>");
+        for cell in row.children() {
+            let tag = if matches!(cell, Node::TableHeader(_)) { "th" } else { "td" };
+            let mut attrs = String::new();
+            if let Node::TableHeader(e) | Node::TableCell(e) = cell {
+                for key in ["colspan", "rowspan"] {
+                    let span = e.attrs.as_ref().and_then(|a| a.get(key)).and_then(|v| v.as_u64()).unwrap_or(1);
+                    if span > 1 {
+                        attrs.push_str(&format!(" {}=\"{}\"", key, span));
+                    }
+                }
+            }
+            let inner = match cell.children() {
+                [Node::Paragraph(p)] => render_inline(p.children()),
+                children => render_blocks(children).trim_end().to_string(),
+            };
+            out.push_str(&format!("<{tag}{attrs}>{inner}</{tag}>"));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+/// 只导出已完成或已采纳的对话
+fn render_ai_block(attrs: &AiBlockAttrs) -> String {
+    if !attrs.is_finished() {
+        return String::new();
+    }
+    let speaker = if attrs.model.is_empty() {
+        "AI".to_string()
+    } else {
+        format!("AI（{}）", escape(&attrs.model))
+    };
+    format!(
+        "<blockquote class=\"ai-block\">\n<p class=\"ai-role\">用户</p>\n{}<p class=\"ai-role\">{}</p>\n{}</blockquote>\n",
+        paragraphs(&attrs.prompt),
+        speaker,
+        markdown_to_html(&attrs.response)
+    )
+}
+
+/// AI 的回答是 Markdown；其中的原始 HTML 按文本输出，避免导出页面执行脚本
+fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let events = Parser::new_ext(markdown.trim(), options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    let mut out = String::new();
+    pulldown_cmark::html::push_html(&mut out, events);
+    out
+}
+
+/// 纯文本按空行分段，段内换行转成 <br>
+fn paragraphs(text: &str) -> String {
+    text.trim()
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| format!("<p>{}</p>\n", escape(p.trim()).replace('\n', "<br>\n")))
+        .collect()
+}
+
+fn render_image(image: &Element<ImageAttrs>) -> String {
+    let Some(attrs) = &image.attrs else {
+        return String::new();
+    };
+    let mut out = format!("<img src=\"{}\" alt=\"{}\"", escape(&safe_url(&attrs.src)), escape(attrs.alt.as_deref().unwrap_or("")));
+    if let Some(title) = &attrs.title {
+        out.push_str(&format!(" title=\"{}\"", escape(title)));
+    }
+    out.push('>');
+    out
+}
+
+fn render_inline(nodes: &[Node]) -> String {
+    nodes.iter()
+        .map(|node| match node {
+            Node::Text(text) => render_text(&text.text, node.marks()),
+            Node::HardBreak(_) => "<br>\n".to_string(),
+            Node::Image(e) => render_image(e),
+            other => render_inline(other.children()),
+        })
+        .collect()
+}
+
+/// 标记由内到外：代码、删除线、斜体、粗体，最外层是标签和链接
+fn render_text(text: &str, marks: &[Mark]) -> String {
+    let mut out = escape(text);
+    for (tag, matches) in [
+        ("code", (|m: &Mark| matches!(m, Mark::Code(_))) as fn(&Mark) -> bool),
+        ("s", |m| matches!(m, Mark::Strike(_))),
+        ("em", |m| matches!(m, Mark::Italic(_))),
+        ("strong", |m| matches!(m, Mark::Bold(_))),
+    ] {
+        if marks.iter().any(matches) {
+            out = format!("<{tag}>{out}</{tag}>");
+        }
+    }
+    for mark in marks {
+        match mark {
+            Mark::Tag(tag) => {
+                let id = tag.attrs.as_ref().and_then(|a| a.tag_id.as_deref()).unwrap_or("");
+                out = format!("<mark class=\"tag\" data-tag-id=\"{}\">{}</mark>", escape(id), out);
+            }
+            Mark::DocumentLink(link) => {
+                let id = link.attrs.as_ref().and_then(|a| a.document_id.as_deref()).unwrap_or("");
+                out = format!("<a class=\"document-link\" data-document-id=\"{}\">{}</a>", escape(id), out);
+            }
+            Mark::Link(link) => {
+                if let Some(attrs) = &link.attrs {
+                    out = format!("<a href=\"{}\">{}</a>", escape(&safe_url(&attrs.href)), out);
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// 导出的 HTML 可能在浏览器中打开，屏蔽脚本链接
+fn safe_url(url: &str) -> String {
+    let scheme = url.trim_start().to_lowercase();
+    if scheme.starts_with("javascript:") || scheme.starts_with("vbscript:") {
+        "#".to_string()
+    } else {
+        url.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,288 @@
+use super::{code_text, is_inline_content, list_item, prefix_lines};
+use crate::tiptap::{AiBlockAttrs, Element, ImageAttrs, Mark, Node};
+
+pub fn render(title: &str, doc: &Node) -> String {
+    let mut out = format!("# {}\n", escape(title));
+    let body = render_blocks(doc.children());
+    if !body.is_empty() {
+        out.push('\n');
+        out.push_str(&body);
+        out.push('\n');
+    }
+    out
+}
+
+/// 块之间空一行；空段落（编辑器里用来留白）不输出。
+/// 空行分隔、标记相同的相邻列表会被 CommonMark 合并成一个，所以相邻列表交替使用 `-`/`*`、`.`/`)`
+fn render_blocks(nodes: &[Node]) -> String {
+    let mut blocks = Vec::new();
+    let mut previous: Option<&Node> = None;
+    let mut alternate = false;
+    for node in nodes {
+        alternate = match previous {
+            Some(prev) if list_kind(prev).is_some() && list_kind(prev) == list_kind(node) => !alternate,
+            _ => false,
+        };
+        if let Some(block) = render_block_with(node, alternate) {
+            blocks.push(block);
+            previous = Some(node);
+        }
+    }
+    blocks.join("\n\n")
+}
+
+fn list_kind(node: &Node) -> Option<bool> {
+    match node {
+        Node::BulletList(_) | Node::TaskList(_) => Some(false),
+        Node::OrderedList(_) => Some(true),
+        _ => None,
+    }
+}
+
+fn render_block(node: &Node) -> Option<String> {
+    render_block_with(node, false)
+}
+
+fn render_block_with(node: &Node, alternate: bool) -> Option<String> {
+    let bullet = if alternate { "*" } else { "-" };
+    let delimiter = if alternate { ")" } else { "." };
+    let out = match node {
+        Node::Paragraph(e) => escape_line_starts(&render_inline(e.children())),
+        Node::Heading(e) => {
+            let level = e.attrs.as_ref().map_or(1, |a| a.level).clamp(1, 6) as usize;
+            format!("{} {}", "#".repeat(level), render_inline(e.children()).replace("\\\n", " "))
+        }
+        Node::BulletList(e) | Node::TaskList(e) => render_list(e.children(), |_| bullet.to_string()),
+        Node::OrderedList(e) => {
+            let start = e.attrs.as_ref().map_or(1, |a| a.start);
+            render_list(e.children(), |i| format!("{}{}", start + i as i64, delimiter))
+        }
+        Node::CodeBlock(e) => {
+            let language = e.attrs.as_ref().and_then(|a| a.language.as_deref()).unwrap_or("");
+            let code = code_text(node);
+            let fence = "`".repeat((longest_run(&code, '`') + 1).max(3));
+            format!("{fence}{language}\n{}\n{fence}", code.strip_suffix('\n').unwrap_or(&code))
+        }
+        Node::Blockquote(e) => prefix_lines(&render_blocks(e.children()), "> "),
+        Node::HorizontalRule(_) => "---".to_string(),
+        Node::Image(e) => render_image(e),
+        Node::Table(e) => render_table(e),
+        Node::AiBlock(e) => render_ai_block(e.attrs.as_ref()?)?,
+        Node::ContextSeparator(_) | Node::Raw(_) => return None,
+        Node::Text(_) | Node::HardBreak(_) => render_inline(std::slice::from_ref(node)),
+        other if is_inline_content(other.children()) => escape_line_starts(&render_inline(other.children())),
+        other => render_blocks(other.children()),
+    };
+    if out.trim().is_empty() { None } else { Some(out) }
+}
+
+/// 紧凑列表：项之间不空行，项内段落与子列表之间只换行
+fn render_list(items: &[Node], marker: impl Fn(usize) -> String) -> String {
+    items.iter().enumerate()
+        .map(|(i, item)| {
+            let mut body = String::new();
+            let blocks: Vec<(&Node, String)> = item.children().iter()
+                .filter_map(|b| render_block(b).map(|s| (b, s)))
+                .collect();
+            for (j, (block, text)) in blocks.iter().enumerate() {
+                if j > 0 {
+                    let nested_list = matches!(block, Node::BulletList(_) | Node::OrderedList(_) | Node::TaskList(_));
+                    body.push_str(if nested_list { "\n" } else { "\n\n" });
+                }
+                body.push_str(text);
+            }
+            if let Node::TaskItem(task) = item {
+                let checked = task.attrs.as_ref().is_some_and(|a| a.checked);
+                body = format!("[{}] {}", if checked { "x" } else { " " }, body);
+            }
+            list_item(&marker(i), body.trim_end())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// GFM 表格要求有表头，第一行总是作为表头；单元格内的多段落、换行用 <br> 连接
+fn render_table(table: &Element) -> String {
+    let rows: Vec<Vec<String>> = table.children().iter()
+        .map(|row| row.children().iter().map(render_cell).collect())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let line = |cells: &[String]| {
+        let padded: Vec<&str> = (0..columns).map(|i| cells.get(i).map_or("", String::as_str)).collect();
+        format!("| {} |", padded.join(" | "))
+    };
+    let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().map(|cells| line(cells)));
+    lines.join("\n")
+}
+
+fn render_cell(cell: &Node) -> String {
+    cell.children().iter()
+        .filter_map(render_block)
+        .collect::<Vec<_>>()
+        .join("<br>")
+        .replace("\\\n", "<br>")
+        .replace('\n', " ")
+        .replace('|', "\\|")
+}
+
+/// AI 对话渲染为引用块；只导出已完成或已采纳的对话
+fn render_ai_block(attrs: &AiBlockAttrs) -> Option<String> {
+    if !attrs.is_finished() {
+        return None;
+    }
+    let speaker = if attrs.model.is_empty() {
+        "**AI**".to_string()
+    } else {
+        format!("**AI**（{}）", escape(&attrs.model))
+    };
+    let prompt = escape_line_starts(&escape(attrs.prompt.trim()));
+    // 回答本身就是 Markdown，原样保留
+    let body = format!("**用户**\n\n{}\n\n{}\n\n{}", prompt, speaker, attrs.response.trim());
+    Some(prefix_lines(&body, "> "))
+}
+
+fn render_image(image: &Element<ImageAttrs>) -> String {
+    let Some(attrs) = &image.attrs else {
+        return String::new();
+    };
+    let alt = escape(attrs.alt.as_deref().unwrap_or(""));
+    match &attrs.title {
+        Some(title) => format!("![{}]({} \"{}\")", alt, link_destination(&attrs.src), title.replace('"', "\\\"")),
+        None => format!("![{}]({})", alt, link_destination(&attrs.src)),
+    }
+}
+
+/// 行内内容：相邻且标记相同的文本节点先合并，避免输出 `**a****b**`
+fn render_inline(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < nodes.len() {
+        match &nodes[i] {
+            Node::Text(first) => {
+                let marks = nodes[i].marks();
+                let mut text = first.text.clone();
+                i += 1;
+                while let Some(Node::Text(next)) = nodes.get(i) {
+                    if nodes[i].marks() != marks {
+                        break;
+                    }
+                    text.push_str(&next.text);
+                    i += 1;
+                }
+                out.push_str(&render_text(&text, marks));
+                continue;
+            }
+            Node::HardBreak(_) => out.push_str("\\\n"),
+            Node::Image(e) => out.push_str(&render_image(e)),
+            other => out.push_str(&render_inline(other.children())),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// 首尾空白放到强调符号外面，否则 CommonMark 不认为是强调
+fn render_text(text: &str, marks: &[Mark]) -> String {
+    let core = text.trim();
+    if core.is_empty() {
+        return text.to_string();
+    }
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+
+    let has = |f: fn(&Mark) -> bool| marks.iter().any(f);
+    let mut out = if has(|m| matches!(m, Mark::Code(_))) { code_span(core) } else { escape(core) };
+    if has(|m| matches!(m, Mark::Strike(_))) {
+        out = format!("~~{}~~", out);
+    }
+    if has(|m| matches!(m, Mark::Italic(_))) {
+        out = format!("*{}*", out);
+    }
+    if has(|m| matches!(m, Mark::Bold(_))) {
+        out = format!("**{}**", out);
+    }
+    for mark in marks {
+        if let Mark::Link(link) = mark {
+            if let Some(attrs) = &link.attrs {
+                out = format!("[{}]({})", out, link_destination(&attrs.href));
+            }
+        }
+    }
+    format!("{}{}{}", leading, out, trailing)
+}
+
+fn code_span(code: &str) -> String {
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    if code.starts_with('`') || code.ends_with('`') {
+        format!("{fence} {code} {fence}")
+    } else {
+        format!("{fence}{code}{fence}")
+    }
+}
+
+fn link_destination(url: &str) -> String {
+    if url.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// 转义行内有特殊含义的字符；单词内部的下划线不会触发强调，保持原样
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let escape = match c {
+            '\\' | '`' | '*' | '[' | ']' | '<' | '~' => true,
+            '_' => {
+                let word = |j: Option<usize>| j.and_then(|j| chars.get(j)).is_some_and(|c| c.is_alphanumeric());
+                !(word(i.checked_sub(1)) && word(Some(i + 1)))
+            }
+            _ => false,
+        };
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// 转义会被当成块语法的行首（标题、引用、列表、分隔线）
+fn escape_line_starts(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let indent = &line[..line.len() - trimmed.len()];
+            let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+            if trimmed.starts_with(['#', '>', '-', '+', '=']) {
+                format!("{}\\{}", indent, trimmed)
+            } else if digits > 0 && trimmed[digits..].starts_with(['.', ')']) {
+                format!("{}{}\\{}", indent, &trimmed[..digits], &trimmed[digits..])
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for ch in text.chars() {
+        if ch == c {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
@@ -0,0 +1,146 @@
+//! 单篇文档导出：Markdown（CommonMark + GFM 任务列表/表格）、带内嵌样式的独立 HTML、纯文本，
+//! 都从存储的 TipTap JSON（见 `tiptap`）生成；JSON 格式直接序列化文档记录。整个工作区的导出见 `workspace`
+
+mod html;
+mod markdown;
+mod text;
+pub mod workspace;
+
+use crate::models::Document;
+use crate::tiptap::{self, Node};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+    Html,
+    Text,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "json" => Ok(ExportFormat::Json),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "html" => Ok(ExportFormat::Html),
+            "text" | "txt" => Ok(ExportFormat::Text),
+            _ => Err(format!("Unsupported format: {}", format)),
+        }
+    }
+}
+
+pub fn export_document(document: &Document, format: ExportFormat) -> Result<String, String> {
+    if format == ExportFormat::Json {
+        return serde_json::to_string_pretty(document)
+            .map_err(|e| format!("Failed to serialize document: {}", e));
+    }
+
+    let doc = tiptap::parse(&document.content)?;
+    Ok(match format {
+        ExportFormat::Markdown => markdown::render(&document.title, &doc),
+        ExportFormat::Html => html::render(&document.title, &doc),
+        ExportFormat::Text => text::render(&document.title, &doc),
+        ExportFormat::Json => unreachable!(),
+    })
+}
+
+/// 列表项：第一行接在标记后面，后续行按标记宽度缩进（空行保持为空）
+fn list_item(marker: &str, body: &str) -> String {
+    let indent = " ".repeat(marker.chars().count() + 1);
+    let mut out = String::from(marker);
+    for (i, line) in body.lines().enumerate() {
+        if i == 0 {
+            out.push(' ');
+        } else {
+            out.push('\n');
+            if !line.is_empty() {
+                out.push_str(&indent);
+            }
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// 给每一行加前缀，空行只保留去掉尾部空格的前缀
+fn prefix_lines(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| if line.is_empty() { prefix.trim_end().to_string() } else { format!("{}{}", prefix, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 代码块中的纯文本（硬换行转成换行）
+fn code_text(node: &Node) -> String {
+    let mut code = String::new();
+    for child in node.children() {
+        match child {
+            Node::Text(text) => code.push_str(&text.text),
+            Node::HardBreak(_) => code.push('\n'),
+            other => code.push_str(&code_text(other)),
+        }
+    }
+    code
+}
+
+/// 子节点是否为行内内容（未建模的节点据此决定按段落还是按块渲染）
+fn is_inline_content(nodes: &[Node]) -> bool {
+    nodes.iter().any(|n| matches!(n, Node::Text(_) | Node::HardBreak(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn sample_document() -> Document {
+        Document {
+            id: "doc-1".into(),
+            title: "Weekly *Notes*".into(),
+            content: include_str!("testdata/document.json").into(),
+            created_at: 1767225600,
+            updated_at: 1767312000,
+            context_summary: None,
+            folder_id: None,
+            is_pinned: Some(false),
+            is_important: Some(false),
+            revision: 0,
+        }
+    }
+
+    /// 与 testdata 中的期望输出比较；设置 UPDATE_GOLDEN=1 时改为写入期望输出
+    fn assert_golden(name: &str, actual: &str) {
+        let path = Path::new(file!()).parent().unwrap().join("testdata").join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+        assert_eq!(actual, expected, "golden file {} is out of date", name);
+    }
+
+    #[test]
+    fn markdown_matches_golden() {
+        let out = export_document(&sample_document(), ExportFormat::Markdown).unwrap();
+        assert_golden("document.md", &out);
+    }
+
+    #[test]
+    fn html_matches_golden() {
+        let out = export_document(&sample_document(), ExportFormat::Html).unwrap();
+        assert_golden("document.html", &out);
+    }
+
+    #[test]
+    fn text_matches_golden() {
+        let out = export_document(&sample_document(), ExportFormat::Text).unwrap();
+        assert_golden("document.txt", &out);
+    }
+
+    #[test]
+    fn rejects_unknown_format() {
+        assert!(ExportFormat::parse("pdf").is_err());
+        assert_eq!(ExportFormat::parse("md").unwrap(), ExportFormat::Markdown);
+    }
+}
@@ -0,0 +1,83 @@
+use super::{code_text, is_inline_content, list_item, prefix_lines};
+use crate::tiptap::{AiBlockAttrs, Node};
+
+pub fn render(title: &str, doc: &Node) -> String {
+    let mut out = format!("{}\n{}\n", title, "=".repeat(title.chars().count().max(1)));
+    let body = render_blocks(doc.children());
+    if !body.is_empty() {
+        out.push('\n');
+        out.push_str(&body);
+        out.push('\n');
+    }
+    out
+}
+
+/// 块之间空一行，空段落不输出
+fn render_blocks(nodes: &[Node]) -> String {
+    nodes.iter().filter_map(render_block).collect::<Vec<_>>().join("\n\n")
+}
+
+fn render_block(node: &Node) -> Option<String> {
+    let out = match node {
+        Node::Paragraph(e) => render_inline(e.children()),
+        Node::Heading(e) => render_inline(e.children()),
+        Node::BulletList(e) => render_list(e.children(), |_| "•".to_string()),
+        Node::OrderedList(e) => {
+            let start = e.attrs.as_ref().map_or(1, |a| a.start);
+            render_list(e.children(), |i| format!("{}.", start + i as i64))
+        }
+        Node::TaskList(e) => render_list(e.children(), |_| String::new()),
+        Node::CodeBlock(_) => code_text(node).trim_end_matches('\n').to_string(),
+        Node::Blockquote(e) => prefix_lines(&render_blocks(e.children()), "> "),
+        Node::HorizontalRule(_) => "-".repeat(40),
+        Node::Image(_) => render_inline(std::slice::from_ref(node)),
+        Node::Table(e) => e.children().iter()
+            .map(|row| row.children().iter().map(|cell| render_blocks(cell.children()).replace('\n', " ")).collect::<Vec<_>>().join(" | "))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Node::AiBlock(e) => render_ai_block(e.attrs.as_ref()?)?,
+        Node::ContextSeparator(_) | Node::Raw(_) => return None,
+        Node::Text(_) | Node::HardBreak(_) => render_inline(std::slice::from_ref(node)),
+        other if is_inline_content(other.children()) => render_inline(other.children()),
+        other => render_blocks(other.children()),
+    };
+    if out.trim().is_empty() { None } else { Some(out) }
+}
+
+/// 任务项用 [x] / [ ] 作为标记，其余列表项之间不空行
+fn render_list(items: &[Node], marker: impl Fn(usize) -> String) -> String {
+    items.iter().enumerate()
+        .map(|(i, item)| {
+            let marker = match item {
+                Node::TaskItem(task) if task.attrs.as_ref().is_some_and(|a| a.checked) => "[x]".to_string(),
+                Node::TaskItem(_) => "[ ]".to_string(),
+                _ => marker(i),
+            };
+            let body = item.children().iter().filter_map(render_block).collect::<Vec<_>>().join("\n");
+            list_item(&marker, &body)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_ai_block(attrs: &AiBlockAttrs) -> Option<String> {
+    if !attrs.is_finished() {
+        return None;
+    }
+    let speaker = if attrs.model.is_empty() { "AI".to_string() } else { format!("AI（{}）", attrs.model) };
+    Some(format!("用户：{}\n{}：{}", attrs.prompt.trim(), speaker, attrs.response.trim()))
+}
+
+fn render_inline(nodes: &[Node]) -> String {
+    nodes.iter()
+        .map(|node| match node {
+            Node::Text(text) => text.text.clone(),
+            Node::HardBreak(_) => "\n".to_string(),
+            Node::Image(e) => e.attrs.as_ref()
+                .and_then(|a| a.alt.as_deref())
+                .map(|alt| format!("[图片: {}]", alt))
+                .unwrap_or_default(),
+            other => render_inline(other.children()),
+        })
+        .collect()
+}
@@ -0,0 +1,326 @@
+//! 整个工作区导出：每篇文档一个带 YAML front matter 的 Markdown 文件，目录结构与文件夹一致，
+//! 文档间链接改写成相对路径；可以直接写入目录，也可以打包成一个 zip
+
+use super::markdown;
+use crate::models::{Document, Folder, WorkspaceExportReport};
+use crate::repo::{DocumentRepo, FolderRepo, TagRepo};
+use crate::tiptap::{self, Attrs, LinkAttrs, Mark, MarkData, Node};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+
+/// 文件名最多保留的字符数（不含扩展名和去重后缀）
+const MAX_NAME_CHARS: usize = 100;
+
+/// 生成好的工作区：路径相对导出根目录，用 `/` 分隔
+pub struct Workspace {
+    dirs: Vec<String>,
+    files: Vec<(String, String)>,
+}
+
+/// 读出全部文件夹和文档，生成要写入的目录和文件
+pub fn build(conn: &Connection) -> Result<Workspace, String> {
+    let folders = FolderRepo::new(conn).list_all()?;
+    let mut documents = DocumentRepo::new(conn).list_all()?;
+    documents.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+    let mut names = UniqueNames::default();
+    let folder_paths = folder_paths(&folders, &mut names);
+    let document_paths: HashMap<&str, String> = documents.iter()
+        .map(|document| {
+            let dir = document.folder_id.as_deref().and_then(|id| folder_paths.get(id)).map_or("", String::as_str);
+            (document.id.as_str(), names.claim(dir, &document.title, ".md"))
+        })
+        .collect();
+
+    let mut dirs: Vec<String> = folder_paths.into_values().collect();
+    dirs.sort();
+    let mut files = Vec::with_capacity(documents.len());
+    for document in &documents {
+        let path = &document_paths[document.id.as_str()];
+        let dir = parent_dir(path);
+        let mut doc = tiptap::parse(&document.content)
+            .map_err(|e| format!("{} ({})", e, document.title))?;
+        rewrite_links(&mut doc, &|id| document_paths.get(id).map(|target| relative_path(dir, target)));
+
+        let tags: Vec<String> = TagRepo::new(conn).list_by_document(&document.id)?
+            .into_iter()
+            .map(|tag| tag.text)
+            .collect();
+        let markdown = format!("{}\n{}", front_matter(document, &tags), markdown::render(&document.title, &doc));
+        files.push((path.clone(), markdown));
+    }
+
+    Ok(Workspace { dirs, files })
+}
+
+impl Workspace {
+    /// 写入目录；目录已存在且不为空时拒绝，避免和已有文件混在一起
+    pub fn write_dir(&self, target: &Path) -> Result<(), String> {
+        if fs::read_dir(target).is_ok_and(|mut entries| entries.next().is_some()) {
+            return Err(format!("Export directory is not empty: {}", target.display()));
+        }
+        for dir in std::iter::once("").chain(self.dirs.iter().map(String::as_str)) {
+            fs::create_dir_all(target.join(dir))
+                .map_err(|e| format!("Failed to create directory {}: {}", target.join(dir).display(), e))?;
+        }
+        for (path, content) in &self.files {
+            fs::write(target.join(path), content)
+                .map_err(|e| format!("Failed to write {}: {}", target.join(path).display(), e))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_zip(&self, target: &Path) -> Result<(), String> {
+        let file = fs::File::create(target)
+            .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for dir in &self.dirs {
+            zip.add_directory(dir.as_str(), options)
+                .map_err(|e| format!("Failed to write zip: {}", e))?;
+        }
+        for (path, content) in &self.files {
+            zip.start_file(path.as_str(), options)
+                .map_err(|e| format!("Failed to write zip: {}", e))?;
+            zip.write_all(content.as_bytes())
+                .map_err(|e| format!("Failed to write zip: {}", e))?;
+        }
+        zip.finish().map_err(|e| format!("Failed to write zip: {}", e))?;
+        Ok(())
+    }
+
+    pub fn report(&self, target: &Path) -> WorkspaceExportReport {
+        WorkspaceExportReport {
+            path: target.to_string_lossy().into_owned(),
+            folders: self.dirs.len(),
+            documents: self.files.len(),
+        }
+    }
+}
+
+/// 文件夹 id → 目录路径；父文件夹不存在或成环时放到根目录
+fn folder_paths(folders: &[Folder], names: &mut UniqueNames) -> HashMap<String, String> {
+    let by_id: HashMap<&str, &Folder> = folders.iter().map(|f| (f.id.as_str(), f)).collect();
+    let mut paths = HashMap::new();
+    for folder in folders {
+        // 先处理祖先，保证父目录的路径已经确定
+        let mut chain = vec![folder];
+        let mut seen = HashSet::from([folder.id.as_str()]);
+        while let Some(parent) = chain.last().unwrap().parent_id.as_deref().and_then(|id| by_id.get(id)) {
+            if !seen.insert(parent.id.as_str()) {
+                break;
+            }
+            chain.push(parent);
+        }
+        for folder in chain.into_iter().rev() {
+            if paths.contains_key(&folder.id) {
+                continue;
+            }
+            let parent = folder.parent_id.as_deref().and_then(|id| paths.get(id)).map_or("", String::as_str);
+            let path = names.claim(parent, &folder.name, "");
+            paths.insert(folder.id.clone(), path);
+        }
+    }
+    paths
+}
+
+/// 同一目录下的名字不区分大小写去重："名字 (2)"、"名字 (3)"…
+#[derive(Default)]
+struct UniqueNames {
+    taken: HashSet<String>,
+}
+
+impl UniqueNames {
+    fn claim(&mut self, dir: &str, name: &str, extension: &str) -> String {
+        let base = sanitize(name);
+        let mut candidate = format!("{}{}", base, extension);
+        let mut n = 2;
+        while !self.taken.insert(join(dir, &candidate).to_lowercase()) {
+            candidate = format!("{} ({}){}", base, n, extension);
+            n += 1;
+        }
+        join(dir, &candidate)
+    }
+}
+
+/// 去掉各平台文件名中不允许的字符；首尾的点和空格在 Windows 上也有问题
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name.chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if cleaned.is_empty() { "Untitled".to_string() } else { cleaned.to_string() }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() { name.to_string() } else { format!("{}/{}", dir, name) }
+}
+
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// 从 `from_dir` 目录指向 `to` 文件的相对路径
+fn relative_path(from_dir: &str, to: &str) -> String {
+    let from: Vec<&str> = from_dir.split('/').filter(|s| !s.is_empty()).collect();
+    let to: Vec<&str> = to.split('/').collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(&to[common..]);
+    parts.join("/")
+}
+
+/// documentLink 标记换成指向目标文件的普通链接；目标已不存在时只保留文字
+fn rewrite_links(node: &mut Node, resolve: &dyn Fn(&str) -> Option<String>) {
+    if let Node::Text(text) = node {
+        let Some(marks) = &mut text.marks else {
+            return;
+        };
+        let has_link = marks.iter().any(|m| matches!(m, Mark::Link(_)));
+        let mut rewritten = Vec::with_capacity(marks.len());
+        for mark in marks.drain(..) {
+            let Mark::DocumentLink(link) = &mark else {
+                rewritten.push(mark);
+                continue;
+            };
+            let href = link.attrs.as_ref().and_then(|a| a.document_id.as_deref()).and_then(resolve);
+            if let (Some(href), false) = (href, has_link) {
+                rewritten.push(Mark::Link(MarkData::new(Some(LinkAttrs { href, extra: Attrs::new() }))));
+            }
+        }
+        *marks = rewritten;
+        return;
+    }
+    if let Some(children) = node.content_mut() {
+        for child in children {
+            rewrite_links(child, resolve);
+        }
+    }
+}
+
+/// 字符串都写成 JSON 风格的双引号字符串，本身就是合法的 YAML
+fn front_matter(document: &Document, tags: &[String]) -> String {
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let time = |secs: i64| {
+        chrono::DateTime::from_timestamp(secs, 0)
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+            .unwrap_or_default()
+    };
+    let summary = match document.context_summary.as_deref() {
+        Some(summary) if !summary.is_empty() => quote(summary),
+        _ => "null".to_string(),
+    };
+    format!(
+        "---\nid: {}\ntitle: {}\ncreated: {}\nupdated: {}\npinned: {}\nimportant: {}\ntags: [{}]\ncontext_summary: {}\n---\n",
+        quote(&document.id),
+        quote(&document.title),
+        time(document.created_at),
+        time(document.updated_at),
+        document.is_pinned.unwrap_or(false),
+        document.is_important.unwrap_or(false),
+        tags.iter().map(|t| quote(t)).collect::<Vec<_>>().join(", "),
+        summary,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::test_connection;
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    fn link_to(id: &str, text: &str) -> String {
+        format!(
+            r#"{{"type":"paragraph","content":[{{"type":"text","text":"{}","marks":[{{"type":"documentLink","attrs":{{"documentId":"{}","title":null}}}}]}}]}}"#,
+            text, id
+        )
+    }
+
+    /// 文件夹 Work/Plans，其中一篇链接到根目录的文档；根目录有两篇同名文档
+    fn sample_workspace(conn: &Connection) -> (Document, Document) {
+        let folders = FolderRepo::new(conn);
+        let work = folders.create("Work", None).unwrap();
+        let plans = folders.create("Plans: 2026", Some(&work.id)).unwrap();
+        folders.create("Empty", None).unwrap();
+
+        let docs = DocumentRepo::new(conn);
+        let root = docs.create("Inbox", None).unwrap();
+        let nested = docs.create("Roadmap", Some(&plans.id)).unwrap();
+        // 更早创建的同名文档先占用 "Inbox.md"
+        let older = docs.create("Inbox", None).unwrap();
+        docs.delete(&older.id).unwrap();
+        docs.insert(&Document { created_at: 0, ..older }).unwrap();
+        docs.update_content(&nested.id, &format!(r#"{{"type":"doc","content":[{},{}]}}"#, link_to(&root.id, "inbox"), link_to("deleted", "gone"))).unwrap();
+        docs.update_content(&root.id, &format!(r#"{{"type":"doc","content":[{}]}}"#, link_to(&nested.id, "roadmap"))).unwrap();
+        docs.update_context_summary(&nested.id, "Quarterly \"goals\"").unwrap();
+        TagRepo::new(conn).create(&nested.id, "planning", "roadmap", 1, None, None).unwrap();
+        (docs.get(&root.id).unwrap(), docs.get(&nested.id).unwrap())
+    }
+
+    fn file<'a>(workspace: &'a Workspace, path: &str) -> &'a str {
+        &workspace.files.iter().find(|(p, _)| p == path).unwrap_or_else(|| panic!("missing {}", path)).1
+    }
+
+    #[test]
+    fn mirrors_folders_and_rewrites_links() {
+        let conn = test_connection();
+        let (root, nested) = sample_workspace(&conn);
+        let workspace = build(&conn).unwrap();
+
+        assert_eq!(workspace.dirs, vec!["Empty", "Work", "Work/Plans_ 2026"]);
+        let mut paths: Vec<&str> = workspace.files.iter().map(|(p, _)| p.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["Inbox (2).md", "Inbox.md", "Work/Plans_ 2026/Roadmap.md"]);
+
+        let roadmap = file(&workspace, "Work/Plans_ 2026/Roadmap.md");
+        assert!(roadmap.starts_with(&format!("---\nid: \"{}\"\ntitle: \"Roadmap\"\n", nested.id)));
+        assert!(roadmap.contains("pinned: false\nimportant: false\ntags: [\"planning\"]\ncontext_summary: \"Quarterly \\\"goals\\\"\"\n---\n\n# Roadmap\n"));
+        assert!(roadmap.contains("[inbox](<../../Inbox (2).md>)"));
+        assert!(roadmap.contains("\ngone\n"));
+
+        let inbox = file(&workspace, "Inbox (2).md");
+        assert!(inbox.contains(&format!("id: \"{}\"", root.id)));
+        assert!(inbox.contains("[roadmap](<Work/Plans_ 2026/Roadmap.md>)"));
+        assert!(inbox.contains("tags: []\ncontext_summary: null\n"));
+    }
+
+    /// 临时路径，离开作用域时删除
+    struct TempPath(PathBuf);
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn writes_directory_and_zip() {
+        let conn = test_connection();
+        sample_workspace(&conn);
+        let workspace = build(&conn).unwrap();
+
+        let dir = TempPath(std::env::temp_dir().join(format!("workspace-{}", uuid::Uuid::new_v4())));
+        workspace.write_dir(&dir.0).unwrap();
+        assert!(dir.0.join("Empty").is_dir());
+        assert!(fs::read_to_string(dir.0.join("Work/Plans_ 2026/Roadmap.md")).unwrap().contains("# Roadmap"));
+        assert!(workspace.write_dir(&dir.0).is_err());
+
+        let zip_path = TempPath(std::env::temp_dir().join(format!("workspace-{}.zip", uuid::Uuid::new_v4())));
+        workspace.write_zip(&zip_path.0).unwrap();
+        let mut archive = zip::ZipArchive::new(fs::File::open(&zip_path.0).unwrap()).unwrap();
+        assert_eq!(archive.len(), 6);
+        let mut content = String::new();
+        archive.by_name("Work/Plans_ 2026/Roadmap.md").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, file(&workspace, "Work/Plans_ 2026/Roadmap.md"));
+
+        let report = workspace.report(&zip_path.0);
+        assert_eq!((report.folders, report.documents), (3, 3));
+    }
+}
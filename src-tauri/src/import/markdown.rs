@@ -0,0 +1,526 @@
+use crate::models::ImportWarning;
+use crate::tiptap::{
+    Attrs, CodeBlockAttrs, Element, HeadingAttrs, ImageAttrs, LinkAttrs, Mark, MarkData, Node, OrderedListAttrs,
+    TaskItemAttrs, TextNode,
+};
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd};
+use serde_json::{json, Value};
+
+/// 编辑器只启用了一到三级标题
+const MAX_HEADING_LEVEL: u8 = 3;
+
+pub struct Imported {
+    pub doc: Node,
+    pub warnings: Vec<ImportWarning>,
+}
+
+/// 解析 CommonMark + GFM（表格、任务列表、删除线），生成编辑器使用的 TipTap 文档。
+/// 开头与 `title` 相同的一级标题会被去掉（导出时会写入这一行），标题只保存在文档记录上
+pub fn parse(markdown: &str, title: &str) -> Imported {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let mut builder = Builder::new();
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        builder.line = markdown[..range.start].matches('\n').count() + 1;
+        builder.event(event);
+    }
+
+    let mut blocks = into_blocks(builder.stack.pop().map(|(_, content)| content).unwrap_or_default());
+    if let Some(Node::Heading(heading)) = blocks.first() {
+        let level = heading.attrs.as_ref().map_or(1, |a| a.level);
+        if level == 1 && inline_text(heading.children()).trim() == title.trim() {
+            blocks.remove(0);
+        }
+    }
+
+    Imported {
+        doc: Node::Doc(Element { content: Some(blocks), ..Element::new(None, Vec::new()) }),
+        warnings: builder.warnings,
+    }
+}
+
+/// 正在构建的块，和收集到的子节点放在同一层栈里
+enum Frame {
+    Doc,
+    Paragraph,
+    Heading(u8),
+    Blockquote,
+    List(Option<u64>),
+    Item { checked: Option<bool> },
+    CodeBlock(Option<String>),
+    HtmlBlock,
+    Table,
+    TableHead,
+    TableRow,
+    TableCell,
+    /// 没有对应节点的块（脚注定义、front matter），内容并入上一层或直接丢弃
+    Unsupported { keep: bool },
+}
+
+struct Builder {
+    stack: Vec<(Frame, Vec<Node>)>,
+    marks: Vec<Mark>,
+    /// 图片的替代文字在 Start/End 之间以文本事件给出
+    image: Option<ImageAttrs>,
+    line: usize,
+    warnings: Vec<ImportWarning>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            stack: vec![(Frame::Doc, Vec::new())],
+            marks: Vec::new(),
+            image: None,
+            line: 1,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, construct: &str) {
+        let warning = ImportWarning { construct: construct.to_string(), line: self.line, detail: None };
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text, self.marks.clone()),
+            Event::Code(code) => {
+                // TipTap 的行内代码标记与其他标记互斥
+                self.text(&code, vec![Mark::Code(MarkData::new(None))]);
+            }
+            Event::InlineHtml(html) if is_line_break(&html) => self.push(Node::HardBreak(Element::new(None, Vec::new()))),
+            Event::Html(html) | Event::InlineHtml(html) => {
+                if !matches!(self.top(), Frame::HtmlBlock) {
+                    self.warn("html");
+                }
+                self.text(&html, self.marks.clone());
+            }
+            Event::InlineMath(math) | Event::DisplayMath(math) => {
+                self.warn("math");
+                self.text(&math, self.marks.clone());
+            }
+            Event::FootnoteReference(label) => {
+                self.warn("footnote");
+                self.text(&format!("[^{}]", label), self.marks.clone());
+            }
+            Event::SoftBreak => self.text(" ", self.marks.clone()),
+            Event::HardBreak => self.push(Node::HardBreak(Element::new(None, Vec::new()))),
+            Event::Rule => self.push(Node::HorizontalRule(Element::new(None, Vec::new()))),
+            Event::TaskListMarker(checked) => {
+                if let Some((Frame::Item { checked: marker }, _)) =
+                    self.stack.iter_mut().rev().find(|(frame, _)| matches!(frame, Frame::Item { .. }))
+                {
+                    *marker = Some(checked);
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        let frame = match tag {
+            Tag::Paragraph => Frame::Paragraph,
+            Tag::Heading { level, .. } => {
+                let level = level as u8;
+                if level > MAX_HEADING_LEVEL {
+                    self.warn("headingLevel");
+                }
+                Frame::Heading(level.min(MAX_HEADING_LEVEL))
+            }
+            Tag::BlockQuote(_) => Frame::Blockquote,
+            Tag::CodeBlock(CodeBlockKind::Fenced(info)) => {
+                let language = info.split_whitespace().next().map(str::to_string);
+                Frame::CodeBlock(language)
+            }
+            Tag::CodeBlock(CodeBlockKind::Indented) => Frame::CodeBlock(None),
+            Tag::HtmlBlock => {
+                self.warn("html");
+                Frame::HtmlBlock
+            }
+            Tag::List(start) => Frame::List(start),
+            Tag::Item => Frame::Item { checked: None },
+            Tag::Table(alignments) => {
+                if alignments.iter().any(|a| *a != Alignment::None) {
+                    self.warn("tableAlignment");
+                }
+                Frame::Table
+            }
+            Tag::TableHead => Frame::TableHead,
+            Tag::TableRow => Frame::TableRow,
+            Tag::TableCell => Frame::TableCell,
+            Tag::Emphasis => return self.marks.push(Mark::Italic(MarkData::new(None))),
+            Tag::Strong => return self.marks.push(Mark::Bold(MarkData::new(None))),
+            Tag::Strikethrough => return self.marks.push(Mark::Strike(MarkData::new(None))),
+            Tag::Link { link_type, dest_url, .. } => {
+                let href = match link_type {
+                    LinkType::Email => format!("mailto:{}", dest_url),
+                    _ => dest_url.to_string(),
+                };
+                return self.marks.push(Mark::Link(MarkData::new(Some(LinkAttrs { href, extra: Attrs::new() }))));
+            }
+            Tag::Image { dest_url, title, .. } => {
+                self.image = Some(ImageAttrs {
+                    src: dest_url.to_string(),
+                    alt: None,
+                    title: if title.is_empty() { None } else { Some(title.to_string()) },
+                    extra: Attrs::new(),
+                });
+                return;
+            }
+            Tag::FootnoteDefinition(_) => {
+                self.warn("footnote");
+                Frame::Unsupported { keep: true }
+            }
+            Tag::MetadataBlock(_) => {
+                self.warn("frontMatter");
+                Frame::Unsupported { keep: false }
+            }
+            Tag::DefinitionList | Tag::DefinitionListTitle | Tag::DefinitionListDefinition => {
+                self.warn("definitionList");
+                Frame::Unsupported { keep: true }
+            }
+        };
+        self.stack.push((frame, Vec::new()));
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link => {
+                self.marks.pop();
+                return;
+            }
+            TagEnd::Image => {
+                if let Some(image) = self.image.take() {
+                    self.push(Node::Image(Element::new(Some(image), Vec::new())));
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        let Some((frame, content)) = self.stack.pop() else {
+            return;
+        };
+        let node = match frame {
+            Frame::Doc => return,
+            Frame::Paragraph => Node::Paragraph(Element::new(None, content)),
+            Frame::Heading(level) => Node::Heading(Element::new(Some(HeadingAttrs { level, extra: Attrs::new() }), content)),
+            Frame::Blockquote => Node::Blockquote(Element::new(None, non_empty(into_blocks(content)))),
+            Frame::List(start) => self.list(start, content),
+            Frame::Item { checked } => {
+                let mut blocks = into_blocks(content);
+                if !matches!(blocks.first(), Some(Node::Paragraph(_))) {
+                    blocks.insert(0, Node::Paragraph(Element::new(None, Vec::new())));
+                }
+                match checked {
+                    Some(checked) => Node::TaskItem(Element::new(Some(TaskItemAttrs { checked, extra: Attrs::new() }), blocks)),
+                    None => Node::ListItem(Element::new(None, blocks)),
+                }
+            }
+            Frame::CodeBlock(language) => {
+                let code = inline_text(&content);
+                let code = code.strip_suffix('\n').unwrap_or(&code);
+                let text = if code.is_empty() { Vec::new() } else { vec![Node::Text(TextNode::new(code, Vec::new()))] };
+                Node::CodeBlock(Element::new(Some(CodeBlockAttrs { language, extra: Attrs::new() }), text))
+            }
+            Frame::HtmlBlock => {
+                // 原样保留为文本，每行之间用硬换行
+                let html = inline_text(&content);
+                let mut lines = Vec::new();
+                for line in html.trim_end().lines() {
+                    if !lines.is_empty() {
+                        lines.push(Node::HardBreak(Element::new(None, Vec::new())));
+                    }
+                    if !line.is_empty() {
+                        lines.push(Node::Text(TextNode::new(line, Vec::new())));
+                    }
+                }
+                Node::Paragraph(Element::new(None, lines))
+            }
+            Frame::Table => Node::Table(Element::new(None, content)),
+            // 表头单元格直接位于 TableHead 中，没有 TableRow
+            Frame::TableHead => Node::TableRow(Element::new(
+                None,
+                content.into_iter()
+                    .map(|cell| match cell {
+                        Node::TableCell(e) => Node::TableHeader(e),
+                        other => other,
+                    })
+                    .collect(),
+            )),
+            Frame::TableRow => Node::TableRow(Element::new(None, content)),
+            Frame::TableCell => {
+                let attrs = json!({ "colspan": 1, "rowspan": 1, "colwidth": Value::Null });
+                let Value::Object(attrs) = attrs else { unreachable!() };
+                Node::TableCell(Element::new(Some(attrs), non_empty(into_blocks(content))))
+            }
+            Frame::Unsupported { keep } => {
+                if keep {
+                    for node in into_blocks(content) {
+                        self.push(node);
+                    }
+                }
+                return;
+            }
+        };
+        self.push(node);
+    }
+
+    /// 全部是任务项时生成任务列表；混合时保留普通列表，任务项的勾选状态写回文字
+    fn list(&mut self, start: Option<u64>, items: Vec<Node>) -> Node {
+        let tasks = items.iter().filter(|item| matches!(item, Node::TaskItem(_))).count();
+        if tasks > 0 && tasks == items.len() {
+            if start.is_some() {
+                self.warn("orderedTaskList");
+            }
+            return Node::TaskList(Element::new(None, items));
+        }
+        let items = if tasks > 0 {
+            self.warn("mixedTaskList");
+            items.into_iter().map(task_to_list_item).collect()
+        } else {
+            items
+        };
+        match start {
+            Some(start) => Node::OrderedList(Element::new(
+                Some(OrderedListAttrs { start: start as i64, extra: Attrs::new() }),
+                items,
+            )),
+            None => Node::BulletList(Element::new(None, items)),
+        }
+    }
+
+    fn top(&self) -> &Frame {
+        &self.stack.last().expect("document frame is never popped").0
+    }
+
+    fn text(&mut self, text: &str, marks: Vec<Mark>) {
+        if let Some(image) = &mut self.image {
+            image.alt.get_or_insert_with(String::new).push_str(text);
+            return;
+        }
+        self.push(Node::Text(TextNode::new(text, marks)));
+    }
+
+    /// 追加到当前块；与前一个文本节点标记相同时合并
+    fn push(&mut self, node: Node) {
+        let content = &mut self.stack.last_mut().expect("document frame is never popped").1;
+        if let (Some(Node::Text(last)), Node::Text(next)) = (content.last_mut(), &node) {
+            if last.marks == next.marks {
+                last.text.push_str(&next.text);
+                return;
+            }
+        }
+        content.push(node);
+    }
+}
+
+/// 紧凑列表项、表格单元格中的行内内容直接出现在块里，连续的行内节点包成段落
+fn into_blocks(content: Vec<Node>) -> Vec<Node> {
+    let mut blocks = Vec::new();
+    let mut inline = Vec::new();
+    for node in content {
+        if matches!(node, Node::Text(_) | Node::HardBreak(_) | Node::Image(_)) {
+            inline.push(node);
+            continue;
+        }
+        if !inline.is_empty() {
+            blocks.push(Node::Paragraph(Element::new(None, std::mem::take(&mut inline))));
+        }
+        blocks.push(node);
+    }
+    if !inline.is_empty() {
+        blocks.push(Node::Paragraph(Element::new(None, inline)));
+    }
+    blocks
+}
+
+/// 引用、单元格至少要有一个段落
+fn non_empty(blocks: Vec<Node>) -> Vec<Node> {
+    if blocks.is_empty() {
+        vec![Node::Paragraph(Element::new(None, Vec::new()))]
+    } else {
+        blocks
+    }
+}
+
+fn task_to_list_item(item: Node) -> Node {
+    let Node::TaskItem(task) = item else {
+        return item;
+    };
+    let checked = task.attrs.as_ref().is_some_and(|a| a.checked);
+    let mut blocks = task.content.unwrap_or_default();
+    if let Some(Node::Paragraph(paragraph)) = blocks.first_mut() {
+        let marker = if checked { "[x] " } else { "[ ] " };
+        let content = paragraph.content.get_or_insert_with(Vec::new);
+        match content.first_mut() {
+            Some(Node::Text(text)) if text.marks.is_none() => text.text.insert_str(0, marker),
+            _ => content.insert(0, Node::Text(TextNode::new(marker, Vec::new()))),
+        }
+    }
+    Node::ListItem(Element::new(None, blocks))
+}
+
+fn inline_text(nodes: &[Node]) -> String {
+    nodes.iter()
+        .map(|node| match node {
+            Node::Text(text) => text.text.clone(),
+            other => inline_text(other.children()),
+        })
+        .collect()
+}
+
+fn is_line_break(html: &str) -> bool {
+    let tag = html.trim().to_ascii_lowercase();
+    matches!(tag.as_str(), "<br>" | "<br/>" | "<br />")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{export_document, ExportFormat};
+    use crate::models::Document;
+
+    fn doc_json(markdown: &str) -> Value {
+        serde_json::to_value(parse(markdown, "").doc).unwrap()
+    }
+
+    fn constructs(markdown: &str) -> Vec<String> {
+        parse(markdown, "").warnings.into_iter().map(|w| w.construct).collect()
+    }
+
+    #[test]
+    fn maps_blocks_and_marks() {
+        let doc = doc_json("## Plan\n\nShip **search** and *export*, ~~later~~ `code` [docs](https://example.com)\n\n---\n");
+        assert_eq!(doc, json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "attrs": { "level": 2 }, "content": [{ "type": "text", "text": "Plan" }] },
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "Ship " },
+                    { "type": "text", "text": "search", "marks": [{ "type": "bold" }] },
+                    { "type": "text", "text": " and " },
+                    { "type": "text", "text": "export", "marks": [{ "type": "italic" }] },
+                    { "type": "text", "text": ", " },
+                    { "type": "text", "text": "later", "marks": [{ "type": "strike" }] },
+                    { "type": "text", "text": " " },
+                    { "type": "text", "text": "code", "marks": [{ "type": "code" }] },
+                    { "type": "text", "text": " " },
+                    { "type": "text", "text": "docs", "marks": [{ "type": "link", "attrs": { "href": "https://example.com" } }] }
+                ] },
+                { "type": "horizontalRule" }
+            ]
+        }));
+    }
+
+    #[test]
+    fn maps_lists_and_task_lists() {
+        let doc = doc_json("- [x] done\n- [ ] todo\n  - nested\n\n3. three\n4. four\n");
+        assert_eq!(doc, json!({
+            "type": "doc",
+            "content": [
+                { "type": "taskList", "content": [
+                    { "type": "taskItem", "attrs": { "checked": true }, "content": [
+                        { "type": "paragraph", "content": [{ "type": "text", "text": "done" }] }
+                    ] },
+                    { "type": "taskItem", "attrs": { "checked": false }, "content": [
+                        { "type": "paragraph", "content": [{ "type": "text", "text": "todo" }] },
+                        { "type": "bulletList", "content": [
+                            { "type": "listItem", "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "nested" }] }] }
+                        ] }
+                    ] }
+                ] },
+                { "type": "orderedList", "attrs": { "start": 3 }, "content": [
+                    { "type": "listItem", "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "three" }] }] },
+                    { "type": "listItem", "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "four" }] }] }
+                ] }
+            ]
+        }));
+    }
+
+    #[test]
+    fn maps_code_blocks_and_tables() {
+        let doc = doc_json("```rust\nfn main() {}\n```\n\n| A | B |\n| --- | --- |\n| 1 | x<br>y |\n");
+        let cell = |kind: &str, content: Value| json!({
+            "type": kind,
+            "attrs": { "colspan": 1, "rowspan": 1, "colwidth": null },
+            "content": [{ "type": "paragraph", "content": content }]
+        });
+        assert_eq!(doc, json!({
+            "type": "doc",
+            "content": [
+                { "type": "codeBlock", "attrs": { "language": "rust" }, "content": [{ "type": "text", "text": "fn main() {}" }] },
+                { "type": "table", "content": [
+                    { "type": "tableRow", "content": [
+                        cell("tableHeader", json!([{ "type": "text", "text": "A" }])),
+                        cell("tableHeader", json!([{ "type": "text", "text": "B" }]))
+                    ] },
+                    { "type": "tableRow", "content": [
+                        cell("tableCell", json!([{ "type": "text", "text": "1" }])),
+                        cell("tableCell", json!([{ "type": "text", "text": "x" }, { "type": "hardBreak" }, { "type": "text", "text": "y" }]))
+                    ] }
+                ] }
+            ]
+        }));
+    }
+
+    #[test]
+    fn reports_unmapped_constructs_with_lines() {
+        let imported = parse("---\ntags: [a]\n---\n\n#### Deep\n\n<div>raw</div>\n\n| A |\n| :-: |\n| 1 |\n\n1. [ ] ordered\n\n- [x] task\n- plain\n", "");
+        let warnings: Vec<(&str, usize)> = imported.warnings.iter().map(|w| (w.construct.as_str(), w.line)).collect();
+        assert_eq!(warnings, vec![
+            ("frontMatter", 1),
+            ("headingLevel", 5),
+            ("html", 7),
+            ("tableAlignment", 9),
+            ("orderedTaskList", 13),
+            ("mixedTaskList", 15),
+        ]);
+
+        let doc = serde_json::to_value(&imported.doc).unwrap();
+        assert_eq!(doc["content"][0]["attrs"]["level"], 3);
+        assert_eq!(doc["content"][1]["content"][0]["text"], "<div>raw</div>");
+        assert_eq!(doc["content"][4]["type"], "bulletList");
+        assert_eq!(doc["content"][4]["content"][0]["content"][0]["content"][0]["text"], "[x] task");
+    }
+
+    #[test]
+    fn reports_footnotes_and_keeps_their_text() {
+        let imported = parse("Claim[^1].\n\n[^1]: Source.\n", "");
+        let warnings: Vec<(&str, usize)> = imported.warnings.iter().map(|w| (w.construct.as_str(), w.line)).collect();
+        assert_eq!(warnings, vec![("footnote", 1), ("footnote", 3)]);
+        let text = crate::tiptap::plain_text(&imported.doc).body;
+        assert!(text.contains("Claim[^1]."));
+        assert!(text.contains("Source."));
+        assert!(constructs("plain *text*").is_empty());
+    }
+
+    #[test]
+    fn round_trips_exported_markdown() {
+        let original = Document {
+            id: "doc-1".into(),
+            title: "Weekly *Notes*".into(),
+            content: include_str!("../export/testdata/document.json").into(),
+            created_at: 0,
+            updated_at: 0,
+            context_summary: None,
+            folder_id: None,
+            is_pinned: None,
+            is_important: None,
+            revision: 0,
+        };
+        let markdown = export_document(&original, ExportFormat::Markdown).unwrap();
+        let imported = parse(&markdown, &original.title);
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+
+        let reimported = Document { content: serde_json::to_string(&imported.doc).unwrap(), ..original };
+        assert_eq!(export_document(&reimported, ExportFormat::Markdown).unwrap(), markdown);
+    }
+}
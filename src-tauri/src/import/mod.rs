@@ -0,0 +1,45 @@
+//! 导入：Markdown（CommonMark + GFM）转换成编辑器的 TipTap JSON 后创建文档，
+//! 无法映射的内容按原文保留并在结果中列出。整个目录的导入见 `vault`
+
+pub mod markdown;
+pub mod vault;
+
+use crate::models::ImportResult;
+use crate::repo::{DocumentRepo, FolderRepo};
+use rusqlite::Connection;
+
+/// 在 `folder_id` 指定的文件夹（None 为根目录）中创建文档
+pub fn import_markdown(conn: &Connection, title: &str, source: &str, folder_id: Option<&str>) -> Result<ImportResult, String> {
+    if let Some(folder_id) = folder_id {
+        FolderRepo::new(conn).get(folder_id)?;
+    }
+    let imported = markdown::parse(source, title);
+    let content = serde_json::to_string(&imported.doc)
+        .map_err(|e| format!("Failed to serialize document content: {}", e))?;
+    let document = DocumentRepo::new(conn).create_with_content(title, folder_id, &content)?;
+    Ok(ImportResult { document, warnings: imported.warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::test_connection;
+    use crate::search;
+
+    #[test]
+    fn creates_indexed_document_in_folder() {
+        let conn = test_connection();
+        let folder = FolderRepo::new(&conn).create("Notes", None).unwrap();
+
+        let result = import_markdown(&conn, "Plan", "# Plan\n\nShip the **importer**\n\n<br>\n", Some(&folder.id)).unwrap();
+        assert_eq!(result.document.folder_id.as_deref(), Some(folder.id.as_str()));
+        assert_eq!(result.warnings.len(), 1);
+
+        let stored = DocumentRepo::new(&conn).get(&result.document.id).unwrap();
+        assert_eq!(stored.title, "Plan");
+        assert!(!stored.content.contains("heading"));
+        assert_eq!(search::search(&conn, "importer", 10).unwrap().len(), 1);
+
+        assert!(import_markdown(&conn, "Orphan", "text", Some("missing")).is_err());
+    }
+}
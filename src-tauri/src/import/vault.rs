@@ -0,0 +1,499 @@
+//! Obsidian 仓库（Markdown 目录）批量导入：目录结构变成文件夹，`[[wikilink]]` 变成指向新文档的
+//! documentLink 标记，`#标签` 变成 tags 记录和 tagMark，文件时间保留为文档的创建、修改时间
+
+use super::markdown;
+use crate::models::{Document, ImportWarning, Tag, VaultDocumentReport, VaultImportReport};
+use crate::repo::{DocumentRepo, FolderRepo, TagRepo};
+use crate::tiptap::{Attrs, DocumentLinkAttrs, Mark, MarkData, Node, TagMarkAttrs, TextNode};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+struct VaultFolder {
+    name: String,
+    /// 在 `Vault::folders` 中的下标；仓库根目录为 None
+    parent: Option<usize>,
+}
+
+/// 仓库中的一篇笔记；id 在写入前分配，其他笔记的链接直接指向它
+struct Note {
+    path: PathBuf,
+    folder: usize,
+    title: String,
+    id: String,
+    created_at: i64,
+    updated_at: i64,
+}
+
+struct Vault {
+    root: PathBuf,
+    folders: Vec<VaultFolder>,
+    notes: Vec<Note>,
+    skipped: Vec<String>,
+    /// 小写文件名 → 笔记下标，用于解析只写了文件名的链接
+    by_title: HashMap<String, Vec<usize>>,
+}
+
+/// 导入 `root` 目录；仓库根目录本身成为 `folder_id` 下的一个文件夹
+pub fn import_vault(conn: &Connection, root: &Path, folder_id: Option<&str>, dry_run: bool) -> Result<VaultImportReport, String> {
+    if let Some(folder_id) = folder_id {
+        FolderRepo::new(conn).get(folder_id)?;
+    }
+    let mut vault = scan(root)?;
+
+    let mut folder_ids: Vec<String> = Vec::new();
+    if !dry_run {
+        for folder in &vault.folders {
+            let parent = folder.parent.map_or(folder_id, |i| Some(folder_ids[i].as_str()));
+            folder_ids.push(FolderRepo::new(conn).create(&folder.name, parent)?.id);
+        }
+    }
+
+    let mut documents = Vec::new();
+    for (index, note) in vault.notes.iter().enumerate() {
+        let Ok(source) = fs::read_to_string(vault.root.join(&note.path)) else {
+            vault.skipped.push(display_path(&note.path));
+            continue;
+        };
+        let converted = convert(&vault, index, &source);
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &converted.tags {
+            if !tags.contains(&tag.text) {
+                tags.push(tag.text.clone());
+            }
+        }
+        documents.push(VaultDocumentReport {
+            path: display_path(&note.path),
+            title: note.title.clone(),
+            document_id: (!dry_run).then(|| note.id.clone()),
+            links: converted.links,
+            tags,
+            warnings: converted.warnings,
+        });
+        if dry_run {
+            continue;
+        }
+
+        let content = serde_json::to_string(&converted.doc)
+            .map_err(|e| format!("Failed to serialize document content: {}", e))?;
+        DocumentRepo::new(conn).insert(&Document {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            content,
+            created_at: note.created_at,
+            updated_at: note.updated_at,
+            context_summary: None,
+            folder_id: Some(folder_ids[note.folder].clone()),
+            is_pinned: Some(false),
+            is_important: Some(false),
+            revision: 0,
+        })?;
+        for tag in &converted.tags {
+            TagRepo::new(conn).insert(tag)?;
+        }
+    }
+
+    Ok(VaultImportReport {
+        dry_run,
+        folders: vault.folders.len(),
+        documents,
+        skipped: vault.skipped,
+    })
+}
+
+fn scan(root: &Path) -> Result<Vault, String> {
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+    let name = root.file_name().map_or_else(|| root.display().to_string(), |n| n.to_string_lossy().into_owned());
+    let mut vault = Vault {
+        root: root.to_path_buf(),
+        folders: vec![VaultFolder { name, parent: None }],
+        notes: Vec::new(),
+        skipped: Vec::new(),
+        by_title: HashMap::new(),
+    };
+    walk(&mut vault, Path::new(""), 0)?;
+    for (i, note) in vault.notes.iter().enumerate() {
+        vault.by_title.entry(note.title.to_lowercase()).or_default().push(i);
+    }
+    Ok(vault)
+}
+
+/// 按文件名排序遍历，跳过隐藏文件和目录（.obsidian、.trash 等）；符号链接不跟随
+fn walk(vault: &mut Vault, dir: &Path, folder: usize) -> Result<(), String> {
+    let mut entries = fs::read_dir(vault.root.join(dir))
+        .map_err(|e| format!("Failed to read directory {}: {}", vault.root.join(dir).display(), e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read directory {}: {}", vault.root.join(dir).display(), e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = dir.join(&name);
+        let file_type = entry.file_type()
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if file_type.is_dir() {
+            vault.folders.push(VaultFolder { name, parent: Some(folder) });
+            let index = vault.folders.len() - 1;
+            walk(vault, &path, index)?;
+            continue;
+        }
+        let is_markdown = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+        let metadata = entry.metadata().ok().filter(|_| file_type.is_file() && is_markdown);
+        let Some(metadata) = metadata else {
+            vault.skipped.push(display_path(&path));
+            continue;
+        };
+        let updated_at = metadata.modified().map_or_else(|_| chrono::Utc::now().timestamp(), unix_seconds);
+        // 拿不到创建时间的文件系统退回修改时间
+        let created_at = metadata.created().map_or(updated_at, unix_seconds).min(updated_at);
+        vault.notes.push(Note {
+            title: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            path,
+            folder,
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at,
+            updated_at,
+        });
+    }
+    Ok(())
+}
+
+struct Converted {
+    doc: Node,
+    tags: Vec<Tag>,
+    links: usize,
+    warnings: Vec<ImportWarning>,
+}
+
+fn convert(vault: &Vault, index: usize, source: &str) -> Converted {
+    let note = &vault.notes[index];
+    let imported = markdown::parse(source, &note.title);
+    let mut converter = Converter { vault, index, source, tags: Vec::new(), links: 0, warnings: imported.warnings };
+    let mut doc = imported.doc;
+    converter.rewrite(&mut doc);
+
+    let mut positions = HashMap::new();
+    let mut pos = 0;
+    for child in doc.children() {
+        tag_positions(child, &mut pos, &mut positions);
+    }
+    for tag in &mut converter.tags {
+        tag.position = positions.get(&tag.id).copied().unwrap_or(0) as i32;
+    }
+
+    Converted { doc, tags: converter.tags, links: converter.links, warnings: converter.warnings }
+}
+
+struct Converter<'a> {
+    vault: &'a Vault,
+    index: usize,
+    source: &'a str,
+    tags: Vec<Tag>,
+    links: usize,
+    warnings: Vec<ImportWarning>,
+}
+
+impl Converter<'_> {
+    /// 代码块、行内代码和已有链接里的文字保持原样
+    fn rewrite(&mut self, node: &mut Node) {
+        if matches!(node, Node::CodeBlock(_)) {
+            return;
+        }
+        let Some(content) = node.content_mut() else {
+            return;
+        };
+        let mut rewritten = Vec::with_capacity(content.len());
+        for mut child in content.drain(..) {
+            match child {
+                Node::Text(text) if !text.marks.iter().flatten().any(|m| matches!(m, Mark::Code(_) | Mark::Link(_) | Mark::DocumentLink(_))) => {
+                    rewritten.extend(self.split(text));
+                }
+                _ => {
+                    self.rewrite(&mut child);
+                    rewritten.push(child);
+                }
+            }
+        }
+        *content = rewritten;
+    }
+
+    /// 把文本中的 `[[链接]]` 和 `#标签` 拆成单独的带标记文本节点
+    fn split(&mut self, text: TextNode) -> Vec<Node> {
+        let source = text.text.as_str();
+        let marks = text.marks.clone().unwrap_or_default();
+        let mut nodes = Vec::new();
+        let mut plain = 0;
+        let mut i = 0;
+        while i < source.len() {
+            let rest = &source[i..];
+            let found = if rest.starts_with("[[") {
+                self.wikilink(rest, source[..i].ends_with('!'), &marks)
+            } else if rest.starts_with('#') && source[..i].chars().next_back().is_none_or(char::is_whitespace) {
+                self.hashtag(rest, &marks)
+            } else {
+                None
+            };
+            match found {
+                Some((len, Some(node))) => {
+                    if plain < i {
+                        nodes.push(Node::Text(TextNode::new(&source[plain..i], marks.clone())));
+                    }
+                    nodes.push(node);
+                    i += len;
+                    plain = i;
+                }
+                Some((len, None)) => i += len,
+                None => i += rest.chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        if plain == 0 {
+            return vec![Node::Text(text)];
+        }
+        if plain < source.len() {
+            nodes.push(Node::Text(TextNode::new(&source[plain..], marks)));
+        }
+        nodes
+    }
+
+    /// 返回匹配长度和替换节点；嵌入（`![[...]]`）和找不到目标的链接保留原文并记录
+    fn wikilink(&mut self, rest: &str, embed: bool, marks: &[Mark]) -> Option<(usize, Option<Node>)> {
+        let end = rest[2..].find("]]")?;
+        let inner = &rest[2..2 + end];
+        if inner.is_empty() || inner.contains(['[', ']', '\n']) {
+            return None;
+        }
+        let raw = &rest[..end + 4];
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target, alias),
+            None => (inner, inner),
+        };
+        if embed {
+            self.warn("embed", raw, target);
+            return Some((raw.len(), None));
+        }
+        let Some(note) = self.resolve(target) else {
+            self.warn("unresolvedLink", raw, target);
+            return Some((raw.len(), None));
+        };
+
+        let mut marks = marks.to_vec();
+        marks.push(Mark::DocumentLink(MarkData::new(Some(DocumentLinkAttrs {
+            document_id: Some(note.id.clone()),
+            title: Some(note.title.clone()),
+            extra: Attrs::new(),
+        }))));
+        self.links += 1;
+        Some((raw.len(), Some(Node::Text(TextNode::new(alias.trim(), marks)))))
+    }
+
+    /// Obsidian 标签：字母、数字、`_`、`-`、`/`，不能全是数字（`#1` 不是标签）
+    fn hashtag(&mut self, rest: &str, marks: &[Mark]) -> Option<(usize, Option<Node>)> {
+        let name: String = rest[1..].chars().take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/')).collect();
+        if name.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let note = &self.vault.notes[self.index];
+        let now = chrono::Utc::now().timestamp_millis();
+        let tag = Tag {
+            id: uuid::Uuid::new_v4().to_string(),
+            document_id: note.id.clone(),
+            text: name.clone(),
+            selected_text: format!("#{}", name),
+            position: 0,
+            ai_block_id: None,
+            color: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let mut marks = marks.to_vec();
+        marks.push(Mark::Tag(MarkData::new(Some(TagMarkAttrs { tag_id: Some(tag.id.clone()), extra: Attrs::new() }))));
+        let node = Node::Text(TextNode::new(&tag.selected_text, marks));
+        self.tags.push(tag);
+        Some((name.len() + 1, Some(node)))
+    }
+
+    /// 与 Obsidian 一致：`#标题`、`^块` 部分忽略；带路径的按路径（可省略前面的目录）匹配，
+    /// 只有文件名的优先同一文件夹，其次路径最短的
+    fn resolve(&self, target: &str) -> Option<&Note> {
+        let target = target.split('#').next().unwrap_or("").trim().replace('\\', "/");
+        let target = target.strip_suffix(".md").unwrap_or(&target).to_lowercase();
+        let current = &self.vault.notes[self.index];
+        if target.is_empty() {
+            return Some(current);
+        }
+
+        let candidates: Vec<&Note> = if target.contains('/') {
+            self.vault.notes.iter()
+                .filter(|note| {
+                    let path = display_path(&note.path.with_extension("")).to_lowercase();
+                    path == target || path.ends_with(&format!("/{}", target))
+                })
+                .collect()
+        } else {
+            self.vault.by_title.get(&target)
+                .map(|indices| indices.iter().map(|&i| &self.vault.notes[i]).collect())
+                .unwrap_or_default()
+        };
+        candidates.iter()
+            .find(|note| note.folder == current.folder)
+            .or_else(|| candidates.iter().min_by_key(|note| note.path.components().count()))
+            .copied()
+    }
+
+    fn warn(&mut self, construct: &str, raw: &str, target: &str) {
+        let line = self.source.find(raw).map_or(1, |offset| self.source[..offset].matches('\n').count() + 1);
+        let warning = ImportWarning { construct: construct.to_string(), line, detail: Some(target.trim().to_string()) };
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+}
+
+/// ProseMirror 位置：进入、离开非叶子节点各占 1，文本按 UTF-16 长度，叶子节点占 1
+fn tag_positions(node: &Node, pos: &mut usize, positions: &mut HashMap<String, usize>) {
+    match node {
+        Node::Text(text) => {
+            for mark in node.marks() {
+                if let Mark::Tag(MarkData { attrs: Some(TagMarkAttrs { tag_id: Some(id), .. }), .. }) = mark {
+                    positions.entry(id.clone()).or_insert(*pos);
+                }
+            }
+            *pos += text.text.encode_utf16().count();
+        }
+        Node::HardBreak(_) | Node::Image(_) | Node::HorizontalRule(_) | Node::AiBlock(_) | Node::ContextSeparator(_) | Node::Raw(_) => {
+            *pos += 1;
+        }
+        _ => {
+            *pos += 1;
+            for child in node.children() {
+                tag_positions(child, pos, positions);
+            }
+            *pos += 1;
+        }
+    }
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::test_connection;
+    use std::fs::File;
+    use std::time::Duration;
+
+    /// 临时目录中的测试仓库，离开作用域时删除
+    struct TempVault(PathBuf);
+
+    impl TempVault {
+        fn new(files: &[(&str, &str)]) -> Self {
+            let root = std::env::temp_dir().join(format!("vault-{}", uuid::Uuid::new_v4())).join("Notes");
+            for (path, content) in files {
+                let path = root.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, content).unwrap();
+            }
+            Self(root)
+        }
+    }
+
+    impl Drop for TempVault {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.0.parent().unwrap());
+        }
+    }
+
+    fn sample_vault() -> TempVault {
+        let vault = TempVault::new(&[
+            ("Projects/Alpha.md", "# Alpha\n\nSee [[Beta|the beta]] and [[Projects/Gamma#Goals]], not [[Missing]].\n\n#project/alpha #1 `#code`\n"),
+            ("Projects/Gamma.md", "## Goals\n\n![[diagram.png]]\n"),
+            ("Beta.md", "Back to [[alpha]].\n"),
+            ("Projects/diagram.png", "png"),
+            (".obsidian/app.json", "{}"),
+            ("Empty/.keep", ""),
+        ]);
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        File::options().write(true).open(vault.0.join("Beta.md")).unwrap().set_modified(mtime).unwrap();
+        vault
+    }
+
+    #[test]
+    fn dry_run_reports_without_writing() {
+        let vault = sample_vault();
+        let conn = test_connection();
+        let report = import_vault(&conn, &vault.0, None, true).unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.folders, 3);
+        assert_eq!(report.skipped, vec!["Projects/diagram.png"]);
+        let paths: Vec<&str> = report.documents.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["Beta.md", "Projects/Alpha.md", "Projects/Gamma.md"]);
+
+        let alpha = &report.documents[1];
+        assert_eq!(alpha.document_id, None);
+        assert_eq!(alpha.links, 2);
+        assert_eq!(alpha.tags, vec!["project/alpha"]);
+        assert_eq!(alpha.warnings, vec![ImportWarning { construct: "unresolvedLink".into(), line: 3, detail: Some("Missing".into()) }]);
+        assert_eq!(report.documents[2].warnings[0].construct, "embed");
+
+        assert!(DocumentRepo::new(&conn).list_all().unwrap().is_empty());
+        assert!(FolderRepo::new(&conn).list_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn imports_folders_links_tags_and_times() {
+        let vault = sample_vault();
+        let conn = test_connection();
+        let parent = FolderRepo::new(&conn).create("Imported", None).unwrap();
+        let report = import_vault(&conn, &vault.0, Some(&parent.id), false).unwrap();
+
+        let folders = FolderRepo::new(&conn).list_all().unwrap();
+        let folder = |name: &str| folders.iter().find(|f| f.name == name).unwrap();
+        assert_eq!(folder("Notes").parent_id.as_deref(), Some(parent.id.as_str()));
+        assert_eq!(folder("Projects").parent_id.as_deref(), Some(folder("Notes").id.as_str()));
+        assert_eq!(folder("Empty").parent_id.as_deref(), Some(folder("Notes").id.as_str()));
+
+        let id = |title: &str| report.documents.iter().find(|d| d.title == title).unwrap().document_id.clone().unwrap();
+        let docs = DocumentRepo::new(&conn);
+        let beta = docs.get(&id("Beta")).unwrap();
+        assert_eq!(beta.folder_id.as_deref(), Some(folder("Notes").id.as_str()));
+        assert_eq!((beta.created_at, beta.updated_at), (1_700_000_000, 1_700_000_000));
+        assert!(beta.content.contains(&format!(r#""documentId":"{}""#, id("Alpha"))));
+
+        let alpha = docs.get(&id("Alpha")).unwrap();
+        let content: serde_json::Value = serde_json::from_str(&alpha.content).unwrap();
+        let paragraph = &content["content"][0]["content"];
+        assert_eq!(paragraph[1]["text"], "the beta");
+        assert_eq!(paragraph[1]["marks"][0]["attrs"]["documentId"], id("Beta").as_str());
+        assert_eq!(paragraph[3]["text"], "Projects/Gamma#Goals");
+        assert_eq!(paragraph[3]["marks"][0]["attrs"]["documentId"], id("Gamma").as_str());
+        assert_eq!(paragraph[4]["text"], ", not [[Missing]].");
+
+        let tags = TagRepo::new(&conn).list_by_document(&alpha.id).unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!((tags[0].text.as_str(), tags[0].selected_text.as_str()), ("project/alpha", "#project/alpha"));
+        // 第一段 "See the beta and Projects/Gamma#Goals, not [[Missing]]." 占 2 + 55，第二段从 57 开始，文字从 58 开始
+        assert_eq!(tags[0].position, 58);
+        let tagged = &content["content"][1]["content"][0];
+        assert_eq!(tagged["marks"][0]["attrs"]["tagId"], tags[0].id.as_str());
+    }
+
+    #[test]
+    fn rejects_missing_directory() {
+        let conn = test_connection();
+        assert!(import_vault(&conn, Path::new("/nonexistent/vault"), None, true).is_err());
+    }
+}
@@ -1,5 +1,16 @@
 mod models;
 mod db;
+mod repo;
+mod search;
+mod links;
+mod ordering;
+mod diff;
+mod events;
+mod merge;
+mod delta;
+mod tiptap;
+mod export;
+mod import;
 mod commands;
 mod migrate;
 mod voice_service;
@@ -24,21 +35,26 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .plugin(
-            tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:ai_notes.db", db::get_migrations())
-                .build(),
-        )
         .setup(|app| {
-            // 运行数据库迁移
-            match commands::get_db_path(&app.handle()) {
-                Ok(db_path) => {
-                    if let Err(e) = migrate::run_migrations(&db_path) {
-                        eprintln!("❌ 数据库迁移失败: {}", e);
+            // 打开数据库并运行迁移，数据库版本高于应用时拒绝启动，避免写坏数据
+            let db_path = commands::get_db_path(app.handle())?;
+            match db::Database::open(&db_path) {
+                Ok(database) => {
+                    match commands::purge_expired_trash(app.handle(), &database) {
+                        Ok(0) => {}
+                        Ok(purged) => println!("🗑️ 已永久删除 {} 个过期的回收站条目", purged),
+                        Err(e) => eprintln!("⚠️ 清理回收站失败: {}", e),
                     }
+                    match commands::prune_versions(app.handle(), &database) {
+                        Ok(0) => {}
+                        Ok(pruned) => println!("📚 已按保留策略删除 {} 个历史版本", pruned),
+                        Err(e) => eprintln!("⚠️ 清理历史版本失败: {}", e),
+                    }
+                    app.manage(database);
                 }
                 Err(e) => {
-                    eprintln!("❌ 获取数据库路径失败: {}", e);
+                    eprintln!("❌ 打开数据库失败: {}", e);
+                    return Err(e.into());
                 }
             }
             
@@ -48,25 +64,45 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             get_all_documents,
+            list_documents,
             get_document,
             create_document,
             update_document,
             update_document_title,
             update_context_summary,
             delete_document,
+            get_delete_impact,
             search_documents,
+            get_backlinks,
+            get_link_graph,
+            get_broken_links,
             get_settings,
             update_settings,
             reset_settings,
             export_document,
+            export_workspace,
+            import_markdown,
+            import_vault,
             get_default_db_path,
             get_current_db_path,
             change_database_path,
             save_file,
             get_all_folders,
+            get_folder_tree,
+            get_breadcrumbs,
             create_folder,
             update_folder,
+            move_folder,
+            move_items,
+            reorder_document,
+            reorder_folder,
+            get_folder_sort_mode,
+            set_folder_sort_mode,
             delete_folder,
+            list_trash,
+            restore_from_trash,
+            purge_from_trash,
+            empty_trash,
             move_document,
             get_tags_by_document,
             create_tag,
@@ -81,6 +117,14 @@ pub fn run() {
             toggle_important_document,
             save_document_version,
             get_document_versions,
+            diff_versions,
+            merge_document_contents,
+            get_version_storage,
+            label_document_version,
+            list_snapshots,
+            create_snapshot,
+            restore_snapshot,
+            delete_snapshot,
             restore_document_version,
             create_backup,
             list_backups,
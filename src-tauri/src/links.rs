@@ -0,0 +1,456 @@
+//! 文档间链接索引：`document_links` 记录每篇文档中 documentLink 标记指向的文档，
+//! 文档内容每次写入时由 `index_document` 同步，用于反向链接、链接图和失效链接。
+//! 目标文档不存在或在回收站中的记录保留在表中，就是失效链接；回收站中文档自身的链接不参与索引。
+//!
+//! 删除文档时可以去掉指向它的标记（`unlink_target`），也可以保留标记：从回收站永久删除时记一条墓碑
+//! （`add_tombstone`），文档之后从回收站或备份恢复时链接自动重新生效，墓碑随之删除
+
+use crate::models::{Backlink, BrokenLink, DeleteImpact, LinkGraph, LinkGraphEdge, LinkGraphNode};
+use crate::repo::{DocumentRepo, VersionRepo};
+use crate::tiptap::{self, visit, Mark, Node};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// 删除文档时如何处理指向它的链接
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DanglingLinks {
+    /// 去掉链接标记，只保留文字
+    Unlink,
+    /// 保留标记并记录墓碑，文档恢复后链接重新生效
+    Tombstone,
+}
+
+impl DanglingLinks {
+    pub fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "unlink" => Ok(DanglingLinks::Unlink),
+            "tombstone" => Ok(DanglingLinks::Tombstone),
+            _ => Err(format!("Unsupported link handling: {}", mode)),
+        }
+    }
+}
+
+/// 文档中的一处链接；相邻且指向同一文档的文本节点（如部分加粗的链接文字）算作一处
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkRef {
+    pub target_id: String,
+    pub text: String,
+}
+
+/// 按文档顺序列出 `doc` 中的所有文档链接
+pub fn outgoing(doc: &Node) -> Vec<LinkRef> {
+    let mut collector = LinkCollector::default();
+    visit::walk(doc, &mut collector);
+    collector.links
+}
+
+#[derive(Default)]
+struct LinkCollector {
+    links: Vec<LinkRef>,
+    /// 上一个访问的节点是否是链接文字，是的话后面紧跟的同目标文字并入同一处
+    continues: bool,
+}
+
+impl visit::Visitor for LinkCollector {
+    fn enter(&mut self, node: &Node, _depth: usize) -> bool {
+        let target = node.marks().iter().find_map(|mark| match mark {
+            Mark::DocumentLink(link) => link.attrs.as_ref()?.document_id.as_deref(),
+            _ => None,
+        });
+        match (node, target) {
+            (Node::Text(text), Some(target)) => {
+                match self.links.last_mut() {
+                    Some(last) if self.continues && last.target_id == target => last.text.push_str(&text.text),
+                    _ => self.links.push(LinkRef { target_id: target.to_string(), text: text.text.clone() }),
+                }
+                self.continues = true;
+            }
+            _ => self.continues = false,
+        }
+        true
+    }
+}
+
+/// 重新索引单篇文档的出链；文档已不存在时删除它的出链
+pub fn index_document(conn: &Connection, document_id: &str) -> Result<(), String> {
+    let content: Option<String> = conn
+        .query_row("SELECT content FROM documents WHERE id = ?1", [document_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to read document for link index: {}", e))?;
+
+    remove_document(conn, document_id)?;
+
+    let Some(content) = content else {
+        return Ok(());
+    };
+    // 内容不是合法 JSON 时没有可索引的链接，不影响保存
+    let links = tiptap::parse(&content).map(|doc| outgoing(&doc)).unwrap_or_default();
+
+    // 同一目标只保留一行：第一处的文字和出现次数
+    let mut targets: Vec<(LinkRef, i64)> = Vec::new();
+    for link in links {
+        match targets.iter_mut().find(|(first, _)| first.target_id == link.target_id) {
+            Some((_, occurrences)) => *occurrences += 1,
+            None => targets.push((link, 1)),
+        }
+    }
+    for (link, occurrences) in targets {
+        conn.execute(
+            "INSERT INTO document_links (source_id, target_id, text, occurrences) VALUES (?1, ?2, ?3, ?4)",
+            params![document_id, &link.target_id, &link.text, occurrences],
+        ).map_err(|e| format!("Failed to index document link: {}", e))?;
+    }
+    Ok(())
+}
+
+pub fn remove_document(conn: &Connection, document_id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM document_links WHERE source_id = ?1", [document_id])
+        .map_err(|e| format!("Failed to remove document links: {}", e))?;
+    Ok(())
+}
+
+/// 清空并重建整个链接索引
+pub fn rebuild(conn: &Connection) -> Result<(), String> {
+    conn.execute("DELETE FROM document_links", [])
+        .map_err(|e| format!("Failed to clear link index: {}", e))?;
+
+    let ids: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT id FROM documents")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let ids = stmt.query_map([], |row| row.get(0))
+            .map_err(|e| format!("Failed to query documents: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect documents: {}", e))?;
+        ids
+    };
+
+    for id in ids {
+        index_document(conn, &id)?;
+    }
+    Ok(())
+}
+
+/// 链接到 `document_id` 的文档，按来源文档的更新时间倒序
+pub fn backlinks(conn: &Connection, document_id: &str) -> Result<Vec<Backlink>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT l.source_id, d.title, l.text, l.occurrences
+             FROM document_links l
+             JOIN documents d ON d.id = l.source_id
+             WHERE l.target_id = ?1
+             ORDER BY d.updated_at DESC, d.id",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let backlinks = stmt.query_map([document_id], |row| {
+            Ok(Backlink {
+                source_id: row.get(0)?,
+                source_title: row.get(1)?,
+                text: row.get(2)?,
+                occurrences: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query backlinks: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect backlinks: {}", e))?;
+
+    Ok(backlinks)
+}
+
+/// 回收站外的全部文档作为节点，有效链接作为边（失效链接见 `broken_links`）
+pub fn graph(conn: &Connection) -> Result<LinkGraph, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, title, folder_id FROM documents WHERE deleted_at IS NULL ORDER BY title, id")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let nodes = stmt.query_map([], |row| {
+            Ok(LinkGraphNode {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                folder_id: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query documents: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect documents: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT l.source_id, l.target_id, l.occurrences
+             FROM document_links l
+             JOIN documents t ON t.id = l.target_id AND t.deleted_at IS NULL
+             ORDER BY l.source_id, l.target_id",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let edges = stmt.query_map([], |row| {
+            Ok(LinkGraphEdge {
+                source: row.get(0)?,
+                target: row.get(1)?,
+                weight: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query document links: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect document links: {}", e))?;
+
+    Ok(LinkGraph { nodes, edges })
+}
+
+/// 删除前的影响报告：哪些文档链接到它
+pub fn delete_impact(conn: &Connection, document_id: &str) -> Result<DeleteImpact, String> {
+    let document = DocumentRepo::new(conn).get(document_id)?;
+    Ok(DeleteImpact {
+        document_id: document.id,
+        title: document.title,
+        backlinks: backlinks(conn, document_id)?,
+    })
+}
+
+/// 去掉所有文档中指向 `target_id` 的链接标记，返回改动了的文档 id；改动前先把原内容保存为版本
+pub fn unlink_target(conn: &Connection, target_id: &str) -> Result<Vec<String>, String> {
+    let repo = DocumentRepo::new(conn);
+    let versions = VersionRepo::new(conn);
+    let mut changed = Vec::new();
+    for backlink in backlinks(conn, target_id)? {
+        let document = repo.get(&backlink.source_id)?;
+        let mut doc = tiptap::parse(&document.content)?;
+        if unlink(&mut doc, target_id) > 0 {
+            let content = serde_json::to_string(&doc)
+                .map_err(|e| format!("Failed to serialize document content: {}", e))?;
+            versions.save_current(&document.id)?;
+            repo.update_content(&document.id, &content)?;
+            changed.push(document.id);
+        }
+    }
+    Ok(changed)
+}
+
+/// 去掉 `node` 中指向 `target_id` 的 documentLink 标记，文字保留；返回去掉的标记数
+pub fn unlink(node: &mut Node, target_id: &str) -> usize {
+    if let Node::Text(text) = node {
+        let Some(marks) = &mut text.marks else {
+            return 0;
+        };
+        let before = marks.len();
+        marks.retain(|mark| !matches!(mark, Mark::DocumentLink(link)
+            if link.attrs.as_ref().and_then(|a| a.document_id.as_deref()) == Some(target_id)));
+        let removed = before - marks.len();
+        if marks.is_empty() {
+            text.marks = None;
+        }
+        return removed;
+    }
+    node.content_mut()
+        .map_or(0, |children| children.iter_mut().map(|child| unlink(child, target_id)).sum())
+}
+
+/// 记录被删除文档的标题，失效链接据此显示原来指向什么
+pub fn add_tombstone(conn: &Connection, document_id: &str, title: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO document_tombstones (document_id, title, deleted_at) VALUES (?1, ?2, ?3)",
+        params![document_id, title, chrono::Utc::now().timestamp()],
+    ).map_err(|e| format!("Failed to add tombstone: {}", e))?;
+    Ok(())
+}
+
+/// 文档重新出现（从回收站或备份恢复）时删除它的墓碑，指向它的链接随之生效
+pub fn clear_tombstone(conn: &Connection, document_id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM document_tombstones WHERE document_id = ?1", [document_id])
+        .map_err(|e| format!("Failed to remove tombstone: {}", e))?;
+    Ok(())
+}
+
+/// 指向已不存在或在回收站中的文档的链接；目标在回收站或有墓碑时附带它的标题和删除时间
+pub fn broken_links(conn: &Connection) -> Result<Vec<BrokenLink>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT l.source_id, s.title, l.target_id, l.text, l.occurrences,
+                    COALESCE(t.title, g.title), COALESCE(t.deleted_at, g.deleted_at)
+             FROM document_links l
+             JOIN documents s ON s.id = l.source_id
+             LEFT JOIN documents t ON t.id = l.target_id
+             LEFT JOIN document_tombstones g ON g.document_id = l.target_id
+             WHERE t.id IS NULL OR t.deleted_at IS NOT NULL
+             ORDER BY s.title, l.source_id, l.target_id",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let links = stmt.query_map([], |row| {
+            Ok(BrokenLink {
+                source_id: row.get(0)?,
+                source_title: row.get(1)?,
+                target_id: row.get(2)?,
+                text: row.get(3)?,
+                occurrences: row.get(4)?,
+                target_title: row.get(5)?,
+                deleted_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query broken links: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect broken links: {}", e))?;
+
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::test_connection;
+
+    fn link(id: &str, text: &str, bold: bool) -> String {
+        let bold = if bold { r#"{"type":"bold"},"# } else { "" };
+        format!(
+            r#"{{"type":"text","text":"{}","marks":[{}{{"type":"documentLink","attrs":{{"documentId":"{}","title":null}}}}]}}"#,
+            text, bold, id
+        )
+    }
+
+    fn paragraph(inline: &[String]) -> String {
+        format!(r#"{{"type":"doc","content":[{{"type":"paragraph","content":[{}]}}]}}"#, inline.join(","))
+    }
+
+    #[test]
+    fn merges_adjacent_link_text() {
+        let doc = tiptap::parse(&paragraph(&[
+            link("a", "Road", false),
+            link("a", "map", true),
+            r#"{"type":"text","text":" and "}"#.to_string(),
+            link("a", "again", false),
+            link("b", "Spec", false),
+        ])).unwrap();
+        let links: Vec<(String, String)> = outgoing(&doc).into_iter().map(|l| (l.target_id, l.text)).collect();
+        assert_eq!(links, vec![
+            ("a".to_string(), "Roadmap".to_string()),
+            ("a".to_string(), "again".to_string()),
+            ("b".to_string(), "Spec".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn tracks_backlinks_graph_and_broken_links() {
+        let conn = test_connection();
+        let docs = DocumentRepo::new(&conn);
+        let target = docs.create("Target", None).unwrap();
+        let source = docs.create("Source", None).unwrap();
+        let other = docs.create("Other", None).unwrap();
+
+        docs.update_content(&source.id, &paragraph(&[link(&target.id, "t", false), link(&other.id, "o", false), link(&target.id, "again", true)])).unwrap();
+        docs.update_content(&other.id, &paragraph(&[link(&target.id, "target", false)])).unwrap();
+
+        let incoming = backlinks(&conn, &target.id).unwrap();
+        let mut sources: Vec<(&str, &str, i64)> = incoming.iter().map(|b| (b.source_title.as_str(), b.text.as_str(), b.occurrences)).collect();
+        sources.sort();
+        assert_eq!(sources, vec![("Other", "target", 1), ("Source", "t", 2)]);
+
+        let full = graph(&conn).unwrap();
+        assert_eq!(full.nodes.len(), 3);
+        assert_eq!(full.edges.len(), 3);
+
+        // 编辑后旧链接被替换
+        docs.update_content(&other.id, r#"{"type":"doc","content":[]}"#).unwrap();
+        assert_eq!(backlinks(&conn, &target.id).unwrap().len(), 1);
+
+        docs.delete(&target.id).unwrap();
+        let broken = broken_links(&conn).unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!((broken[0].source_id.as_str(), broken[0].target_id.as_str()), (source.id.as_str(), target.id.as_str()));
+        assert_eq!(graph(&conn).unwrap().edges.len(), 1);
+
+        docs.delete(&source.id).unwrap();
+        assert!(broken_links(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unlinks_marks_but_keeps_text() {
+        let conn = test_connection();
+        let docs = DocumentRepo::new(&conn);
+        let target = docs.create("Target", None).unwrap();
+        let keep = docs.create("Keep", None).unwrap();
+        let source = docs.create("Source", None).unwrap();
+        docs.update_content(&source.id, &paragraph(&[link(&target.id, "gone", true), link(&keep.id, "kept", false)])).unwrap();
+
+        let impact = delete_impact(&conn, &target.id).unwrap();
+        assert_eq!(impact.backlinks.len(), 1);
+        assert_eq!(impact.backlinks[0].source_id, source.id);
+
+        let before = docs.get(&source.id).unwrap().content;
+        assert_eq!(unlink_target(&conn, &target.id).unwrap(), vec![source.id.clone()]);
+        docs.delete(&target.id).unwrap();
+
+        // 改动前的内容存为了版本，没有链接的文档不受影响
+        let versions = VersionRepo::new(&conn);
+        let saved = versions.list(&source.id).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(versions.get(&source.id, &saved[0].id).unwrap().content, before);
+        assert!(versions.list(&keep.id).unwrap().is_empty());
+
+        let content = docs.get(&source.id).unwrap().content;
+        assert!(content.contains(r#""text":"gone","marks":[{"type":"bold"}]"#));
+        assert!(!content.contains(&target.id));
+        assert_eq!(backlinks(&conn, &keep.id).unwrap().len(), 1);
+        assert!(broken_links(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unlink_returns_exactly_the_rewritten_documents() {
+        let conn = test_connection();
+        let docs = DocumentRepo::new(&conn);
+        let target = docs.create("Target", None).unwrap();
+        let first = docs.create("First", None).unwrap();
+        let second = docs.create("Second", None).unwrap();
+        let unrelated = docs.create("Unrelated", None).unwrap();
+        docs.update_content(&first.id, &paragraph(&[link(&target.id, "a", false)])).unwrap();
+        docs.update_content(&second.id, &paragraph(&[link(&target.id, "b", false), link(&target.id, "c", true)])).unwrap();
+        docs.update_content(&unrelated.id, &paragraph(&[link(&first.id, "d", false)])).unwrap();
+
+        let mut unlinked = unlink_target(&conn, &target.id).unwrap();
+        unlinked.sort();
+        let mut expected = vec![first.id.clone(), second.id.clone()];
+        expected.sort();
+        assert_eq!(unlinked, expected);
+        for id in &unlinked {
+            let document = docs.get(id).unwrap();
+            assert!(!document.content.contains(&target.id));
+            assert_eq!(document.revision, 2);
+        }
+        assert_eq!(docs.get(&unrelated.id).unwrap().revision, 1);
+        assert!(unlink_target(&conn, &target.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn tombstoned_links_resolve_after_restore() {
+        let conn = test_connection();
+        let docs = DocumentRepo::new(&conn);
+        let target = docs.create("Target", None).unwrap();
+        let source = docs.create("Source", None).unwrap();
+        docs.update_content(&source.id, &paragraph(&[link(&target.id, "t", false)])).unwrap();
+
+        let deleted = docs.get(&target.id).unwrap();
+        add_tombstone(&conn, &target.id, &deleted.title).unwrap();
+        docs.delete(&target.id).unwrap();
+
+        let broken = broken_links(&conn).unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].target_title.as_deref(), Some("Target"));
+        assert!(broken[0].deleted_at.is_some());
+
+        docs.insert(&deleted).unwrap();
+        assert!(broken_links(&conn).unwrap().is_empty());
+        assert_eq!(backlinks(&conn, &target.id).unwrap().len(), 1);
+        let tombstones: i64 = conn.query_row("SELECT COUNT(*) FROM document_tombstones", [], |row| row.get(0)).unwrap();
+        assert_eq!(tombstones, 0);
+    }
+
+    #[test]
+    fn rebuild_indexes_existing_documents() {
+        let conn = test_connection();
+        let docs = DocumentRepo::new(&conn);
+        let target = docs.create("Target", None).unwrap();
+        let source = docs.create("Source", None).unwrap();
+        docs.update_content(&source.id, &paragraph(&[link(&target.id, "t", false)])).unwrap();
+
+        conn.execute("DELETE FROM document_links", []).unwrap();
+        assert!(backlinks(&conn, &target.id).unwrap().is_empty());
+        rebuild(&conn).unwrap();
+        assert_eq!(backlinks(&conn, &target.id).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,260 @@
+//! 文档内容的三方合并。
+//!
+//! 分别对 `base` 与 `ours`、`base` 与 `theirs` 的块序列做 LCS 得到改动（替换 `base` 中的一段），
+//! 只有一边改动的区域直接应用；两边的改动重叠时，改得相同取任一边，在同一处各自插入则都保留，
+//! 都改了同一个容器节点（列表、引用、表格等）时递归合并其子节点，否则记为冲突，合并结果中保留 `ours` 的内容
+
+use crate::diff::{lcs, Op};
+use crate::models::{MergeConflict, MergeResult};
+use crate::tiptap::{self, Node};
+
+/// 合并三份 `Document.content`；任一份不是合法文档时报错
+pub fn merge_contents(base: &str, ours: &str, theirs: &str) -> Result<MergeResult, String> {
+    let base = tiptap::parse(base)?;
+    let ours = tiptap::parse(ours)?;
+    let theirs = tiptap::parse(theirs)?;
+    let Node::Doc(mut doc) = ours.clone() else {
+        return Err("Failed to merge: content is not a document".to_string());
+    };
+
+    let mut merger = Merger { path: Vec::new(), conflicts: Vec::new() };
+    let children = merger.merge(base.children(), ours.children(), theirs.children())?;
+    doc.content = Some(children);
+    let content = serde_json::to_string(&Node::Doc(doc))
+        .map_err(|e| format!("Failed to serialize merged content: {}", e))?;
+    Ok(MergeResult { content, conflicts: merger.conflicts })
+}
+
+struct Merger {
+    /// 正在合并的父节点在合并结果中的路径
+    path: Vec<usize>,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Merger {
+    fn merge(&mut self, base: &[Node], ours: &[Node], theirs: &[Node]) -> Result<Vec<Node>, String> {
+        let mut hunks: Vec<(bool, Hunk)> = hunks(base, ours).into_iter().map(|h| (true, h))
+            .chain(hunks(base, theirs).into_iter().map(|h| (false, h)))
+            .collect();
+        hunks.sort_by_key(|(_, h)| (h.start, h.end));
+
+        let mut out = Vec::new();
+        let mut cursor = 0;
+        let mut rest = hunks.as_slice();
+        while let Some((_, first)) = rest.first() {
+            // 相互重叠的一组改动
+            let (mut start, mut end) = (first.start, first.end);
+            let len = rest.iter()
+                .take_while(|(_, h)| {
+                    let joins = overlaps(start, end, h);
+                    if joins {
+                        (start, end) = (start.min(h.start), end.max(h.end));
+                    }
+                    joins
+                })
+                .count();
+            let (group, next) = rest.split_at(len);
+            rest = next;
+
+            out.extend_from_slice(&base[cursor..start]);
+            cursor = end;
+            let side = |ours: bool| apply(base, start, end, group.iter().filter(|(o, _)| *o == ours).map(|(_, h)| h));
+            match (group.iter().any(|(o, _)| *o), group.iter().any(|(o, _)| !*o)) {
+                (true, true) => self.resolve(&base[start..end], &side(true), &side(false), &mut out)?,
+                (ours, _) => out.extend(side(ours)),
+            }
+        }
+        out.extend_from_slice(&base[cursor..]);
+        Ok(out)
+    }
+
+    /// 两边都改动了的区域
+    fn resolve(&mut self, base: &[Node], ours: &[Node], theirs: &[Node], out: &mut Vec<Node>) -> Result<(), String> {
+        if ours == base {
+            out.extend_from_slice(theirs);
+        } else if theirs == base || ours == theirs {
+            out.extend_from_slice(ours);
+        } else if base.is_empty() {
+            out.extend_from_slice(ours);
+            out.extend_from_slice(theirs);
+        } else if let ([b], [o], [t]) = (base, ours, theirs) {
+            match self.merge_container(b, o, t, out.len())? {
+                Some(node) => out.push(node),
+                None => self.conflict(base, ours, theirs, out)?,
+            }
+        } else {
+            self.conflict(base, ours, theirs, out)?;
+        }
+        Ok(())
+    }
+
+    /// 三边是同类容器且自身属性最多一边改过时，递归合并子节点
+    fn merge_container(&mut self, base: &Node, ours: &Node, theirs: &Node, index: usize) -> Result<Option<Node>, String> {
+        let mergeable = [base, ours, theirs].iter().all(|node| is_container(node) && !node.children().is_empty());
+        if !mergeable {
+            return Ok(None);
+        }
+        let (base_shell, ours_shell, theirs_shell) = (shell(base), shell(ours), shell(theirs));
+        let mut node = if ours_shell == base_shell {
+            theirs.clone()
+        } else if theirs_shell == base_shell || ours_shell == theirs_shell {
+            ours.clone()
+        } else {
+            return Ok(None);
+        };
+
+        self.path.push(index);
+        let children = self.merge(base.children(), ours.children(), theirs.children());
+        self.path.pop();
+        if let Some(content) = node.content_mut() {
+            *content = children?;
+        }
+        Ok(Some(node))
+    }
+
+    fn conflict(&mut self, base: &[Node], ours: &[Node], theirs: &[Node], out: &mut Vec<Node>) -> Result<(), String> {
+        let json = |nodes: &[Node]| serde_json::to_string(nodes)
+            .map_err(|e| format!("Failed to serialize conflict: {}", e));
+        let mut path = self.path.clone();
+        path.push(out.len());
+        self.conflicts.push(MergeConflict {
+            path,
+            count: ours.len(),
+            base: json(base)?,
+            ours: json(ours)?,
+            theirs: json(theirs)?,
+        });
+        out.extend_from_slice(ours);
+        Ok(())
+    }
+}
+
+/// 一处改动：把 `base[start..end]` 替换为 `nodes`
+struct Hunk<'a> {
+    start: usize,
+    end: usize,
+    nodes: &'a [Node],
+}
+
+fn hunks<'a>(base: &[Node], other: &'a [Node]) -> Vec<Hunk<'a>> {
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let equals = lcs(base, other, |a, b| a == b).into_iter().filter_map(|op| match op {
+        Op::Equal(bi, oj) => Some((bi, oj)),
+        _ => None,
+    });
+    for (bi, oj) in equals.chain([(base.len(), other.len())]) {
+        if (bi, oj) != (i, j) {
+            hunks.push(Hunk { start: i, end: bi, nodes: &other[j..oj] });
+        }
+        (i, j) = (bi + 1, oj + 1);
+    }
+    hunks
+}
+
+/// 改动与 `base[start..end]` 重叠；同一处的两个插入也算重叠，紧挨着的不算
+fn overlaps(start: usize, end: usize, hunk: &Hunk) -> bool {
+    (start < hunk.end && hunk.start < end) || (start == end && hunk.start == hunk.end && start == hunk.start)
+}
+
+/// 把一边的改动应用到 `base[start..end]`
+fn apply<'a>(base: &[Node], start: usize, end: usize, hunks: impl Iterator<Item = &'a Hunk<'a>>) -> Vec<Node> {
+    let mut out = Vec::new();
+    let mut cursor = start;
+    for hunk in hunks {
+        out.extend_from_slice(&base[cursor..hunk.start]);
+        out.extend_from_slice(hunk.nodes);
+        cursor = hunk.end;
+    }
+    out.extend_from_slice(&base[cursor..end]);
+    out
+}
+
+/// 子节点为块的节点
+fn is_container(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::Blockquote(_)
+            | Node::BulletList(_)
+            | Node::OrderedList(_)
+            | Node::ListItem(_)
+            | Node::TaskList(_)
+            | Node::TaskItem(_)
+            | Node::Table(_)
+            | Node::TableRow(_)
+            | Node::TableHeader(_)
+            | Node::TableCell(_)
+    )
+}
+
+/// 去掉子节点后的节点，用于比较类型和属性
+fn shell(node: &Node) -> Node {
+    let mut node = node.clone();
+    if let Some(content) = node.content_mut() {
+        content.clear();
+    }
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraph(text: &str) -> String {
+        format!(r#"{{"type":"paragraph","content":[{{"type":"text","text":"{}"}}]}}"#, text)
+    }
+
+    fn list(items: &[&str]) -> String {
+        let items: Vec<String> = items.iter().map(|text| format!(r#"{{"type":"listItem","content":[{}]}}"#, paragraph(text))).collect();
+        format!(r#"{{"type":"bulletList","content":[{}]}}"#, items.join(","))
+    }
+
+    fn doc(blocks: &[String]) -> String {
+        format!(r#"{{"type":"doc","content":[{}]}}"#, blocks.join(","))
+    }
+
+    fn merged(base: &str, ours: &str, theirs: &str) -> (Node, Vec<MergeConflict>) {
+        let result = merge_contents(base, ours, theirs).unwrap();
+        (tiptap::parse(&result.content).unwrap(), result.conflicts)
+    }
+
+    #[test]
+    fn merges_edits_to_different_blocks() {
+        let base = doc(&[paragraph("a"), paragraph("b"), paragraph("c")]);
+        let ours = doc(&[paragraph("a2"), paragraph("b"), paragraph("c"), paragraph("ours")]);
+        let theirs = doc(&[paragraph("a"), paragraph("b"), paragraph("c2"), paragraph("theirs")]);
+
+        let (content, conflicts) = merged(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        let expected = doc(&[paragraph("a2"), paragraph("b"), paragraph("c2"), paragraph("theirs"), paragraph("ours")]);
+        assert_eq!(content, tiptap::parse(&expected).unwrap());
+    }
+
+    #[test]
+    fn overlapping_edits_conflict_and_keep_ours() {
+        let base = doc(&[paragraph("a"), paragraph("b")]);
+        let ours = doc(&[paragraph("a"), paragraph("b ours")]);
+        let theirs = doc(&[paragraph("a"), paragraph("b theirs")]);
+
+        let (content, conflicts) = merged(&base, &ours, &theirs);
+        assert_eq!(content, tiptap::parse(&ours).unwrap());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].path.as_slice(), conflicts[0].count), (&[1][..], 1));
+        assert_eq!(conflicts[0].theirs, format!("[{}]", paragraph("b theirs")));
+    }
+
+    #[test]
+    fn merges_inside_containers() {
+        let base = doc(&[paragraph("intro"), list(&["one", "two", "three", "four", "five"])]);
+        let ours = doc(&[paragraph("intro"), list(&["one!", "two", "three ours", "four", "five"])]);
+        let theirs = doc(&[paragraph("intro"), list(&["one", "two", "three theirs", "four", "five?"])]);
+
+        let (content, conflicts) = merged(&base, &ours, &theirs);
+        let expected = doc(&[paragraph("intro"), list(&["one!", "two", "three ours", "four", "five?"])]);
+        assert_eq!(content, tiptap::parse(&expected).unwrap());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, [1, 2, 0]);
+
+        assert!(merge_contents(&base, "[]", &theirs).is_err());
+    }
+}
@@ -1,155 +1,754 @@
-use rusqlite::Connection;
-use std::path::PathBuf;
+use rusqlite::{Connection, OptionalExtension};
+use std::path::Path;
 
-pub fn run_migrations(db_path: &PathBuf) -> Result<(), String> {
-    let conn = Connection::open(db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-    
-    // 检查是否已有 context_summary 列
-    let has_column: bool = conn
+/// 单个结构迁移步骤，`up` 在迁移事务内执行。
+/// `up` 只执行固定的 SQL，不调用会随结构演进的应用代码；需要从现有数据计算的内容列在 `rebuilds` 中
+struct Migration {
+    version: i64,
+    description: &'static str,
+    up: fn(&Connection) -> Result<(), String>,
+    rebuilds: &'static [Rebuild],
+}
+
+/// 由现有数据计算的索引、排序键等。所有待执行的迁移完成后用当前代码统一计算一次，
+/// 当前代码只需要兼容最新结构
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Rebuild {
+    SearchIndex,
+    LinkIndex,
+    SortOrder,
+    Summaries,
+    VersionStorage,
+}
+
+impl Rebuild {
+    fn run(self, conn: &Connection) -> Result<(), String> {
+        match self {
+            Rebuild::SearchIndex => crate::search::rebuild(conn),
+            Rebuild::LinkIndex => crate::links::rebuild(conn),
+            Rebuild::SortOrder => {
+                crate::ordering::renumber(conn, "folders", "parent_id", "name ASC")?;
+                crate::ordering::renumber(conn, "documents", "folder_id", "is_pinned DESC, updated_at DESC")
+            }
+            Rebuild::Summaries => crate::repo::rebuild_summaries(conn),
+            Rebuild::VersionStorage => crate::repo::compress_legacy_versions(conn),
+        }
+    }
+}
+
+/// 按版本号升序排列的全部迁移，新增迁移只能追加到末尾
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema (adopt legacy databases)",
+        up: baseline,
+        rebuilds: &[],
+    },
+    Migration {
+        version: 2,
+        description: "full-text search index",
+        up: full_text_search,
+        rebuilds: &[],
+    },
+    Migration {
+        version: 3,
+        description: "CJK-aware search index",
+        up: cjk_search_index,
+        rebuilds: &[Rebuild::SearchIndex],
+    },
+    Migration {
+        version: 4,
+        description: "document link index",
+        up: document_links,
+        rebuilds: &[Rebuild::LinkIndex],
+    },
+    Migration {
+        version: 5,
+        description: "tombstones for deleted link targets",
+        up: document_tombstones,
+        rebuilds: &[],
+    },
+    Migration {
+        version: 6,
+        description: "trash (soft delete) columns",
+        up: trash_columns,
+        rebuilds: &[],
+    },
+    Migration {
+        version: 7,
+        description: "reattach orphaned folders and documents",
+        up: reattach_orphans,
+        rebuilds: &[],
+    },
+    Migration {
+        version: 8,
+        description: "manual ordering and per-folder sort modes",
+        up: manual_ordering,
+        rebuilds: &[Rebuild::SortOrder],
+    },
+    Migration {
+        version: 9,
+        description: "document word counts and previews",
+        up: document_summaries,
+        rebuilds: &[Rebuild::Summaries],
+    },
+    Migration {
+        version: 10,
+        description: "compressed version storage",
+        up: compressed_versions,
+        rebuilds: &[Rebuild::VersionStorage],
+    },
+    Migration {
+        version: 11,
+        description: "version labels and named snapshots",
+        up: version_labels,
+        rebuilds: &[],
+    },
+    Migration {
+        version: 12,
+        description: "document revisions",
+        up: document_revisions,
+        rebuilds: &[],
+    },
+];
+
+/// 当前应用支持的最新结构版本
+pub const LATEST_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// 在已打开的连接上执行所有待执行的迁移（单个事务）
+pub fn migrate(conn: &mut Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+    )
+    .map_err(|e| format!("Failed to create schema_version table: {}", e))?;
+
+    let current = schema_version(conn)?;
+    if current > LATEST_VERSION {
+        return Err(format!(
+            "Database schema version {} is newer than this app supports ({}), please upgrade the app",
+            current, LATEST_VERSION
+        ));
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.transaction()
+        .map_err(|e| format!("Failed to begin migration transaction: {}", e))?;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut rebuilds = Vec::new();
+    for migration in pending {
+        println!("🔧 数据库迁移 v{}: {}", migration.version, migration.description);
+        (migration.up)(&tx)
+            .map_err(|e| format!("Migration v{} failed: {}", migration.version, e))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![migration.version, migration.description, now],
+        )
+        .map_err(|e| format!("Failed to record schema version: {}", e))?;
+        rebuilds.extend_from_slice(migration.rebuilds);
+    }
+
+    rebuilds.sort();
+    rebuilds.dedup();
+    for rebuild in rebuilds {
+        rebuild.run(&tx)
+            .map_err(|e| format!("Failed to rebuild {:?} after migration: {}", rebuild, e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit migrations: {}", e))?;
+    println!("✅ 数据库结构已更新到 v{}", LATEST_VERSION);
+
+    Ok(())
+}
+
+/// 读取数据库当前结构版本，没有 schema_version 表时视为 0
+pub fn schema_version(conn: &Connection) -> Result<i64, String> {
+    let has_table: bool = conn
         .query_row(
-            "SELECT COUNT(*) FROM pragma_table_info('documents') WHERE name='context_summary'",
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='schema_version'",
             [],
-            |row| row.get(0),
+            |row| row.get::<_, i64>(0),
         )
-        .map(|count: i32| count > 0)
-        .unwrap_or(false);
-    
-    if !has_column {
-        println!("🔧 添加 context_summary 列到 documents 表...");
+        .map(|count| count > 0)
+        .map_err(|e| format!("Failed to inspect schema: {}", e))?;
+
+    if !has_table {
+        return Ok(0);
+    }
+
+    conn.query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get::<_, Option<i64>>(0))
+        .optional()
+        .map(|v| v.flatten().unwrap_or(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))
+}
+
+/// 检查外部数据库文件（备份、导入）能否被当前应用打开
+pub fn check_compatible(db_path: &Path) -> Result<(), String> {
+    let conn = Connection::open(db_path)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let version = schema_version(&conn)?;
+    if version > LATEST_VERSION {
+        return Err(format!(
+            "Database schema version {} is newer than this app supports ({}), please upgrade the app",
+            version, LATEST_VERSION
+        ));
+    }
+    Ok(())
+}
+
+fn has_table(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
+        [table],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name=?2",
+        [table, column],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    if has_table(conn, table)? && !has_column(conn, table, column)? {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+// v1：统一历史上三条建表路径（tauri_plugin_sql 迁移、旧版 run_migrations、命令里的内联建表）
+// 留下的各种结构，缺什么补什么
+fn baseline(conn: &Connection) -> Result<(), String> {
+    baseline_schema(conn).map_err(|e| e.to_string())
+}
+
+fn baseline_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS documents (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            context_summary TEXT,
+            folder_id TEXT,
+            is_pinned INTEGER DEFAULT 0,
+            is_important INTEGER DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS folders (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            parent_id TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            FOREIGN KEY (parent_id) REFERENCES folders(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS tags (
+            id TEXT PRIMARY KEY,
+            document_id TEXT NOT NULL,
+            text TEXT NOT NULL,
+            selected_text TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            ai_block_id TEXT,
+            color TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS document_versions (
+            id TEXT PRIMARY KEY,
+            document_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            version_number INTEGER NOT NULL,
+            FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+        );",
+    )?;
+
+    add_column_if_missing(conn, "documents", "context_summary", "TEXT")?;
+    add_column_if_missing(conn, "documents", "folder_id", "TEXT")?;
+    add_column_if_missing(conn, "documents", "is_pinned", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "documents", "is_important", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "tags", "ai_block_id", "TEXT")?;
+
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_documents_updated_at ON documents(updated_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_documents_title ON documents(title);
+        CREATE INDEX IF NOT EXISTS idx_documents_folder_id ON documents(folder_id);
+        CREATE INDEX IF NOT EXISTS idx_folders_parent_id ON folders(parent_id);
+        CREATE INDEX IF NOT EXISTS idx_tags_document_id ON tags(document_id);
+        CREATE INDEX IF NOT EXISTS idx_tags_position ON tags(position);
+        CREATE INDEX IF NOT EXISTS idx_versions_document_id ON document_versions(document_id);
+        CREATE INDEX IF NOT EXISTS idx_versions_created_at ON document_versions(created_at DESC);",
+    )?;
+
+    Ok(())
+}
+
+// v2：FTS5 全文索引（v3 会用新结构重建并填充）
+fn full_text_search(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5(
+            document_id UNINDEXED,
+            title,
+            body,
+            ai,
+            tags,
+            summary,
+            tokenize = 'unicode61 remove_diacritics 2'
+        )",
+    )
+    .map_err(|e| format!("Failed to create search index: {}", e))
+}
+
+// v3：索引列改为存放 CJK 二元切分后的文本，并增加原文列，迁移后重建全部索引
+fn cjk_search_index(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS documents_fts;
+         CREATE VIRTUAL TABLE documents_fts USING fts5(
+            document_id UNINDEXED,
+            title,
+            body,
+            ai,
+            tags,
+            summary,
+            raw_title UNINDEXED,
+            raw_body UNINDEXED,
+            raw_ai UNINDEXED,
+            raw_tags UNINDEXED,
+            raw_summary UNINDEXED,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );",
+    )
+    .map_err(|e| format!("Failed to create search index: {}", e))
+}
+
+// v4：documentLink 标记的链接索引，迁移后从现有文档内容建立
+fn document_links(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS document_links (
+            source_id TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            text TEXT NOT NULL,
+            occurrences INTEGER NOT NULL,
+            PRIMARY KEY (source_id, target_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_document_links_target ON document_links(target_id);",
+    )
+    .map_err(|e| format!("Failed to create link index: {}", e))
+}
+
+// v5：被删除文档的墓碑，失效链接据此显示原标题
+fn document_tombstones(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS document_tombstones (
+            document_id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            deleted_at INTEGER NOT NULL
+        )",
+    )
+    .map_err(|e| format!("Failed to create tombstone table: {}", e))
+}
+
+// v6：回收站。deleted_at 非空表示已删除；trashed_with 记录随哪个文件夹一起删除，
+// trash_path 记录单独删除时的原文件夹路径
+fn trash_columns(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "ALTER TABLE documents ADD COLUMN deleted_at INTEGER;
+         ALTER TABLE documents ADD COLUMN trashed_with TEXT;
+         ALTER TABLE documents ADD COLUMN trash_path TEXT;
+         ALTER TABLE folders ADD COLUMN deleted_at INTEGER;
+         ALTER TABLE folders ADD COLUMN trashed_with TEXT;
+         ALTER TABLE folders ADD COLUMN trash_path TEXT;
+         CREATE INDEX IF NOT EXISTS idx_documents_trashed_with ON documents(trashed_with);
+         CREATE INDEX IF NOT EXISTS idx_folders_trashed_with ON folders(trashed_with);",
+    )
+    .map_err(|e| format!("Failed to add trash columns: {}", e))
+}
+
+// v7：外键从未启用，旧版本删除文件夹时子文件夹和更深的文档留下了失效的 parent_id / folder_id，
+// 把它们移到根目录，否则界面上看不到
+fn reattach_orphans(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "UPDATE folders SET parent_id = NULL
+         WHERE parent_id IS NOT NULL AND parent_id NOT IN (SELECT id FROM folders);
+         UPDATE documents SET folder_id = NULL
+         WHERE folder_id IS NOT NULL AND deleted_at IS NULL AND folder_id NOT IN (SELECT id FROM folders);",
+    )
+    .map_err(|e| format!("Failed to reattach orphans: {}", e))
+}
+
+// v8：同级手动排序键和每个文件夹的排序方式，迁移后现有数据按原来的显示顺序编号
+fn manual_ordering(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "ALTER TABLE documents ADD COLUMN sort_order TEXT;
+         ALTER TABLE folders ADD COLUMN sort_order TEXT;
+         CREATE TABLE IF NOT EXISTS folder_sort_modes (
+             folder_id TEXT PRIMARY KEY,
+             sort_mode TEXT NOT NULL
+         );",
+    )
+    .map_err(|e| format!("Failed to add sort order columns: {}", e))
+}
+
+// v9：文档列表用的字数和预览，迁移后从现有内容计算
+fn document_summaries(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "ALTER TABLE documents ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE documents ADD COLUMN preview TEXT NOT NULL DEFAULT '';",
+    )
+    .map_err(|e| format!("Failed to add summary columns: {}", e))
+}
+
+// v10：历史版本改为压缩的完整快照加增量，迁移后现有版本重新编码，content 列清空
+fn compressed_versions(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "ALTER TABLE document_versions ADD COLUMN base_version INTEGER;
+         ALTER TABLE document_versions ADD COLUMN data BLOB;
+         ALTER TABLE document_versions ADD COLUMN size INTEGER NOT NULL DEFAULT 0;",
+    )
+    .map_err(|e| format!("Failed to add version storage columns: {}", e))
+}
+
+// v11：版本的标签、备注，以及把多个文档的版本归为一组的命名快照
+fn version_labels(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "ALTER TABLE document_versions ADD COLUMN label TEXT;
+         ALTER TABLE document_versions ADD COLUMN note TEXT;
+         ALTER TABLE document_versions ADD COLUMN snapshot_id TEXT;
+         CREATE INDEX IF NOT EXISTS idx_versions_snapshot_id ON document_versions(snapshot_id);
+         CREATE TABLE IF NOT EXISTS named_snapshots (
+             id TEXT PRIMARY KEY,
+             label TEXT NOT NULL,
+             note TEXT,
+             folder_id TEXT,
+             created_at INTEGER NOT NULL
+         );",
+    )
+    .map_err(|e| format!("Failed to add version labels: {}", e))
+}
+
+// v12：文档内容的修订号，用于检测并发写入
+fn document_revisions(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch("ALTER TABLE documents ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;")
+        .map_err(|e| format!("Failed to add revision column: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid").unwrap();
+        stmt.query_map([table], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap()
+    }
+
+    fn assert_latest(conn: &Connection) {
+        assert_eq!(schema_version(conn).unwrap(), LATEST_VERSION);
+        let docs = columns(conn, "documents");
+        for col in ["id", "title", "content", "created_at", "updated_at", "context_summary", "folder_id", "is_pinned", "is_important"] {
+            assert!(docs.contains(&col.to_string()), "documents.{} missing", col);
+        }
+        assert!(columns(conn, "tags").contains(&"ai_block_id".to_string()));
+        assert!(columns(conn, "folders").contains(&"parent_id".to_string()));
+        assert!(columns(conn, "documents").contains(&"deleted_at".to_string()));
+        assert!(columns(conn, "folders").contains(&"deleted_at".to_string()));
+        assert!(columns(conn, "documents").contains(&"sort_order".to_string()));
+        assert!(columns(conn, "documents").contains(&"preview".to_string()));
+        assert!(columns(conn, "document_versions").contains(&"version_number".to_string()));
+        assert!(columns(conn, "document_versions").contains(&"data".to_string()));
+    }
+
+    fn insert_legacy_document(conn: &Connection) {
         conn.execute(
-            "ALTER TABLE documents ADD COLUMN context_summary TEXT",
+            "INSERT INTO documents (id, title, content, created_at, updated_at) VALUES ('d1', 'Old', '{}', 1, 2)",
             [],
         )
-        .map_err(|e| format!("Failed to add context_summary column: {}", e))?;
-        println!("✅ context_summary 列添加成功");
-    } else {
-        println!("✓ context_summary 列已存在");
-    }
-    
-    // 检查是否已有 tags 表
-    let has_tags_table: bool = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='tags'",
-            [],
-            |row| row.get(0),
+        .unwrap();
+    }
+
+    #[test]
+    fn empty_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        assert_latest(&conn);
+    }
+
+    // change_database_path / create_document 内联建表留下的最小结构
+    #[test]
+    fn bare_documents_table() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );",
         )
-        .map(|count: i32| count > 0)
-        .unwrap_or(false);
-    
-    if !has_tags_table {
-        println!("🔧 创建 tags 表...");
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tags (
+        .unwrap();
+        insert_legacy_document(&conn);
+
+        migrate(&mut conn).unwrap();
+        assert_latest(&conn);
+
+        let (title, pinned): (String, Option<bool>) = conn
+            .query_row("SELECT title, is_pinned FROM documents WHERE id = 'd1'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(title, "Old");
+        assert_eq!(pinned, Some(false));
+    }
+
+    // tauri_plugin_sql 的 v1-v3 迁移：有 settings 和不带 ai_block_id 的 tags，没有 folders
+    #[test]
+    fn plugin_sql_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+            ALTER TABLE documents ADD COLUMN context_summary TEXT;
+            CREATE TABLE tags (
                 id TEXT PRIMARY KEY,
                 document_id TEXT NOT NULL,
                 text TEXT NOT NULL,
                 selected_text TEXT NOT NULL,
                 position INTEGER NOT NULL,
-                ai_block_id TEXT,
                 color TEXT,
                 created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL,
-                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
-            )",
-            [],
+                updated_at INTEGER NOT NULL
+            );",
         )
-        .map_err(|e| format!("Failed to create tags table: {}", e))?;
-        
+        .unwrap();
+        insert_legacy_document(&conn);
         conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tags_document_id ON tags(document_id)",
+            "INSERT INTO tags (id, document_id, text, selected_text, position, created_at, updated_at) VALUES ('t1', 'd1', 'x', 'y', 0, 1, 1)",
             [],
         )
-        .map_err(|e| format!("Failed to create index: {}", e))?;
-        
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tags_position ON tags(position)",
-            [],
-        )
-        .map_err(|e| format!("Failed to create index: {}", e))?;
-        
-        println!("✅ tags 表创建成功");
-    } else {
-        println!("✓ tags 表已存在");
-        
-        // 检查是否需要添加 ai_block_id 列
-        let has_ai_block_id: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('tags') WHERE name='ai_block_id'",
-                [],
-                |row| row.get(0),
-            )
-            .map(|count: i32| count > 0)
-            .unwrap_or(false);
-        
-        if !has_ai_block_id {
-            println!("🔧 添加 ai_block_id 列到 tags 表...");
-            conn.execute(
-                "ALTER TABLE tags ADD COLUMN ai_block_id TEXT",
-                [],
-            )
-            .map_err(|e| format!("Failed to add ai_block_id column: {}", e))?;
-            println!("✅ ai_block_id 列添加成功");
-        }
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        assert_latest(&conn);
+
+        let ai_block_id: Option<String> = conn
+            .query_row("SELECT ai_block_id FROM tags WHERE id = 't1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(ai_block_id, None);
     }
-    
-    // 检查是否已有 folders 表
-    let has_folders_table: bool = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='folders'",
-            [],
-            |row| row.get(0),
-        )
-        .map(|count: i32| count > 0)
-        .unwrap_or(false);
-    
-    if !has_folders_table {
-        println!("🔧 创建 folders 表...");
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS folders (
+
+    // 旧版 run_migrations 的结果：有 folders 和 folder_id，但没有置顶/重要标记和版本表
+    #[test]
+    fn legacy_run_migrations_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                context_summary TEXT,
+                folder_id TEXT
+            );
+            CREATE TABLE tags (
+                id TEXT PRIMARY KEY,
+                document_id TEXT NOT NULL,
+                text TEXT NOT NULL,
+                selected_text TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                ai_block_id TEXT,
+                color TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE folders (
                 id TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
                 parent_id TEXT,
                 created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );",
+        )
+        .unwrap();
+        insert_legacy_document(&conn);
+
+        migrate(&mut conn).unwrap();
+        assert_latest(&conn);
+    }
+
+    // get_all_documents 内联补齐后的完整结构
+    #[test]
+    fn inline_command_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
                 updated_at INTEGER NOT NULL,
-                FOREIGN KEY (parent_id) REFERENCES folders(id) ON DELETE CASCADE
-            )",
-            [],
+                context_summary TEXT,
+                folder_id TEXT,
+                is_pinned INTEGER DEFAULT 0,
+                is_important INTEGER DEFAULT 0
+            );
+            CREATE TABLE folders (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                parent_id TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE document_versions (
+                id TEXT PRIMARY KEY,
+                document_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                version_number INTEGER NOT NULL
+            );
+            INSERT INTO document_versions VALUES ('v1', 'd1', 'first', 1, 1);
+            INSERT INTO document_versions VALUES ('v2', 'd1', 'second', 2, 2);",
         )
-        .map_err(|e| format!("Failed to create folders table: {}", e))?;
-        
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_folders_parent_id ON folders(parent_id)",
-            [],
+        .unwrap();
+        insert_legacy_document(&conn);
+
+        migrate(&mut conn).unwrap();
+        assert_latest(&conn);
+
+        // 旧版本重新编码后内容不变，content 列已清空
+        let versions = crate::repo::VersionRepo::new(&conn).list("d1").unwrap();
+        let contents: Vec<_> = versions.iter().map(|v| v.content.as_str()).collect();
+        assert_eq!(contents, ["second", "first"]);
+        let legacy: i64 = conn
+            .query_row("SELECT COUNT(*) FROM document_versions WHERE content != '' OR data IS NULL", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(legacy, 0);
+    }
+
+    // 外键未启用时删除文件夹留下的孤儿
+    #[test]
+    fn orphans_are_reattached_to_root() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                context_summary TEXT,
+                folder_id TEXT
+            );
+            CREATE TABLE folders (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                parent_id TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            INSERT INTO folders VALUES ('kept', 'Kept', NULL, 1, 1);
+            INSERT INTO folders VALUES ('orphan', 'Orphan', 'gone', 1, 1);
+            INSERT INTO documents VALUES ('d1', 'In kept', '{}', 1, 1, NULL, 'kept');
+            INSERT INTO documents VALUES ('d2', 'Lost', '{}', 1, 1, NULL, 'gone');",
         )
-        .map_err(|e| format!("Failed to create index: {}", e))?;
-        
-        println!("✅ folders 表创建成功");
-    } else {
-        println!("✓ folders 表已存在");
-    }
-    
-    // 检查 documents 表是否有 folder_id 列
-    let has_folder_id: bool = conn
-        .query_row(
-            "SELECT COUNT(*) FROM pragma_table_info('documents') WHERE name='folder_id'",
-            [],
-            |row| row.get(0),
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        assert_latest(&conn);
+        let parent: Option<String> = conn.query_row("SELECT parent_id FROM folders WHERE id = 'orphan'", [], |row| row.get(0)).unwrap();
+        assert_eq!(parent, None);
+        let folders: Vec<Option<String>> = conn
+            .prepare("SELECT folder_id FROM documents ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(folders, vec![Some("kept".to_string()), None]);
+    }
+
+    // 迁移只改结构，索引、预览和排序键在全部迁移完成后按最新结构计算
+    #[test]
+    fn derived_data_is_rebuilt_after_migrations() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            INSERT INTO documents VALUES ('target', 'Target', '{"type":"doc","content":[]}', 1, 1);
+            INSERT INTO documents VALUES ('source', 'Source', '{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"see target","marks":[{"type":"documentLink","attrs":{"documentId":"target","title":null}}]}]}]}', 1, 2);"#,
         )
-        .map(|count: i32| count > 0)
-        .unwrap_or(false);
-    
-    if !has_folder_id {
-        println!("🔧 添加 folder_id 列到 documents 表...");
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        assert_latest(&conn);
+        let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM documents_fts", [], |row| row.get(0)).unwrap();
+        assert_eq!(indexed, 2);
+        assert_eq!(crate::links::backlinks(&conn, "target").unwrap().len(), 1);
+        let (preview, sort_order): (String, Option<String>) = conn
+            .query_row("SELECT preview, sort_order FROM documents WHERE id = 'source'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(preview, "see target");
+        assert!(sort_order.is_some());
+    }
+
+    #[test]
+    fn migrate_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
         conn.execute(
-            "ALTER TABLE documents ADD COLUMN folder_id TEXT",
-            [],
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, 'future', 0)",
+            [LATEST_VERSION + 1],
         )
-        .map_err(|e| format!("Failed to add folder_id column: {}", e))?;
-        println!("✅ folder_id 列添加成功");
-    } else {
-        println!("✓ folder_id 列已存在");
+        .unwrap();
+
+        let err = migrate(&mut conn).unwrap_err();
+        assert!(err.contains("newer"), "{}", err);
+    }
+
+    #[test]
+    fn migration_versions_are_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
     }
-    
-    Ok(())
 }
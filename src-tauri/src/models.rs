@@ -12,6 +12,31 @@ pub struct Document {
     pub folder_id: Option<String>,  // 所属文件夹ID
     pub is_pinned: Option<bool>,  // 是否置顶
     pub is_important: Option<bool>,  // 是否标记为重要
+    #[serde(default)]
+    pub revision: i64,  // 内容修订号，每次写入内容加一
+}
+
+/// 文档列表项：不含 `content`，全文用 `get_document` 获取
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSummary {
+    pub id: String,
+    pub title: String,
+    pub folder_id: Option<String>,
+    pub is_pinned: bool,
+    pub is_important: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub word_count: i64,
+    pub preview: String,  // 正文第一行（截断）
+}
+
+/// `list_documents` 的一页结果，`next_cursor` 为 None 表示没有更多
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentPage {
+    pub items: Vec<DocumentSummary>,
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,6 +47,9 @@ pub struct DocumentVersion {
     pub content: String,
     pub created_at: i64,
     pub version_number: i32,
+    pub label: Option<String>,  // 有标签的版本不会被保留策略删除
+    pub note: Option<String>,
+    pub snapshot_id: Option<String>,  // 所属的命名快照，快照删除前同样不会被清理
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -34,6 +62,33 @@ pub struct Folder {
     pub updated_at: i64,
 }
 
+/// 嵌套文件夹树中的一个节点
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderTreeNode {
+    pub folder: Folder,
+    pub document_count: i64,  // 直接包含的文档数
+    pub total_document_count: i64,  // 包括子文件夹在内的文档数
+    pub last_modified: Option<i64>,  // 子树中最新文档的更新时间，没有文档时为 None
+    pub children: Vec<FolderTreeNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderTree {
+    pub folders: Vec<FolderTreeNode>,
+    pub root_document_count: i64,  // 不在任何文件夹中的文档数
+}
+
+/// 面包屑路径中的一级
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Breadcrumb {
+    pub id: String,
+    pub name: String,
+    pub kind: String,  // "folder" 或 "document"
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Tag {
@@ -48,6 +103,249 @@ pub struct Tag {
     pub updated_at: i64,
 }
 
+/// 全文搜索命中的文档
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub document: Document,
+    pub score: f64,  // 相关度，越大越相关
+    pub snippet: String,  // 带 <mark></mark> 高亮的片段
+    pub matches: Vec<SearchMatch>,
+}
+
+/// 匹配位置，start/end 为字段纯文本中的 UTF-16 偏移
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    pub field: String,  // title / body / ai / tags / summary
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 搜索错误：查询语法错误带 UTF-16 位置，前端据此标出出错的片段
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SearchError {
+    Syntax { message: String, start: usize, end: usize },
+    Database { message: String },
+}
+
+impl From<String> for SearchError {
+    fn from(message: String) -> Self {
+        SearchError::Database { message }
+    }
+}
+
+/// 写入文档内容的错误：`Conflict` 表示文档已被其他写入修改，带当前的修订号和内容供前端合并
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum WriteError {
+    Conflict { revision: i64, content: String },
+    Database { message: String },
+}
+
+impl From<String> for WriteError {
+    fn from(message: String) -> Self {
+        WriteError::Database { message }
+    }
+}
+
+/// 链接到某篇文档的来源文档；`text` 为第一处链接的文字，`occurrences` 为链接次数
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Backlink {
+    pub source_id: String,
+    pub source_title: String,
+    pub text: String,
+    pub occurrences: i64,
+}
+
+/// 目标文档已不存在的链接
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenLink {
+    pub source_id: String,
+    pub source_title: String,
+    pub target_id: String,
+    pub text: String,
+    pub occurrences: i64,
+    pub target_title: Option<String>,  // 被删除文档的标题（在回收站中或留了墓碑时才有）
+    pub deleted_at: Option<i64>,
+}
+
+/// 删除文档前的影响报告：链接到它的文档
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteImpact {
+    pub document_id: String,
+    pub title: String,
+    pub backlinks: Vec<Backlink>,
+}
+
+/// 命名快照：一个文件夹或整个工作区在某一时刻的全部文档
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedSnapshot {
+    pub id: String,
+    pub label: String,
+    pub note: Option<String>,
+    pub folder_id: Option<String>,  // None 表示整个工作区
+    pub created_at: i64,
+    pub document_count: i64,
+}
+
+/// 历史版本占用的空间
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionStorageReport {
+    pub version_count: i64,
+    pub snapshot_count: i64,  // 完整快照数，其余为增量
+    pub stored_bytes: i64,  // 实际占用（压缩后）
+    pub original_bytes: i64,  // 未压缩时的内容大小
+    pub documents: Vec<DocumentVersionStorage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentVersionStorage {
+    pub document_id: String,
+    pub title: String,
+    pub version_count: i64,
+    pub snapshot_count: i64,
+    pub stored_bytes: i64,
+    pub original_bytes: i64,
+}
+
+/// 两个版本（或版本与当前内容）之间的差异
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionDiff {
+    pub blocks: Vec<BlockDiff>,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub modifications: usize,
+}
+
+/// 一个块的变化
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDiff {
+    pub kind: String,  // "equal"、"insert"、"delete" 或 "modify"
+    pub block_type: String,  // 节点类型，如 "paragraph"
+    pub old_index: Option<usize>,  // 在旧版本块序列中的位置
+    pub new_index: Option<usize>,
+    pub text: String,  // 块的纯文本（删除时为旧文本，其余为新文本）
+    pub words: Vec<WordDiff>,  // 仅 "modify" 有：词级差异
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WordDiff {
+    pub kind: String,  // "equal"、"insert" 或 "delete"
+    pub text: String,
+}
+
+/// 三方合并的结果，冲突处保留了 `ours` 的内容
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    pub content: String,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// 两边都改过的区域；`base`、`ours`、`theirs` 为各自块数组的 JSON
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub path: Vec<usize>,  // 区域第一个块在合并结果中的位置（逐层的子节点下标）
+    pub count: usize,  // 区域在合并结果中占的块数，即 ours 的块数
+    pub base: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+/// 回收站中的一个条目（单独删除的文档或文件夹）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub id: String,
+    pub kind: String,  // "document" 或 "folder"
+    pub title: String,
+    pub deleted_at: i64,
+    pub path: Vec<String>,  // 删除时所在的文件夹路径，从根开始
+    pub item_count: i64,  // 随文件夹一起删除的子文件夹和文档数
+}
+
+/// 文档链接图，用于可视化
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraph {
+    pub nodes: Vec<LinkGraphNode>,
+    pub edges: Vec<LinkGraphEdge>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraphNode {
+    pub id: String,
+    pub title: String,
+    pub folder_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraphEdge {
+    pub source: String,
+    pub target: String,
+    pub weight: i64,  // 来源文档中链接到目标的次数
+}
+
+/// 导入时无法映射到编辑器结构的内容，`construct` 为 html / footnote / headingLevel 等，`line` 从 1 开始
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportWarning {
+    pub construct: String,
+    pub line: usize,
+    pub detail: Option<String>,  // 例如找不到的链接目标
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub document: Document,
+    pub warnings: Vec<ImportWarning>,
+}
+
+/// 仓库批量导入的结果；dry_run 时不写数据库，document_id 为空
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultImportReport {
+    pub dry_run: bool,
+    pub folders: usize,
+    pub documents: Vec<VaultDocumentReport>,
+    pub skipped: Vec<String>,  // 非 Markdown 文件或无法读取的文件，相对仓库根目录
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultDocumentReport {
+    pub path: String,
+    pub title: String,
+    pub document_id: Option<String>,
+    pub links: usize,  // 转换成文档链接的 [[wikilink]] 数量
+    pub tags: Vec<String>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+/// 工作区导出结果，`path` 为导出目录或 zip 文件
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceExportReport {
+    pub path: String,
+    pub folders: usize,
+    pub documents: usize,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CustomModel {
     pub id: String,
@@ -67,8 +365,26 @@ pub struct Settings {
     pub auto_save: bool,
     pub auto_save_delay: i32,
     pub database_path: Option<String>,
+    pub trash_retention_days: Option<i32>,  // 回收站保留天数，0 表示不自动清理
+    pub version_interval_minutes: Option<i32>,  // 自动保存历史版本的最短间隔（分钟），0 表示关闭
+    pub version_min_change_chars: Option<i32>,  // 单次修改达到这么多字符时不受间隔限制
+    pub version_keep_all_hours: Option<i32>,  // 这么多小时内的版本全部保留
+    pub version_hourly_days: Option<i32>,  // 这么多天内每小时保留一个版本
+    pub version_daily_days: Option<i32>,  // 这么多天内每天保留一个版本，更早的删除
 }
 
+/// 未设置时回收站条目保留的天数
+pub const DEFAULT_TRASH_RETENTION_DAYS: i32 = 30;
+
+/// 未设置时自动保存历史版本的间隔（分钟）和大改动阈值（字符）
+pub const DEFAULT_VERSION_INTERVAL_MINUTES: i32 = 5;
+pub const DEFAULT_VERSION_MIN_CHANGE_CHARS: i32 = 2000;
+
+/// 未设置时的历史版本保留策略：一天内全部保留，一周内每小时一个，一个月内每天一个
+pub const DEFAULT_VERSION_KEEP_ALL_HOURS: i32 = 24;
+pub const DEFAULT_VERSION_HOURLY_DAYS: i32 = 7;
+pub const DEFAULT_VERSION_DAILY_DAYS: i32 = 30;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AIProviderConfig {
     pub provider_type: String,
@@ -101,6 +417,12 @@ impl Default for Settings {
             auto_save: true,
             auto_save_delay: 2000,
             database_path: None,
+            trash_retention_days: Some(DEFAULT_TRASH_RETENTION_DAYS),
+            version_interval_minutes: Some(DEFAULT_VERSION_INTERVAL_MINUTES),
+            version_min_change_chars: Some(DEFAULT_VERSION_MIN_CHANGE_CHARS),
+            version_keep_all_hours: Some(DEFAULT_VERSION_KEEP_ALL_HOURS),
+            version_hourly_days: Some(DEFAULT_VERSION_HOURLY_DAYS),
+            version_daily_days: Some(DEFAULT_VERSION_DAILY_DAYS),
         }
     }
 }
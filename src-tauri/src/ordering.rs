@@ -0,0 +1,265 @@
+//! 同级文件夹、文档的手动排序和每个文件夹的排序方式。
+//!
+//! `sort_order` 存分数索引键：base62 字符串，按字节序比较，且不以 '0' 结尾，
+//! 任意两个键之间总能生成新键，所以拖动排序只需要改被移动的那一行
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// `folder_sort_modes` 中代表根目录的键
+pub const ROOT_FOLDER_KEY: &str = "";
+
+/// 文件夹内文档和子文件夹的排序方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    /// 按 `sort_order` 手动排序
+    Manual,
+    /// 按标题（文件夹按名称）
+    Title,
+    /// 按创建时间，新的在前
+    Created,
+    /// 按更新时间，新的在前（默认）
+    Updated,
+}
+
+impl SortMode {
+    pub fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "manual" => Ok(SortMode::Manual),
+            "title" => Ok(SortMode::Title),
+            "created" => Ok(SortMode::Created),
+            "updated" => Ok(SortMode::Updated),
+            _ => Err(format!("Unsupported sort mode: {}", mode)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortMode::Manual => "manual",
+            SortMode::Title => "title",
+            SortMode::Created => "created",
+            SortMode::Updated => "updated",
+        }
+    }
+}
+
+/// 文件夹的排序方式，`folder_id` 为 None 表示根目录，没有设置时为 `Updated`
+pub fn sort_mode(conn: &Connection, folder_id: Option<&str>) -> Result<SortMode, String> {
+    let mode: Option<String> = conn
+        .query_row(
+            "SELECT sort_mode FROM folder_sort_modes WHERE folder_id = ?1",
+            [folder_id.unwrap_or(ROOT_FOLDER_KEY)],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to query sort mode: {}", e))?;
+    mode.map_or(Ok(SortMode::Updated), |mode| SortMode::parse(&mode))
+}
+
+pub fn set_sort_mode(conn: &Connection, folder_id: Option<&str>, mode: SortMode) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO folder_sort_modes (folder_id, sort_mode) VALUES (?1, ?2)",
+        params![folder_id.unwrap_or(ROOT_FOLDER_KEY), mode.as_str()],
+    ).map_err(|e| format!("Failed to set sort mode: {}", e))?;
+    Ok(())
+}
+
+/// 严格位于 `a` 和 `b` 之间的键，None 表示开头或末尾；要求 `a < b`
+pub fn key_between(a: Option<&str>, b: Option<&str>) -> String {
+    match (a, b) {
+        (Some(a), None) => key_after(a),
+        _ => midpoint(a.unwrap_or("").as_bytes(), b.map(str::as_bytes)),
+    }
+}
+
+/// 按顺序排列的 `n` 个等长键，用于整组重新编号
+pub fn sequence(n: usize) -> Vec<String> {
+    let mut width = 1;
+    while DIGITS.len().pow(width as u32) <= n {
+        width += 1;
+    }
+    (1..=n)
+        .map(|i| {
+            let mut key = vec![b'0'; width];
+            let mut rest = i;
+            for slot in key.iter_mut().rev() {
+                *slot = DIGITS[rest % DIGITS.len()];
+                rest /= DIGITS.len();
+            }
+            // 补一位中间值，保证不以 '0' 结尾
+            key.push(DIGITS[DIGITS.len() / 2]);
+            String::from_utf8(key).unwrap()
+        })
+        .collect()
+}
+
+/// 追加到末尾用的键：第一个不是最大数字的位加一，键长增长得比取中点慢
+fn key_after(a: &str) -> String {
+    let bytes = a.as_bytes();
+    match bytes.iter().position(|&c| digit(c) + 1 < DIGITS.len()) {
+        Some(i) => format!("{}{}", &a[..i], DIGITS[digit(bytes[i]) + 1] as char),
+        None => format!("{}{}", a, DIGITS[DIGITS.len() / 2] as char),
+    }
+}
+
+fn midpoint(a: &[u8], b: Option<&[u8]>) -> String {
+    if let Some(b) = b {
+        // 公共前缀（a 不足的部分按 '0' 补齐）原样保留，在后面的部分取中点
+        let n = b.iter().enumerate().take_while(|&(i, &c)| a.get(i).copied().unwrap_or(b'0') == c).count();
+        if n > 0 {
+            let prefix = String::from_utf8_lossy(&b[..n]);
+            return format!("{}{}", prefix, midpoint(a.get(n..).unwrap_or(&[]), Some(&b[n..])));
+        }
+    }
+    let low = a.first().map_or(0, |&c| digit(c));
+    let high = b.and_then(|b| b.first()).map_or(DIGITS.len(), |&c| digit(c));
+    if high - low > 1 {
+        return (DIGITS[(low + high) / 2] as char).to_string();
+    }
+    match b {
+        // b 去掉后面的位就比 b 小，又比 a 大
+        Some(b) if b.len() > 1 => (b[0] as char).to_string(),
+        _ => format!("{}{}", DIGITS[low] as char, midpoint(a.get(1..).unwrap_or(&[]), None)),
+    }
+}
+
+fn digit(c: u8) -> usize {
+    DIGITS.iter().position(|&d| d == c).unwrap_or(0)
+}
+
+/// `table` 中父级为 `parent_id` 的行追加到末尾用的键。回收站中的行也参与比较，恢复后不会撞键
+pub(crate) fn append_key(conn: &Connection, table: &str, parent_column: &str, parent_id: Option<&str>) -> Result<String, String> {
+    let last: Option<String> = conn
+        .query_row(
+            &format!("SELECT MAX(sort_order) FROM {} WHERE {} IS ?1", table, parent_column),
+            [parent_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to query sort order: {}", e))?;
+    Ok(key_between(last.as_deref(), None))
+}
+
+/// 把 `id` 移到同级的 `before_id` 前面（None 为末尾），只更新这一行
+pub(crate) fn move_before(conn: &Connection, table: &str, parent_column: &str, id: &str, before_id: Option<&str>) -> Result<(), String> {
+    let parent_id: Option<String> = conn
+        .query_row(
+            &format!("SELECT {} FROM {} WHERE id = ?1 AND deleted_at IS NULL", parent_column, table),
+            [id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Item not found: {}", e))?;
+
+    let key = match before_id {
+        None => append_key(conn, table, parent_column, parent_id.as_deref())?,
+        Some(before_id) => {
+            let (before_parent, before_key): (Option<String>, Option<String>) = conn
+                .query_row(
+                    &format!("SELECT {}, sort_order FROM {} WHERE id = ?1 AND deleted_at IS NULL", parent_column, table),
+                    [before_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(|e| format!("Item not found: {}", e))?;
+            if before_parent != parent_id {
+                return Err(format!("{} and {} are not in the same folder", id, before_id));
+            }
+            match before_key {
+                Some(before_key) => {
+                    let previous: Option<String> = conn
+                        .query_row(
+                            &format!(
+                                "SELECT MAX(sort_order) FROM {} WHERE {} IS ?1 AND sort_order < ?2 AND id != ?3",
+                                table, parent_column
+                            ),
+                            params![parent_id, before_key, id],
+                            |row| row.get(0),
+                        )
+                        .map_err(|e| format!("Failed to query sort order: {}", e))?;
+                    key_between(previous.as_deref(), Some(&before_key))
+                }
+                None => append_key(conn, table, parent_column, parent_id.as_deref())?,
+            }
+        }
+    };
+
+    conn.execute(&format!("UPDATE {} SET sort_order = ?1 WHERE id = ?2", table), params![key, id])
+        .map_err(|e| format!("Failed to update sort order: {}", e))?;
+    Ok(())
+}
+
+/// 按现有顺序给每组同级行重新编号（迁移时使用）
+pub(crate) fn renumber(conn: &Connection, table: &str, parent_column: &str, order_by: &str) -> Result<(), String> {
+    let rows: Vec<(Option<String>, String)> = {
+        let mut stmt = conn
+            .prepare(&format!("SELECT {}, id FROM {} ORDER BY {}, {}", parent_column, table, parent_column, order_by))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to query {}: {}", table, e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect {}: {}", table, e))?;
+        rows
+    };
+
+    for group in rows.chunk_by(|a, b| a.0 == b.0) {
+        for ((_, id), key) in group.iter().zip(sequence(group.len())) {
+            conn.execute(&format!("UPDATE {} SET sort_order = ?1 WHERE id = ?2", table), params![key, id])
+                .map_err(|e| format!("Failed to update sort order: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_sort_between_neighbours() {
+        let cases = [
+            (None, None),
+            (Some("V"), None),
+            (None, Some("V")),
+            (Some("V"), Some("W")),
+            (Some("V"), Some("V1")),
+            (Some("01"), Some("0V")),
+            (None, Some("01")),
+            (Some("zz"), None),
+            (Some("a"), Some("a0V")),
+        ];
+        for (a, b) in cases {
+            let key = key_between(a, b);
+            assert!(!key.ends_with('0'), "{:?}", key);
+            if let Some(a) = a {
+                assert!(a < key.as_str(), "{} < {}", a, key);
+            }
+            if let Some(b) = b {
+                assert!(key.as_str() < b, "{} < {}", key, b);
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_inserts_stay_ordered() {
+        // 一直插在最前面、一直插在第一个之后、一直追加
+        let mut keys = vec![key_between(None, None)];
+        for _ in 0..200 {
+            keys.insert(0, key_between(None, Some(&keys[0])));
+            let between = key_between(Some(&keys[0]), Some(&keys[1]));
+            keys.insert(1, between);
+            keys.push(key_between(keys.last().map(|k| k.as_str()), None));
+        }
+        let mut sorted = keys.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, keys);
+        assert!(keys.last().unwrap().len() < 10);
+    }
+
+    #[test]
+    fn sequences_are_ordered_and_fixed_width() {
+        let keys = sequence(100);
+        assert_eq!(keys.len(), 100);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        assert!(keys.iter().all(|k| k.len() == 3 && !k.ends_with('0')));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::links;
+use crate::ordering;
+use crate::models::{Document, DocumentPage, DocumentSummary, WriteError};
+use crate::search;
+use crate::tiptap;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+
+const DOCUMENT_COLUMNS: &str =
+    "id, title, content, created_at, updated_at, context_summary, folder_id, is_pinned, is_important, revision";
+
+const QUALIFIED_DOCUMENT_COLUMNS: &str =
+    "d.id, d.title, d.content, d.created_at, d.updated_at, d.context_summary, d.folder_id, d.is_pinned, d.is_important, d.revision";
+
+/// 预览最多保留的字符数
+const PREVIEW_CHARS: usize = 120;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+
+pub struct DocumentRepo<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> DocumentRepo<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    /// 回收站外的全部文档；置顶的在前，同一文件夹内按该文件夹的排序方式（默认更新时间）
+    pub fn list_all(&self) -> Result<Vec<Document>, String> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT {} FROM documents d
+                 LEFT JOIN folder_sort_modes m ON m.folder_id = COALESCE(d.folder_id, '{}')
+                 WHERE d.deleted_at IS NULL
+                 ORDER BY d.is_pinned DESC,
+                          CASE WHEN m.sort_mode = 'manual' THEN d.sort_order END,
+                          CASE WHEN m.sort_mode = 'title' THEN d.title END COLLATE NOCASE,
+                          CASE WHEN m.sort_mode = 'created' THEN d.created_at END DESC,
+                          d.updated_at DESC",
+                QUALIFIED_DOCUMENT_COLUMNS, ordering::ROOT_FOLDER_KEY
+            ))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let documents = stmt.query_map([], map_document)
+            .map_err(|e| format!("Failed to query documents: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect documents: {}", e))?;
+
+        Ok(documents)
+    }
+
+    /// 回收站外文档的摘要，置顶的在前、按更新时间倒序分页。
+    /// `folder_id` 为 None 时不过滤，为 `ROOT_FOLDER_KEY` 时只取根目录；
+    /// `cursor` 是上一页返回的 `next_cursor`
+    pub fn list_page(&self, folder_id: Option<&str>, cursor: Option<&str>, limit: Option<usize>) -> Result<DocumentPage, String> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let mut conditions = vec!["deleted_at IS NULL".to_string()];
+        let mut values: Vec<SqlValue> = Vec::new();
+        match folder_id {
+            None => {}
+            Some(ordering::ROOT_FOLDER_KEY) => conditions.push("folder_id IS NULL".to_string()),
+            Some(folder_id) => {
+                values.push(SqlValue::Text(folder_id.to_string()));
+                conditions.push(format!("folder_id = ?{}", values.len()));
+            }
+        }
+        if let Some(cursor) = cursor {
+            let (pinned, updated_at, id) = parse_cursor(cursor)?;
+            values.extend([SqlValue::Integer(pinned), SqlValue::Integer(updated_at), SqlValue::Text(id)]);
+            let n = values.len();
+            conditions.push(format!(
+                "(COALESCE(is_pinned, 0), updated_at, id) < (?{}, ?{}, ?{})",
+                n - 2, n - 1, n
+            ));
+        }
+        values.push(SqlValue::Integer(limit as i64 + 1));
+
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT id, title, folder_id, COALESCE(is_pinned, 0), COALESCE(is_important, 0),
+                        created_at, updated_at, word_count, preview
+                 FROM documents
+                 WHERE {}
+                 ORDER BY COALESCE(is_pinned, 0) DESC, updated_at DESC, id DESC
+                 LIMIT ?{}",
+                conditions.join(" AND "),
+                values.len()
+            ))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let mut items = stmt.query_map(params_from_iter(values), map_summary)
+            .map_err(|e| format!("Failed to query documents: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect documents: {}", e))?;
+
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items.last().map(|last| format!("{}:{}:{}", last.is_pinned as i64, last.updated_at, last.id))
+        } else {
+            None
+        };
+        Ok(DocumentPage { items, next_cursor })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Document, String> {
+        self.conn
+            .query_row(&format!("SELECT {} FROM documents WHERE id = ?1", DOCUMENT_COLUMNS), [id], map_document)
+            .map_err(|e| format!("Document not found: {}", e))
+    }
+
+    pub fn create(&self, title: &str, folder_id: Option<&str>) -> Result<Document, String> {
+        self.create_with_content(title, folder_id, r#"{"type":"doc","content":[]}"#)
+    }
+
+    pub fn create_with_content(&self, title: &str, folder_id: Option<&str>, content: &str) -> Result<Document, String> {
+        let now = chrono::Utc::now().timestamp();
+        let document = Document {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            content: content.to_string(),
+            created_at: now,
+            updated_at: now,
+            context_summary: None,
+            folder_id: folder_id.map(|s| s.to_string()),
+            is_pinned: Some(false),
+            is_important: Some(false),
+            revision: 0,
+        };
+        self.insert(&document)?;
+        Ok(document)
+    }
+
+    /// 按给定的 id、时间戳写入（导入时保留原文件的时间）
+    pub fn insert(&self, document: &Document) -> Result<(), String> {
+        let sort_order = ordering::append_key(self.conn, "documents", "folder_id", document.folder_id.as_deref())?;
+        let (word_count, preview) = summarize(&document.content);
+        self.conn.execute(
+            "INSERT INTO documents (id, title, content, created_at, updated_at, context_summary, folder_id, is_pinned, is_important, sort_order, word_count, preview) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                &document.id,
+                &document.title,
+                &document.content,
+                document.created_at,
+                document.updated_at,
+                document.context_summary.as_deref().unwrap_or(""),
+                &document.folder_id,
+                document.is_pinned.unwrap_or(false),
+                document.is_important.unwrap_or(false),
+                sort_order,
+                word_count,
+                preview,
+            ],
+        ).map_err(|e| format!("Failed to insert document: {}", e))?;
+        search::index_document(self.conn, &document.id)?;
+        links::index_document(self.conn, &document.id)?;
+        links::clear_tombstone(self.conn, &document.id)
+    }
+
+    /// 写入内容并把修订号加一，返回新的修订号
+    /// 写入内容并返回新的修订号；文档不存在或在回收站中时报错
+    pub fn update_content(&self, id: &str, content: &str) -> Result<i64, String> {
+        let now = chrono::Utc::now().timestamp();
+        let (word_count, preview) = summarize(content);
+        let revision = self.conn
+            .query_row(
+                "UPDATE documents SET content = ?1, updated_at = ?2, word_count = ?3, preview = ?4, revision = revision + 1
+                 WHERE id = ?5 AND deleted_at IS NULL RETURNING revision",
+                params![content, now, word_count, preview, id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to update document: {}", e))?
+            .ok_or_else(|| format!("Document not found or in trash: {}", id))?;
+        search::index_document(self.conn, id)?;
+        links::index_document(self.conn, id)?;
+        Ok(revision)
+    }
+
+    /// 乐观并发检查：当前修订号不是 `expected` 时返回带当前内容的冲突
+    pub fn check_revision(&self, id: &str, expected: i64) -> Result<(), WriteError> {
+        let (revision, content) = self.conn
+            .query_row("SELECT revision, content FROM documents WHERE id = ?1", [id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Document not found: {}", e))?;
+        if revision != expected {
+            return Err(WriteError::Conflict { revision, content });
+        }
+        Ok(())
+    }
+
+    pub fn update_title(&self, id: &str, title: &str) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE documents SET title = ?1, updated_at = ?2 WHERE id = ?3",
+            params![title, now, id],
+        ).map_err(|e| format!("Failed to update document title: {}", e))?;
+        search::index_document(self.conn, id)
+    }
+
+    pub fn update_context_summary(&self, id: &str, summary: &str) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE documents SET context_summary = ?1, updated_at = ?2 WHERE id = ?3",
+            params![summary, now, id],
+        ).map_err(|e| format!("Failed to update context summary: {}", e))?;
+        search::index_document(self.conn, id)
+    }
+
+    /// 永久删除文档及其标签和历史版本（旧数据库的表没有外键，不能依赖 ON DELETE CASCADE）
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        self.conn.execute("DELETE FROM documents WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete document: {}", e))?;
+        self.conn.execute("DELETE FROM tags WHERE document_id = ?1", [id])
+            .map_err(|e| format!("Failed to delete document tags: {}", e))?;
+        self.conn.execute("DELETE FROM document_versions WHERE document_id = ?1", [id])
+            .map_err(|e| format!("Failed to delete document versions: {}", e))?;
+        search::remove_document(self.conn, id)?;
+        links::remove_document(self.conn, id)
+    }
+
+    /// 移到另一个文件夹，排在末尾；文档不存在或在回收站中时报错，目标文件夹由调用方检查
+    pub fn move_to_folder(&self, id: &str, folder_id: Option<&str>) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+        let sort_order = ordering::append_key(self.conn, "documents", "folder_id", folder_id)?;
+        let updated = self.conn.execute(
+            "UPDATE documents SET folder_id = ?1, updated_at = ?2, sort_order = ?3 WHERE id = ?4 AND deleted_at IS NULL",
+            params![folder_id, now, sort_order, id],
+        ).map_err(|e| format!("Failed to move document: {}", e))?;
+        if updated == 0 {
+            return Err(format!("Document not found or in trash: {}", id));
+        }
+        Ok(())
+    }
+
+    /// 手动排序：移到同一文件夹中 `before_id` 的前面（None 为末尾）
+    pub fn reorder(&self, id: &str, before_id: Option<&str>) -> Result<(), String> {
+        ordering::move_before(self.conn, "documents", "folder_id", id, before_id)
+    }
+
+    pub fn toggle_pinned(&self, id: &str) -> Result<(), String> {
+        self.conn.execute(
+            "UPDATE documents SET is_pinned = CASE WHEN is_pinned = 1 THEN 0 ELSE 1 END WHERE id = ?1",
+            [id],
+        ).map_err(|e| format!("Failed to toggle pin: {}", e))?;
+        Ok(())
+    }
+
+    pub fn toggle_important(&self, id: &str) -> Result<(), String> {
+        self.conn.execute(
+            "UPDATE documents SET is_important = CASE WHEN is_important = 1 THEN 0 ELSE 1 END WHERE id = ?1",
+            [id],
+        ).map_err(|e| format!("Failed to toggle important: {}", e))?;
+        Ok(())
+    }
+}
+
+fn map_document(row: &Row) -> rusqlite::Result<Document> {
+    Ok(Document {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        content: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+        context_summary: row.get(5)?,
+        folder_id: row.get(6)?,
+        is_pinned: row.get(7).ok(),
+        is_important: row.get(8).ok(),
+        revision: row.get(9)?,
+    })
+}
+
+fn map_summary(row: &Row) -> rusqlite::Result<DocumentSummary> {
+    Ok(DocumentSummary {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        folder_id: row.get(2)?,
+        is_pinned: row.get(3)?,
+        is_important: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+        word_count: row.get(7)?,
+        preview: row.get(8)?,
+    })
+}
+
+/// 游标格式为 `is_pinned:updated_at:id`
+fn parse_cursor(cursor: &str) -> Result<(i64, i64, String), String> {
+    let mut parts = cursor.splitn(3, ':');
+    let invalid = || format!("Invalid cursor: {}", cursor);
+    let pinned = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+    let updated_at = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+    let id = parts.next().ok_or_else(invalid)?;
+    Ok((pinned, updated_at, id.to_string()))
+}
+
+/// 由内容计算字数和预览（正文第一个非空行）；内容不是合法 JSON 时为空
+fn summarize(content: &str) -> (i64, String) {
+    let body = tiptap::parse(content).map(|doc| tiptap::plain_text(&doc).body).unwrap_or_default();
+    let first_line = body.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    let mut preview: String = first_line.chars().take(PREVIEW_CHARS).collect();
+    if first_line.chars().nth(PREVIEW_CHARS).is_some() {
+        preview.push('…');
+    }
+    (search::word_count(&body) as i64, preview)
+}
+
+/// 重新计算全部文档的字数和预览（迁移时使用）
+pub(crate) fn rebuild_summaries(conn: &Connection) -> Result<(), String> {
+    let rows: Vec<(String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, content FROM documents")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to query documents: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect documents: {}", e))?;
+        rows
+    };
+    for (id, content) in rows {
+        let (word_count, preview) = summarize(&content);
+        conn.execute(
+            "UPDATE documents SET word_count = ?1, preview = ?2 WHERE id = ?3",
+            params![word_count, preview, id],
+        ).map_err(|e| format!("Failed to update document summary: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{test_connection, TrashRepo};
+
+    #[test]
+    fn create_update_and_list() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+
+        let doc = repo.create("Draft", None).unwrap();
+        repo.update_content(&doc.id, r#"{"type":"doc","content":[{"type":"paragraph"}]}"#).unwrap();
+        repo.update_title(&doc.id, "Final").unwrap();
+        repo.toggle_pinned(&doc.id).unwrap();
+
+        let stored = repo.get(&doc.id).unwrap();
+        assert_eq!(stored.title, "Final");
+        assert!(stored.content.contains("paragraph"));
+        assert_eq!(stored.is_pinned, Some(true));
+        assert_eq!(repo.list_all().unwrap().len(), 1);
+
+        repo.delete(&doc.id).unwrap();
+        assert!(repo.get(&doc.id).is_err());
+    }
+
+    #[test]
+    fn list_follows_folder_sort_mode() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let b = repo.create("b", None).unwrap();
+        let a = repo.create("A", None).unwrap();
+        let c = repo.create("c", None).unwrap();
+        conn.execute("UPDATE documents SET updated_at = 300, created_at = 1 WHERE id = ?1", [&b.id]).unwrap();
+        conn.execute("UPDATE documents SET updated_at = 200, created_at = 3 WHERE id = ?1", [&a.id]).unwrap();
+        conn.execute("UPDATE documents SET updated_at = 100, created_at = 2 WHERE id = ?1", [&c.id]).unwrap();
+        let titles = || repo.list_all().unwrap().into_iter().map(|d| d.title).collect::<Vec<_>>();
+
+        assert_eq!(titles(), vec!["b", "A", "c"]);
+        ordering::set_sort_mode(&conn, None, ordering::SortMode::Title).unwrap();
+        assert_eq!(titles(), vec!["A", "b", "c"]);
+        ordering::set_sort_mode(&conn, None, ordering::SortMode::Created).unwrap();
+        assert_eq!(titles(), vec!["A", "c", "b"]);
+
+        // 手动排序：创建顺序 b、A、c，把 c 移到 b 前面，再把 b 移到末尾
+        ordering::set_sort_mode(&conn, None, ordering::SortMode::Manual).unwrap();
+        assert_eq!(titles(), vec!["b", "A", "c"]);
+        repo.reorder(&c.id, Some(&b.id)).unwrap();
+        assert_eq!(titles(), vec!["c", "b", "A"]);
+        repo.reorder(&b.id, None).unwrap();
+        assert_eq!(titles(), vec!["c", "A", "b"]);
+
+        repo.toggle_pinned(&b.id).unwrap();
+        assert_eq!(titles(), vec!["b", "c", "A"]);
+    }
+
+    #[test]
+    fn list_page_pages_through_summaries() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let folder = crate::repo::FolderRepo::new(&conn).create("Notes", None).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let folder_id = if i % 2 == 0 { None } else { Some(folder.id.as_str()) };
+            let doc = repo.create(&format!("Doc {}", i), folder_id).unwrap();
+            conn.execute("UPDATE documents SET updated_at = ?1 WHERE id = ?2", params![i, doc.id]).unwrap();
+            ids.push(doc.id);
+        }
+        repo.toggle_pinned(&ids[0]).unwrap();
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = repo.list_page(None, cursor.as_deref(), Some(2)).unwrap();
+            assert!(page.items.len() <= 2);
+            seen.extend(page.items.into_iter().map(|d| d.title));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(seen, vec!["Doc 0", "Doc 4", "Doc 3", "Doc 2", "Doc 1"]);
+
+        let titles = |folder_id| {
+            repo.list_page(Some(folder_id), None, None).unwrap().items.into_iter().map(|d| d.title).collect::<Vec<_>>()
+        };
+        assert_eq!(titles(folder.id.as_str()), vec!["Doc 3", "Doc 1"]);
+        assert_eq!(titles(ordering::ROOT_FOLDER_KEY), vec!["Doc 0", "Doc 4", "Doc 2"]);
+        assert!(repo.list_page(None, Some("garbage"), None).is_err());
+    }
+
+    #[test]
+    fn summaries_track_content() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let doc = repo.create("Draft", None).unwrap();
+        repo.update_content(
+            &doc.id,
+            r#"{"type":"doc","content":[
+                {"type":"paragraph"},
+                {"type":"paragraph","content":[{"type":"text","text":"Hello world"}]},
+                {"type":"paragraph","content":[{"type":"text","text":"你好"}]}
+            ]}"#,
+        ).unwrap();
+
+        let summary = repo.list_page(None, None, None).unwrap().items.remove(0);
+        assert_eq!(summary.preview, "Hello world");
+        assert_eq!(summary.word_count, 4);
+        assert!(!summary.is_pinned);
+    }
+
+    #[test]
+    fn stale_revisions_conflict() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let doc = repo.create_with_content("Draft", None, "v0").unwrap();
+        assert_eq!(doc.revision, 0);
+
+        repo.check_revision(&doc.id, 0).unwrap();
+        assert_eq!(repo.update_content(&doc.id, "v1").unwrap(), 1);
+        assert_eq!(repo.get(&doc.id).unwrap().revision, 1);
+        assert_eq!(
+            repo.check_revision(&doc.id, 0),
+            Err(WriteError::Conflict { revision: 1, content: "v1".to_string() })
+        );
+        assert!(matches!(repo.check_revision("missing", 0), Err(WriteError::Database { .. })));
+    }
+
+    #[test]
+    fn trashed_documents_reject_writes() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let target = repo.create("Target", None).unwrap();
+        let source = repo.create("Source", None).unwrap();
+        TrashRepo::new(&conn).trash_document(&source.id).unwrap();
+
+        let content = format!(
+            r#"{{"type":"doc","content":[{{"type":"paragraph","content":[{{"type":"text","text":"t","marks":[{{"type":"documentLink","attrs":{{"documentId":"{}","title":null}}}}]}}]}}]}}"#,
+            target.id
+        );
+        assert!(repo.update_content(&source.id, &content).is_err());
+        assert!(repo.update_content("missing", &content).is_err());
+        assert_eq!(repo.get(&source.id).unwrap().revision, 0);
+        assert!(links::backlinks(&conn, &target.id).unwrap().is_empty());
+    }
+}
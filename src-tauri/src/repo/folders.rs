@@ -0,0 +1,577 @@
+use crate::models::{Breadcrumb, Folder, FolderTree, FolderTreeNode};
+use crate::ordering;
+use crate::repo::{DocumentRepo, TrashRepo};
+use rusqlite::{params, Connection, Row};
+use std::collections::{HashMap, HashSet};
+
+/// 文件夹最多嵌套的层数（根目录下的文件夹为第 1 层）
+pub const MAX_FOLDER_DEPTH: i64 = 16;
+
+/// 沿 parent_id 向上追溯的最大层数，防止数据里已有环时死循环
+pub(crate) const MAX_PATH_DEPTH: i64 = 64;
+
+/// 删除文件夹时如何处理其中的子文件夹和文档
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FolderDeleteMode {
+    /// 移到上级文件夹
+    MoveToParent,
+    /// 移到根目录
+    MoveToRoot,
+    /// 连同整棵子树一起移入回收站
+    Recursive,
+}
+
+impl FolderDeleteMode {
+    pub fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "parent" => Ok(FolderDeleteMode::MoveToParent),
+            "root" => Ok(FolderDeleteMode::MoveToRoot),
+            "recursive" => Ok(FolderDeleteMode::Recursive),
+            _ => Err(format!("Unsupported folder delete mode: {}", mode)),
+        }
+    }
+}
+
+pub struct FolderRepo<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> FolderRepo<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    /// 回收站外的全部文件夹；上级文件夹为手动排序时按 `sort_order`，否则按名称
+    pub fn list_all(&self) -> Result<Vec<Folder>, String> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT f.id, f.name, f.parent_id, f.created_at, f.updated_at FROM folders f
+                 LEFT JOIN folder_sort_modes m ON m.folder_id = COALESCE(f.parent_id, '{}')
+                 WHERE f.deleted_at IS NULL
+                 ORDER BY CASE WHEN m.sort_mode = 'manual' THEN f.sort_order END, f.name ASC",
+                ordering::ROOT_FOLDER_KEY
+            ))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let folders = stmt.query_map([], map_folder)
+            .map_err(|e| format!("Failed to query folders: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect folders: {}", e))?;
+
+        Ok(folders)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Folder, String> {
+        self.conn
+            .query_row("SELECT id, name, parent_id, created_at, updated_at FROM folders WHERE id = ?1", [id], map_folder)
+            .map_err(|e| format!("Folder not found: {}", e))
+    }
+
+    /// 回收站外的嵌套文件夹树，附带直接和递归的文档数、子树中最新文档的更新时间
+    pub fn tree(&self) -> Result<FolderTree, String> {
+        let folders = self.list_all()?;
+
+        let mut stmt = self.conn
+            .prepare("SELECT folder_id, COUNT(*), MAX(updated_at) FROM documents WHERE deleted_at IS NULL GROUP BY folder_id")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let stats = stmt.query_map([], |row| Ok((row.get::<_, Option<String>>(0)?, (row.get(1)?, row.get(2)?))))
+            .map_err(|e| format!("Failed to query document counts: {}", e))?
+            .collect::<Result<HashMap<Option<String>, (i64, i64)>, _>>()
+            .map_err(|e| format!("Failed to collect document counts: {}", e))?;
+
+        // 上级不存在或在回收站中的文件夹挂到根目录
+        let ids: HashSet<String> = folders.iter().map(|f| f.id.clone()).collect();
+        let mut children: HashMap<Option<String>, Vec<Folder>> = HashMap::new();
+        for folder in folders {
+            let parent_id = folder.parent_id.clone().filter(|id| ids.contains(id));
+            children.entry(parent_id).or_default().push(folder);
+        }
+
+        Ok(FolderTree {
+            folders: build_tree(None, &mut children, &stats),
+            root_document_count: stats.get(&None).map_or(0, |(count, _)| *count),
+        })
+    }
+
+    /// 从根开始到 `id` 的文件夹路径（含自身）
+    pub fn path(&self, id: &str) -> Result<Vec<Breadcrumb>, String> {
+        let mut stmt = self.conn
+            .prepare(
+                "WITH RECURSIVE chain(id, name, parent_id, depth) AS (
+                     SELECT id, name, parent_id, 0 FROM folders WHERE id = ?1
+                     UNION ALL
+                     SELECT f.id, f.name, f.parent_id, c.depth + 1
+                     FROM folders f JOIN chain c ON f.id = c.parent_id
+                     WHERE c.depth < ?2
+                 )
+                 SELECT id, name FROM chain ORDER BY depth DESC",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let path = stmt.query_map(params![id, MAX_PATH_DEPTH], |row| {
+                Ok(Breadcrumb {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    kind: "folder".to_string(),
+                })
+            })
+            .map_err(|e| format!("Failed to query folder path: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect folder path: {}", e))?;
+        Ok(path)
+    }
+
+    /// 文档或文件夹的面包屑：从根开始的各级文件夹，最后一级是它自己
+    pub fn breadcrumbs(&self, id: &str) -> Result<Vec<Breadcrumb>, String> {
+        if let Ok(document) = DocumentRepo::new(self.conn).get(id) {
+            let mut crumbs = match &document.folder_id {
+                Some(folder_id) => self.path(folder_id)?,
+                None => Vec::new(),
+            };
+            crumbs.push(Breadcrumb {
+                id: document.id,
+                name: document.title,
+                kind: "document".to_string(),
+            });
+            return Ok(crumbs);
+        }
+        self.get(id)?;
+        self.path(id)
+    }
+
+    /// 在 `parent_id` 下新建文件夹（None 为根目录），上级必须存在且不在回收站中，也不能超过嵌套层数上限
+    pub fn create(&self, name: &str, parent_id: Option<&str>) -> Result<Folder, String> {
+        if let Some(parent_id) = parent_id {
+            self.ensure_live(parent_id)?;
+            if self.path(parent_id)?.len() as i64 >= MAX_FOLDER_DEPTH {
+                return Err(format!("Cannot create folder {}: folders can be nested at most {} levels deep", name, MAX_FOLDER_DEPTH));
+            }
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        let sort_order = ordering::append_key(self.conn, "folders", "parent_id", parent_id)?;
+        self.conn.execute(
+            "INSERT INTO folders (id, name, parent_id, created_at, updated_at, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![&id, name, parent_id, now, now, sort_order],
+        ).map_err(|e| format!("Failed to insert folder: {}", e))?;
+
+        Ok(Folder {
+            id,
+            name: name.to_string(),
+            parent_id: parent_id.map(|s| s.to_string()),
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    pub fn rename(&self, id: &str, name: &str) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE folders SET name = ?1, updated_at = ?2 WHERE id = ?3",
+            params![name, now, id],
+        ).map_err(|e| format!("Failed to update folder: {}", e))?;
+        Ok(())
+    }
+
+    /// 把文件夹移到 `parent_id` 下（None 为根目录），不能移到自身或子孙文件夹下，也不能超过嵌套层数上限
+    pub fn move_to(&self, id: &str, parent_id: Option<&str>) -> Result<(), String> {
+        self.ensure_live(id)?;
+        let parent_depth = match parent_id {
+            Some(parent_id) => {
+                self.ensure_live(parent_id)?;
+                let ancestors = self.path(parent_id)?;
+                if ancestors.iter().any(|ancestor| ancestor.id == id) {
+                    return Err(format!("Cannot move folder {} into itself or one of its subfolders", id));
+                }
+                ancestors.len() as i64
+            }
+            None => 0,
+        };
+        if parent_depth + self.subtree_height(id)? > MAX_FOLDER_DEPTH {
+            return Err(format!("Cannot move folder {}: folders can be nested at most {} levels deep", id, MAX_FOLDER_DEPTH));
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let sort_order = ordering::append_key(self.conn, "folders", "parent_id", parent_id)?;
+        self.conn.execute(
+            "UPDATE folders SET parent_id = ?1, updated_at = ?2, sort_order = ?3 WHERE id = ?4",
+            params![parent_id, now, sort_order, id],
+        ).map_err(|e| format!("Failed to move folder: {}", e))?;
+        Ok(())
+    }
+
+    /// 手动排序：移到同级 `before_id` 的前面（None 为末尾）
+    pub fn reorder(&self, id: &str, before_id: Option<&str>) -> Result<(), String> {
+        ordering::move_before(self.conn, "folders", "parent_id", id, before_id)
+    }
+
+    /// 把文档移到文件夹 `target`（None 为根目录），文件夹和文档都必须存在且不在回收站中
+    pub fn move_document(&self, id: &str, target: Option<&str>) -> Result<(), String> {
+        if let Some(target) = target {
+            self.ensure_live(target)?;
+        }
+        DocumentRepo::new(self.conn).move_to_folder(id, target)
+    }
+
+    /// 把一批文件夹和文档移到 `target`，任何一项失败都返回错误，调用方负责事务
+    pub fn move_items(&self, folder_ids: &[String], document_ids: &[String], target: Option<&str>) -> Result<(), String> {
+        if let Some(target) = target {
+            self.ensure_live(target)?;
+        }
+        for id in folder_ids {
+            self.move_to(id, target)?;
+        }
+        for id in document_ids {
+            self.move_document(id, target)?;
+        }
+        Ok(())
+    }
+
+    fn ensure_live(&self, id: &str) -> Result<(), String> {
+        let count: i64 = self.conn
+            .query_row("SELECT COUNT(*) FROM folders WHERE id = ?1 AND deleted_at IS NULL", [id], |row| row.get(0))
+            .map_err(|e| format!("Failed to query folder: {}", e))?;
+        if count == 0 {
+            return Err(format!("Folder not found: {}", id));
+        }
+        Ok(())
+    }
+
+    /// 以 `id` 为根的子树层数，只有自身时为 1
+    fn subtree_height(&self, id: &str) -> Result<i64, String> {
+        self.conn
+            .query_row(
+                "WITH RECURSIVE subtree(id, depth) AS (
+                     SELECT ?1, 1
+                     UNION
+                     SELECT f.id, s.depth + 1 FROM folders f JOIN subtree s ON f.parent_id = s.id
+                     WHERE f.deleted_at IS NULL AND s.depth <= ?2
+                 )
+                 SELECT MAX(depth) FROM subtree",
+                params![id, MAX_FOLDER_DEPTH],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to query folder subtree: {}", e))
+    }
+
+    /// 按 `mode` 处理子文件夹和文档后把文件夹移入回收站，调用方负责事务
+    pub fn trash(&self, id: &str, mode: FolderDeleteMode) -> Result<(), String> {
+        let folder = self.get(id)?;
+        let target = match mode {
+            // 上级不存在（旧版本级联删除留下的孤儿）时按根目录处理
+            FolderDeleteMode::MoveToParent => folder.parent_id.filter(|parent_id| self.get(parent_id).is_ok()),
+            FolderDeleteMode::MoveToRoot | FolderDeleteMode::Recursive => None,
+        };
+        if mode != FolderDeleteMode::Recursive {
+            self.move_children(id, target.as_deref())?;
+        }
+        TrashRepo::new(self.conn).trash_folder(id)
+    }
+
+    /// 把直接子文件夹和文档（不含回收站中的）按原顺序移到 `target` 的末尾
+    fn move_children(&self, id: &str, target: Option<&str>) -> Result<(), String> {
+        for (table, parent_column) in [("folders", "parent_id"), ("documents", "folder_id")] {
+            let mut stmt = self.conn
+                .prepare(&format!(
+                    "SELECT id FROM {} WHERE {} = ?1 AND deleted_at IS NULL ORDER BY sort_order",
+                    table, parent_column
+                ))
+                .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+            let children = stmt.query_map([id], |row| row.get::<_, String>(0))
+                .map_err(|e| format!("Failed to query {}: {}", table, e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to collect {}: {}", table, e))?;
+
+            for child in children {
+                let sort_order = ordering::append_key(self.conn, table, parent_column, target)?;
+                self.conn.execute(
+                    &format!("UPDATE {} SET {} = ?1, sort_order = ?2 WHERE id = ?3", table, parent_column),
+                    params![target, sort_order, child],
+                ).map_err(|e| format!("Failed to move {}: {}", table, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 永久删除文件夹这一行，其中的内容由调用方处理。
+    /// 仍指向它的子项（回收站中单独删除的条目）先挂到根目录：新建的表有 ON DELETE CASCADE，
+    /// 旧表没有外键，两种情况都不能让子项跟着消失或留下失效的 parent_id，恢复时按原路径重建
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        self.conn.execute("UPDATE folders SET parent_id = NULL WHERE parent_id = ?1", [id])
+            .map_err(|e| format!("Failed to detach subfolders: {}", e))?;
+        self.conn.execute("UPDATE documents SET folder_id = NULL WHERE folder_id = ?1", [id])
+            .map_err(|e| format!("Failed to detach documents: {}", e))?;
+        self.conn.execute("DELETE FROM folders WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete folder: {}", e))?;
+        Ok(())
+    }
+}
+
+/// 逐层取出 `parent_id` 的子文件夹组装节点；每组子文件夹只取一次，数据里有环也不会死循环
+fn build_tree(
+    parent_id: Option<&str>,
+    children: &mut HashMap<Option<String>, Vec<Folder>>,
+    stats: &HashMap<Option<String>, (i64, i64)>,
+) -> Vec<FolderTreeNode> {
+    let Some(folders) = children.remove(&parent_id.map(|id| id.to_string())) else {
+        return Vec::new();
+    };
+    folders
+        .into_iter()
+        .map(|folder| {
+            let nodes = build_tree(Some(&folder.id), children, stats);
+            let (count, newest) = match stats.get(&Some(folder.id.clone())) {
+                Some((count, newest)) => (*count, Some(*newest)),
+                None => (0, None),
+            };
+            FolderTreeNode {
+                document_count: count,
+                total_document_count: count + nodes.iter().map(|n| n.total_document_count).sum::<i64>(),
+                last_modified: nodes.iter().filter_map(|n| n.last_modified).chain(newest).max(),
+                folder,
+                children: nodes,
+            }
+        })
+        .collect()
+}
+
+fn map_folder(row: &Row) -> rusqlite::Result<Folder> {
+    Ok(Folder {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        parent_id: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{test_connection, DocumentRepo};
+
+    /// 建 Root/Work/{Sub, Doc}，Sub 下有 Nested，返回 (root, work, sub)
+    fn tree(conn: &Connection) -> (Folder, Folder, Folder) {
+        let folders = FolderRepo::new(conn);
+        let docs = DocumentRepo::new(conn);
+        let root = folders.create("Root", None).unwrap();
+        let work = folders.create("Work", Some(&root.id)).unwrap();
+        let sub = folders.create("Sub", Some(&work.id)).unwrap();
+        docs.create("Doc", Some(&work.id)).unwrap();
+        docs.create("Nested", Some(&sub.id)).unwrap();
+        (root, work, sub)
+    }
+
+    fn location(conn: &Connection, title: &str) -> Option<String> {
+        DocumentRepo::new(conn).list_all().unwrap().into_iter().find(|d| d.title == title).unwrap().folder_id
+    }
+
+    #[test]
+    fn move_to_parent() {
+        let conn = test_connection();
+        let (root, work, sub) = tree(&conn);
+        let folders = FolderRepo::new(&conn);
+
+        folders.trash(&work.id, FolderDeleteMode::MoveToParent).unwrap();
+        assert_eq!(folders.get(&sub.id).unwrap().parent_id, Some(root.id.clone()));
+        assert_eq!(location(&conn, "Doc"), Some(root.id));
+        assert_eq!(location(&conn, "Nested"), Some(sub.id));
+        assert_eq!(folders.list_all().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn move_to_root() {
+        let conn = test_connection();
+        let (_, work, sub) = tree(&conn);
+        let folders = FolderRepo::new(&conn);
+
+        folders.trash(&work.id, FolderDeleteMode::MoveToRoot).unwrap();
+        assert_eq!(folders.get(&sub.id).unwrap().parent_id, None);
+        assert_eq!(location(&conn, "Doc"), None);
+        assert_eq!(location(&conn, "Nested"), Some(sub.id));
+    }
+
+    #[test]
+    fn recursive_delete_takes_the_subtree() {
+        let conn = test_connection();
+        let (root, work, _) = tree(&conn);
+        let folders = FolderRepo::new(&conn);
+
+        folders.trash(&work.id, FolderDeleteMode::Recursive).unwrap();
+        let names: Vec<String> = folders.list_all().unwrap().into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["Root"]);
+        assert!(DocumentRepo::new(&conn).list_all().unwrap().is_empty());
+
+        folders.trash(&root.id, FolderDeleteMode::Recursive).unwrap();
+        assert!(folders.list_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tree_counts_documents_recursively() {
+        let conn = test_connection();
+        let (root, work, sub) = tree(&conn);
+        let docs = DocumentRepo::new(&conn);
+        docs.create("Loose", None).unwrap();
+        let gone = docs.create("Gone", Some(&sub.id)).unwrap();
+        TrashRepo::new(&conn).trash_document(&gone.id).unwrap();
+        conn.execute("UPDATE documents SET updated_at = 100 WHERE title = 'Doc'", []).unwrap();
+        conn.execute("UPDATE documents SET updated_at = 200 WHERE title = 'Nested'", []).unwrap();
+
+        let tree = FolderRepo::new(&conn).tree().unwrap();
+        assert_eq!(tree.root_document_count, 1);
+        assert_eq!(tree.folders.len(), 1);
+        let root_node = &tree.folders[0];
+        assert_eq!(root_node.folder.id, root.id);
+        assert_eq!((root_node.document_count, root_node.total_document_count), (0, 2));
+        assert_eq!(root_node.last_modified, Some(200));
+        let work_node = &root_node.children[0];
+        assert_eq!(work_node.folder.id, work.id);
+        assert_eq!((work_node.document_count, work_node.total_document_count), (1, 2));
+        let sub_node = &work_node.children[0];
+        assert_eq!((sub_node.document_count, sub_node.total_document_count), (1, 1));
+        assert!(sub_node.children.is_empty());
+    }
+
+    #[test]
+    fn breadcrumbs_for_documents_and_folders() {
+        let conn = test_connection();
+        let (_, _, sub) = tree(&conn);
+        let folders = FolderRepo::new(&conn);
+        let nested = DocumentRepo::new(&conn).list_all().unwrap().into_iter().find(|d| d.title == "Nested").unwrap();
+
+        let names = |crumbs: Vec<Breadcrumb>| crumbs.into_iter().map(|c| format!("{}:{}", c.kind, c.name)).collect::<Vec<_>>();
+        assert_eq!(names(folders.breadcrumbs(&sub.id).unwrap()), vec!["folder:Root", "folder:Work", "folder:Sub"]);
+        assert_eq!(
+            names(folders.breadcrumbs(&nested.id).unwrap()),
+            vec!["folder:Root", "folder:Work", "folder:Sub", "document:Nested"]
+        );
+        assert!(folders.breadcrumbs("missing").is_err());
+    }
+
+    #[test]
+    fn manual_folder_order() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let b = folders.create("b", None).unwrap();
+        let a = folders.create("a", None).unwrap();
+        let c = folders.create("c", None).unwrap();
+        let names = || folders.list_all().unwrap().into_iter().map(|f| f.name).collect::<Vec<_>>();
+
+        assert_eq!(names(), vec!["a", "b", "c"]);
+        ordering::set_sort_mode(&conn, None, ordering::SortMode::Manual).unwrap();
+        assert_eq!(names(), vec!["b", "a", "c"]);
+        folders.reorder(&c.id, Some(&a.id)).unwrap();
+        assert_eq!(names(), vec!["b", "c", "a"]);
+        assert!(folders.reorder(&a.id, Some(&folders.create("x", Some(&b.id)).unwrap().id)).is_err());
+    }
+
+    #[test]
+    fn move_rejects_cycles() {
+        let conn = test_connection();
+        let (root, work, sub) = tree(&conn);
+        let folders = FolderRepo::new(&conn);
+
+        assert!(folders.move_to(&work.id, Some(&work.id)).is_err());
+        assert!(folders.move_to(&root.id, Some(&sub.id)).is_err());
+        assert_eq!(folders.get(&root.id).unwrap().parent_id, None);
+
+        folders.move_to(&sub.id, None).unwrap();
+        folders.move_to(&root.id, Some(&sub.id)).unwrap();
+        assert_eq!(folders.get(&root.id).unwrap().parent_id, Some(sub.id));
+    }
+
+    #[test]
+    fn move_document_requires_live_folder_and_document() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let (root, work, _) = tree(&conn);
+        let doc = DocumentRepo::new(&conn).create("Loose", None).unwrap();
+        folders.trash(&work.id, FolderDeleteMode::MoveToParent).unwrap();
+
+        assert!(folders.move_document(&doc.id, Some(&work.id)).is_err());
+        assert!(folders.move_document(&doc.id, Some("missing")).is_err());
+        assert!(folders.move_document("missing", Some(&root.id)).is_err());
+        assert_eq!(location(&conn, "Loose"), None);
+
+        folders.move_document(&doc.id, Some(&root.id)).unwrap();
+        assert_eq!(location(&conn, "Loose"), Some(root.id));
+        TrashRepo::new(&conn).trash_document(&doc.id).unwrap();
+        assert!(folders.move_document(&doc.id, None).is_err());
+    }
+
+    #[test]
+    fn create_requires_live_parent() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let (root, work, _) = tree(&conn);
+        folders.trash(&work.id, FolderDeleteMode::Recursive).unwrap();
+
+        assert!(folders.create("Orphan", Some("missing")).is_err());
+        assert!(folders.create("Trashed", Some(&work.id)).is_err());
+        let child = folders.create("Child", Some(&root.id)).unwrap();
+        assert_eq!(child.parent_id, Some(root.id));
+    }
+
+    #[test]
+    fn move_respects_depth_limit() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let mut deepest = folders.create("1", None).unwrap();
+        for level in 2..=MAX_FOLDER_DEPTH {
+            deepest = folders.create(&level.to_string(), Some(&deepest.id)).unwrap();
+        }
+        let single = folders.create("single", None).unwrap();
+        let (root, _, _) = tree(&conn);
+        assert!(folders.create("too deep", Some(&deepest.id)).is_err());
+
+        assert!(folders.move_to(&single.id, Some(&deepest.id)).is_err());
+        let parent = folders.get(&deepest.parent_id.clone().unwrap()).unwrap();
+        folders.move_to(&single.id, Some(&parent.id)).unwrap();
+        // Root 下还有两层，放到第 1 层下面正好 4 层，放到第 14 层下面超出
+        let first = folders.list_all().unwrap().into_iter().find(|f| f.name == "1").unwrap();
+        folders.move_to(&root.id, Some(&first.id)).unwrap();
+        let fourteenth = folders.list_all().unwrap().into_iter().find(|f| f.name == "14").unwrap();
+        assert!(folders.move_to(&root.id, Some(&fourteenth.id)).is_err());
+    }
+
+    #[test]
+    fn bulk_move_is_all_or_nothing() {
+        let mut conn = test_connection();
+        let (root, work, sub) = tree(&conn);
+        let doc = DocumentRepo::new(&conn).list_all().unwrap().into_iter().find(|d| d.title == "Doc").unwrap();
+        let documents = vec![doc.id];
+
+        {
+            // Root 不能移到自己下面，前面已经移动的 Sub 随事务回滚
+            let tx = conn.transaction().unwrap();
+            let result = FolderRepo::new(&tx).move_items(&[sub.id.clone(), root.id.clone()], &documents, Some(&root.id));
+            assert!(result.is_err());
+        }
+        assert_eq!(FolderRepo::new(&conn).get(&sub.id).unwrap().parent_id, Some(work.id.clone()));
+
+        let tx = conn.transaction().unwrap();
+        FolderRepo::new(&tx).move_items(&[sub.id.clone(), work.id], &documents, None).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(FolderRepo::new(&conn).get(&sub.id).unwrap().parent_id, None);
+        assert_eq!(location(&conn, "Doc"), None);
+        assert!(FolderRepo::new(&conn).move_items(&[], &documents, Some("missing")).is_err());
+
+        // 回收站中的文档不能移动
+        TrashRepo::new(&conn).trash_document(&documents[0]).unwrap();
+        assert!(FolderRepo::new(&conn).move_items(&[], &documents, Some(&sub.id)).is_err());
+        assert_eq!(DocumentRepo::new(&conn).get(&documents[0]).unwrap().folder_id, None);
+    }
+
+    #[test]
+    fn orphaned_parent_moves_to_root() {
+        let conn = test_connection();
+        let (root, work, sub) = tree(&conn);
+        let folders = FolderRepo::new(&conn);
+        conn.execute_batch(&format!("PRAGMA foreign_keys = OFF; DELETE FROM folders WHERE id = '{}';", root.id)).unwrap();
+
+        folders.trash(&work.id, FolderDeleteMode::MoveToParent).unwrap();
+        assert_eq!(folders.get(&sub.id).unwrap().parent_id, None);
+        assert_eq!(location(&conn, "Doc"), None);
+    }
+}
@@ -0,0 +1,26 @@
+//! 数据访问层：每个 Repo 只借用一个 `rusqlite::Connection`（或事务），
+//! 不依赖 Tauri，命令层负责取连接和处理事务
+
+mod documents;
+mod folders;
+mod snapshots;
+mod tags;
+mod trash;
+mod versions;
+
+pub use documents::DocumentRepo;
+pub(crate) use documents::rebuild_summaries;
+pub use folders::{FolderDeleteMode, FolderRepo};
+pub use snapshots::SnapshotRepo;
+pub use tags::TagRepo;
+pub use trash::TrashRepo;
+pub(crate) use versions::compress_legacy_versions;
+pub use versions::{RetentionPolicy, SnapshotPolicy, VersionRepo};
+
+/// 已迁移到最新结构的内存数据库，供单元测试使用
+#[cfg(test)]
+pub(crate) fn test_connection() -> rusqlite::Connection {
+    let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    crate::migrate::migrate(&mut conn).unwrap();
+    conn
+}
@@ -0,0 +1,226 @@
+//! 命名快照：给一个文件夹（含子文件夹）或整个工作区的全部文档各存一个带同一标签的版本，
+//! 之后可以一起恢复。快照中的版本带标签，不会被保留策略删除；删除快照只是去掉这些标签
+
+use crate::models::NamedSnapshot;
+use crate::repo::VersionRepo;
+use rusqlite::{params, Connection, Row};
+
+pub struct SnapshotRepo<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SnapshotRepo<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    /// 新的在前
+    pub fn list(&self) -> Result<Vec<NamedSnapshot>, String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.label, s.note, s.folder_id, s.created_at,
+                    (SELECT COUNT(*) FROM document_versions v WHERE v.snapshot_id = s.id)
+             FROM named_snapshots s ORDER BY s.created_at DESC"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let snapshots = stmt.query_map([], map_snapshot)
+            .map_err(|e| format!("Failed to query snapshots: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect snapshots: {}", e))?;
+
+        Ok(snapshots)
+    }
+
+    /// 给 `folder_id` 子树（None 为整个工作区）中回收站外的文档各存一个版本
+    pub fn create(&self, label: &str, note: Option<&str>, folder_id: Option<&str>) -> Result<NamedSnapshot, String> {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err("Snapshot label cannot be empty".to_string());
+        }
+        let note = note.map(str::trim).filter(|note| !note.is_empty());
+        if let Some(folder_id) = folder_id {
+            self.conn
+                .query_row("SELECT 1 FROM folders WHERE id = ?1 AND deleted_at IS NULL", [folder_id], |_| Ok(()))
+                .map_err(|e| format!("Folder not found: {}", e))?;
+        }
+
+        let snapshot = NamedSnapshot {
+            id: uuid::Uuid::new_v4().to_string(),
+            label: label.to_string(),
+            note: note.map(str::to_string),
+            folder_id: folder_id.map(str::to_string),
+            created_at: chrono::Utc::now().timestamp(),
+            document_count: 0,
+        };
+        self.conn.execute(
+            "INSERT INTO named_snapshots (id, label, note, folder_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![&snapshot.id, &snapshot.label, &snapshot.note, &snapshot.folder_id, snapshot.created_at],
+        ).map_err(|e| format!("Failed to create snapshot: {}", e))?;
+
+        let documents = self.documents(folder_id)?;
+        let versions = VersionRepo::new(self.conn);
+        for (document_id, content) in &documents {
+            let version_id = versions.save(document_id, content)?;
+            self.conn.execute(
+                "UPDATE document_versions SET label = ?1, note = ?2, snapshot_id = ?3 WHERE id = ?4",
+                params![&snapshot.label, &snapshot.note, &snapshot.id, version_id],
+            ).map_err(|e| format!("Failed to label version: {}", e))?;
+        }
+        Ok(NamedSnapshot { document_count: documents.len() as i64, ..snapshot })
+    }
+
+    /// 把快照中仍然存在的文档恢复到快照时的内容（恢复前各自保存当前内容），返回恢复的文档数。
+    /// 快照之后新建的文档和回收站中的文档不受影响
+    pub fn restore(&self, id: &str) -> Result<usize, String> {
+        self.conn
+            .query_row("SELECT 1 FROM named_snapshots WHERE id = ?1", [id], |_| Ok(()))
+            .map_err(|e| format!("Snapshot not found: {}", e))?;
+        let members: Vec<(String, String, String)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT v.document_id, v.id, d.content FROM document_versions v
+                 JOIN documents d ON d.id = v.document_id
+                 WHERE v.snapshot_id = ?1 AND d.deleted_at IS NULL"
+            ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+            let members = stmt.query_map([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                .map_err(|e| format!("Failed to query snapshot: {}", e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to collect snapshot: {}", e))?;
+            members
+        };
+
+        let versions = VersionRepo::new(self.conn);
+        let mut restored = 0;
+        for (document_id, version_id, current) in members {
+            if versions.get(&document_id, &version_id)?.content != current {
+                versions.restore(&document_id, &version_id)?;
+                restored += 1;
+            }
+        }
+        Ok(restored)
+    }
+
+    /// 删除快照；其中的版本去掉标签，之后按保留策略清理
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        self.conn.execute(
+            "UPDATE document_versions SET label = NULL, note = NULL, snapshot_id = NULL WHERE snapshot_id = ?1",
+            [id],
+        ).map_err(|e| format!("Failed to unlabel versions: {}", e))?;
+        self.conn.execute("DELETE FROM named_snapshots WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete snapshot: {}", e))?;
+        Ok(())
+    }
+
+    fn documents(&self, folder_id: Option<&str>) -> Result<Vec<(String, String)>, String> {
+        let mut stmt = self.conn.prepare(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT ?1 WHERE ?1 IS NOT NULL
+                 UNION SELECT f.id FROM folders f JOIN subtree s ON f.parent_id = s.id WHERE f.deleted_at IS NULL
+             )
+             SELECT id, content FROM documents
+             WHERE deleted_at IS NULL AND (?1 IS NULL OR folder_id IN (SELECT id FROM subtree))"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let documents = stmt.query_map([folder_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to query documents: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect documents: {}", e))?;
+        Ok(documents)
+    }
+}
+
+fn map_snapshot(row: &Row) -> rusqlite::Result<NamedSnapshot> {
+    Ok(NamedSnapshot {
+        id: row.get(0)?,
+        label: row.get(1)?,
+        note: row.get(2)?,
+        folder_id: row.get(3)?,
+        created_at: row.get(4)?,
+        document_count: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{test_connection, DocumentRepo, FolderRepo, RetentionPolicy, TrashRepo};
+
+    #[test]
+    fn folder_snapshot_restores_its_documents() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let documents = DocumentRepo::new(&conn);
+        let parent = folders.create("Client", None).unwrap();
+        let child = folders.create("Drafts", Some(&parent.id)).unwrap();
+        let a = documents.create_with_content("A", Some(&parent.id), "a1").unwrap();
+        let b = documents.create_with_content("B", Some(&child.id), "b1").unwrap();
+        let outside = documents.create_with_content("C", None, "c1").unwrap();
+
+        let snapshots = SnapshotRepo::new(&conn);
+        let snapshot = snapshots.create("sent to client", Some("first round"), Some(&parent.id)).unwrap();
+        assert_eq!(snapshot.document_count, 2);
+        assert_eq!(snapshots.list().unwrap()[0].document_count, 2);
+
+        documents.update_content(&a.id, "a2").unwrap();
+        documents.update_content(&b.id, "b2").unwrap();
+        documents.update_content(&outside.id, "c2").unwrap();
+        assert_eq!(snapshots.restore(&snapshot.id).unwrap(), 2);
+        assert_eq!(documents.get(&a.id).unwrap().content, "a1");
+        assert_eq!(documents.get(&b.id).unwrap().content, "b1");
+        assert_eq!(documents.get(&outside.id).unwrap().content, "c2");
+        // 恢复前的内容也保存成了版本
+        assert_eq!(VersionRepo::new(&conn).list(&a.id).unwrap()[0].content, "a2");
+
+        assert!(snapshots.create("  ", None, None).is_err());
+    }
+
+    #[test]
+    fn restore_skips_trashed_documents() {
+        let conn = test_connection();
+        let documents = DocumentRepo::new(&conn);
+        let kept = documents.create_with_content("A", None, "a1").unwrap();
+        let trashed = documents.create_with_content("B", None, "b1").unwrap();
+        let snapshots = SnapshotRepo::new(&conn);
+        let snapshot = snapshots.create("workspace", None, None).unwrap();
+
+        documents.update_content(&kept.id, "a2").unwrap();
+        documents.update_content(&trashed.id, "b2").unwrap();
+        TrashRepo::new(&conn).trash_document(&trashed.id).unwrap();
+        assert_eq!(snapshots.restore(&snapshot.id).unwrap(), 1);
+        assert_eq!(documents.get(&kept.id).unwrap().content, "a1");
+        assert_eq!(documents.get(&trashed.id).unwrap().content, "b2");
+    }
+
+    #[test]
+    fn snapshot_versions_are_kept_until_the_snapshot_is_deleted() {
+        let conn = test_connection();
+        let doc = DocumentRepo::new(&conn).create_with_content("A", None, "old").unwrap();
+        let snapshots = SnapshotRepo::new(&conn);
+        let snapshot = snapshots.create("workspace", None, None).unwrap();
+        let versions = VersionRepo::new(&conn);
+        versions.save(&doc.id, "newer").unwrap();
+        conn.execute("UPDATE document_versions SET created_at = created_at - 400 * 86400 WHERE snapshot_id IS NOT NULL", []).unwrap();
+
+        let policy = RetentionPolicy { keep_all_secs: 86400, hourly_secs: 0, daily_secs: 0 };
+        assert_eq!(versions.prune(&doc.id, policy).unwrap(), 0);
+        snapshots.delete(&snapshot.id).unwrap();
+        assert!(snapshots.list().unwrap().is_empty());
+        assert_eq!(versions.prune(&doc.id, policy).unwrap(), 1);
+    }
+
+    #[test]
+    fn clearing_a_snapshot_version_label_keeps_it() {
+        let conn = test_connection();
+        let documents = DocumentRepo::new(&conn);
+        let doc = documents.create_with_content("A", None, "old").unwrap();
+        let snapshots = SnapshotRepo::new(&conn);
+        let snapshot = snapshots.create("workspace", None, None).unwrap();
+        let versions = VersionRepo::new(&conn);
+        let version = versions.list(&doc.id).unwrap().remove(0);
+        versions.set_label(&doc.id, &version.id, Some(""), None).unwrap();
+        conn.execute("UPDATE document_versions SET created_at = created_at - 400 * 86400", []).unwrap();
+
+        let policy = RetentionPolicy { keep_all_secs: 86400, hourly_secs: 0, daily_secs: 0 };
+        assert_eq!(versions.prune(&doc.id, policy).unwrap(), 0);
+        documents.update_content(&doc.id, "new").unwrap();
+        assert_eq!(snapshots.restore(&snapshot.id).unwrap(), 1);
+        assert_eq!(documents.get(&doc.id).unwrap().content, "old");
+    }
+}
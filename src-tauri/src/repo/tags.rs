@@ -0,0 +1,108 @@
+use crate::models::Tag;
+use crate::search;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+pub struct TagRepo<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> TagRepo<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    pub fn list_by_document(&self, document_id: &str) -> Result<Vec<Tag>, String> {
+        let mut stmt = self.conn
+            .prepare("SELECT id, document_id, text, selected_text, position, ai_block_id, color, created_at, updated_at FROM tags WHERE document_id = ?1 ORDER BY position ASC")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let tags = stmt.query_map([document_id], map_tag)
+            .map_err(|e| format!("Failed to query tags: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect tags: {}", e))?;
+
+        Ok(tags)
+    }
+
+    pub fn create(
+        &self,
+        document_id: &str,
+        text: &str,
+        selected_text: &str,
+        position: i32,
+        ai_block_id: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<Tag, String> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let tag = Tag {
+            id: uuid::Uuid::new_v4().to_string(),
+            document_id: document_id.to_string(),
+            text: text.to_string(),
+            selected_text: selected_text.to_string(),
+            position,
+            ai_block_id: ai_block_id.map(|s| s.to_string()),
+            color: color.map(|s| s.to_string()),
+            created_at: now,
+            updated_at: now,
+        };
+        self.insert(&tag)?;
+        Ok(tag)
+    }
+
+    /// 按给定的 id 写入（导入时标签 id 要先写进文档内容的 tagMark）
+    pub fn insert(&self, tag: &Tag) -> Result<(), String> {
+        self.conn.execute(
+            "INSERT INTO tags (id, document_id, text, selected_text, position, ai_block_id, color, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![&tag.id, &tag.document_id, &tag.text, &tag.selected_text, tag.position, &tag.ai_block_id, &tag.color, tag.created_at, tag.updated_at],
+        ).map_err(|e| format!("Failed to create tag: {}", e))?;
+        search::index_document(self.conn, &tag.document_id)
+    }
+
+    pub fn update_text(&self, id: &str, text: &str) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.conn.execute(
+            "UPDATE tags SET text = ?1, updated_at = ?2 WHERE id = ?3",
+            params![text, now, id],
+        ).map_err(|e| format!("Failed to update tag: {}", e))?;
+        self.reindex_owner(id)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let document_id = self.document_id_of(id)?;
+        self.conn.execute("DELETE FROM tags WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete tag: {}", e))?;
+        match document_id {
+            Some(document_id) => search::index_document(self.conn, &document_id),
+            None => Ok(()),
+        }
+    }
+
+    fn document_id_of(&self, id: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row("SELECT document_id FROM tags WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to query tag: {}", e))
+    }
+
+    /// 标签文本是文档搜索索引的一部分
+    fn reindex_owner(&self, id: &str) -> Result<(), String> {
+        match self.document_id_of(id)? {
+            Some(document_id) => search::index_document(self.conn, &document_id),
+            None => Ok(()),
+        }
+    }
+}
+
+fn map_tag(row: &Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        document_id: row.get(1)?,
+        text: row.get(2)?,
+        selected_text: row.get(3)?,
+        position: row.get(4)?,
+        ai_block_id: row.get(5)?,
+        color: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
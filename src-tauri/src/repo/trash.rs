@@ -0,0 +1,496 @@
+//! 回收站：文档和文件夹软删除（`deleted_at`），可以恢复到原来的文件夹路径，超过保留期后永久删除。
+//!
+//! 删除文件夹时整棵子树一起进回收站，子项的 `trashed_with` 指向被删除的文件夹；回收站列表只显示
+//! 单独删除的条目，恢复或永久删除文件夹时连同这些子项一起处理。单独删除的条目在 `trash_path` 记下
+//! 原文件夹路径（文件夹名的 JSON 数组），原文件夹已被永久删除时按路径重建
+
+use crate::links;
+use crate::models::TrashItem;
+use crate::repo::folders::MAX_PATH_DEPTH;
+use crate::repo::{DocumentRepo, FolderRepo};
+use crate::search;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// (父文件夹 id, 原路径 JSON)
+type TrashEntry = (Option<String>, Option<String>);
+
+pub struct TrashRepo<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> TrashRepo<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    /// 回收站中单独删除的条目，最近删除的在前
+    pub fn list(&self) -> Result<Vec<TrashItem>, String> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT id, 'folder', name, deleted_at, trash_path,
+                        (SELECT COUNT(*) FROM documents d WHERE d.trashed_with = f.id)
+                      + (SELECT COUNT(*) FROM folders c WHERE c.trashed_with = f.id)
+                 FROM folders f
+                 WHERE deleted_at IS NOT NULL AND trashed_with IS NULL
+                 UNION ALL
+                 SELECT id, 'document', title, deleted_at, trash_path, 0
+                 FROM documents
+                 WHERE deleted_at IS NOT NULL AND trashed_with IS NULL
+                 ORDER BY 4 DESC, 3",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let items = stmt.query_map([], |row| {
+                let path: Option<String> = row.get(4)?;
+                Ok(TrashItem {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    title: row.get(2)?,
+                    deleted_at: row.get(3)?,
+                    path: path.and_then(|p| serde_json::from_str(&p).ok()).unwrap_or_default(),
+                    item_count: row.get(5)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query trash: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect trash: {}", e))?;
+
+        Ok(items)
+    }
+
+    /// 把文档移入回收站
+    pub fn trash_document(&self, id: &str) -> Result<(), String> {
+        let folder_id: Option<String> = self.conn
+            .query_row("SELECT folder_id FROM documents WHERE id = ?1 AND deleted_at IS NULL", [id], |row| row.get(0))
+            .map_err(|e| format!("Document not found: {}", e))?;
+        let path = self.path_of(folder_id.as_deref())?;
+        self.conn.execute(
+            "UPDATE documents SET deleted_at = ?1, trashed_with = NULL, trash_path = ?2 WHERE id = ?3",
+            params![chrono::Utc::now().timestamp(), path, id],
+        ).map_err(|e| format!("Failed to trash document: {}", e))?;
+        unindex(self.conn, id)
+    }
+
+    /// 把文件夹连同其中的子文件夹和文档移入回收站
+    pub fn trash_folder(&self, id: &str) -> Result<(), String> {
+        let parent_id: Option<String> = self.conn
+            .query_row("SELECT parent_id FROM folders WHERE id = ?1 AND deleted_at IS NULL", [id], |row| row.get(0))
+            .map_err(|e| format!("Folder not found: {}", e))?;
+        let path = self.path_of(parent_id.as_deref())?;
+        let now = chrono::Utc::now().timestamp();
+
+        let documents = self.ids(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT ?1
+                 UNION SELECT f.id FROM folders f JOIN subtree s ON f.parent_id = s.id WHERE f.deleted_at IS NULL
+             )
+             SELECT id FROM documents WHERE deleted_at IS NULL AND folder_id IN (SELECT id FROM subtree)",
+            id,
+        )?;
+        for document_id in &documents {
+            self.conn.execute(
+                "UPDATE documents SET deleted_at = ?1, trashed_with = ?2, trash_path = NULL WHERE id = ?3",
+                params![now, id, document_id],
+            ).map_err(|e| format!("Failed to trash document: {}", e))?;
+            unindex(self.conn, document_id)?;
+        }
+
+        self.conn.execute(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT ?2
+                 UNION SELECT f.id FROM folders f JOIN subtree s ON f.parent_id = s.id WHERE f.deleted_at IS NULL
+             )
+             UPDATE folders SET deleted_at = ?1, trashed_with = ?2, trash_path = NULL
+             WHERE id IN (SELECT id FROM subtree) AND id != ?2",
+            params![now, id],
+        ).map_err(|e| format!("Failed to trash folders: {}", e))?;
+        self.conn.execute(
+            "UPDATE folders SET deleted_at = ?1, trashed_with = NULL, trash_path = ?2 WHERE id = ?3",
+            params![now, path, id],
+        ).map_err(|e| format!("Failed to trash folder: {}", e))?;
+        Ok(())
+    }
+
+    /// 恢复回收站条目；原文件夹也在回收站中时一并恢复，已被永久删除时按原路径重建
+    pub fn restore(&self, id: &str) -> Result<(), String> {
+        if let Some((folder_id, path)) = self.entry("documents", "folder_id", id)? {
+            let folder_id = self.restore_parent(folder_id, path)?;
+            self.conn.execute(
+                "UPDATE documents SET deleted_at = NULL, trash_path = NULL, folder_id = ?1 WHERE id = ?2",
+                params![folder_id, id],
+            ).map_err(|e| format!("Failed to restore document: {}", e))?;
+            return reindex(self.conn, id);
+        }
+
+        if let Some((parent_id, path)) = self.entry("folders", "parent_id", id)? {
+            let parent_id = self.restore_parent(parent_id, path)?;
+            let documents = self.ids("SELECT id FROM documents WHERE trashed_with = ?1", id)?;
+            self.conn.execute("UPDATE documents SET deleted_at = NULL, trashed_with = NULL WHERE trashed_with = ?1", [id])
+                .map_err(|e| format!("Failed to restore documents: {}", e))?;
+            self.conn.execute("UPDATE folders SET deleted_at = NULL, trashed_with = NULL WHERE trashed_with = ?1", [id])
+                .map_err(|e| format!("Failed to restore folders: {}", e))?;
+            self.conn.execute(
+                "UPDATE folders SET deleted_at = NULL, trash_path = NULL, parent_id = ?1 WHERE id = ?2",
+                params![parent_id, id],
+            ).map_err(|e| format!("Failed to restore folder: {}", e))?;
+            for document_id in &documents {
+                reindex(self.conn, document_id)?;
+            }
+            return Ok(());
+        }
+
+        Err(format!("Trash item not found: {}", id))
+    }
+
+    /// 永久删除回收站条目（文件夹连同随它删除的内容）
+    pub fn purge(&self, id: &str) -> Result<(), String> {
+        if self.entry("documents", "folder_id", id)?.is_some() {
+            return purge_document(self.conn, id);
+        }
+        if self.entry("folders", "parent_id", id)?.is_some() {
+            for document_id in self.ids("SELECT id FROM documents WHERE trashed_with = ?1", id)? {
+                purge_document(self.conn, &document_id)?;
+            }
+            let folders = FolderRepo::new(self.conn);
+            for folder_id in self.ids("SELECT id FROM folders WHERE trashed_with = ?1", id)? {
+                folders.delete(&folder_id)?;
+            }
+            return folders.delete(id);
+        }
+        Err(format!("Trash item not found: {}", id))
+    }
+
+    /// 永久删除 `deleted_before` 之前删除的全部条目，返回条目数
+    pub fn purge_older_than(&self, deleted_before: i64) -> Result<usize, String> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT id FROM folders WHERE deleted_at < ?1 AND trashed_with IS NULL
+                 UNION ALL
+                 SELECT id FROM documents WHERE deleted_at < ?1 AND trashed_with IS NULL",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let ids = stmt.query_map([deleted_before], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query trash: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect trash: {}", e))?;
+
+        for id in &ids {
+            self.purge(id)?;
+        }
+        Ok(ids.len())
+    }
+
+    /// 按保留天数清理过期条目，天数不大于 0 时不清理
+    pub fn purge_expired(&self, retention_days: i64) -> Result<usize, String> {
+        if retention_days <= 0 {
+            return Ok(0);
+        }
+        self.purge_older_than(chrono::Utc::now().timestamp() - retention_days * 86_400)
+    }
+
+    /// 清空回收站
+    pub fn empty(&self) -> Result<usize, String> {
+        self.purge_older_than(i64::MAX)
+    }
+
+    /// 单独删除的回收站条目的父文件夹和原路径
+    fn entry(&self, table: &str, parent_column: &str, id: &str) -> Result<Option<TrashEntry>, String> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {}, trash_path FROM {} WHERE id = ?1 AND deleted_at IS NOT NULL AND trashed_with IS NULL",
+                    parent_column, table
+                ),
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query trash: {}", e))
+    }
+
+    fn ids(&self, sql: &str, id: &str) -> Result<Vec<String>, String> {
+        let mut stmt = self.conn.prepare(sql)
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let ids = stmt.query_map([id], |row| row.get(0))
+            .map_err(|e| format!("Failed to query trash: {}", e))?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("Failed to collect trash: {}", e))?;
+        Ok(ids)
+    }
+
+    /// 文件夹从根开始的名称路径（JSON 数组），根目录为 None
+    fn path_of(&self, folder_id: Option<&str>) -> Result<Option<String>, String> {
+        let Some(folder_id) = folder_id else {
+            return Ok(None);
+        };
+        let names: Vec<String> = FolderRepo::new(self.conn).path(folder_id)?.into_iter().map(|crumb| crumb.name).collect();
+        serde_json::to_string(&names)
+            .map(Some)
+            .map_err(|e| format!("Failed to serialize folder path: {}", e))
+    }
+
+    /// 确定恢复后的父文件夹：原文件夹还在就用它（并恢复回收站中的上级），否则按原路径重建
+    fn restore_parent(&self, folder_id: Option<String>, path: Option<String>) -> Result<Option<String>, String> {
+        if let Some(folder_id) = folder_id {
+            if FolderRepo::new(self.conn).get(&folder_id).is_ok() {
+                self.revive_ancestors(&folder_id)?;
+                return Ok(Some(folder_id));
+            }
+        }
+
+        let names: Vec<String> = path.and_then(|p| serde_json::from_str(&p).ok()).unwrap_or_default();
+        let mut parent_id: Option<String> = None;
+        for name in &names {
+            let existing: Option<String> = self.conn
+                .query_row(
+                    "SELECT id FROM folders WHERE name = ?1 AND parent_id IS ?2 AND deleted_at IS NULL ORDER BY created_at LIMIT 1",
+                    params![name, parent_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| format!("Failed to query folder: {}", e))?;
+            parent_id = Some(match existing {
+                Some(id) => id,
+                None => FolderRepo::new(self.conn).create(name, parent_id.as_deref())?.id,
+            });
+        }
+        Ok(parent_id)
+    }
+
+    /// 恢复 `folder_id` 及其在回收站中的上级文件夹（只恢复这条路径）。
+    /// 这些文件夹原来随哪个文件夹一起删除的，那批里剩下的内容拆成新的回收站条目
+    fn revive_ancestors(&self, folder_id: &str) -> Result<(), String> {
+        let mut batches = Vec::new();
+        let mut current = Some(folder_id.to_string());
+        let mut depth = 0;
+        while let Some(id) = current.take() {
+            let row: Option<(Option<String>, Option<i64>, Option<String>)> = self.conn
+                .query_row("SELECT parent_id, deleted_at, trashed_with FROM folders WHERE id = ?1", [&id], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })
+                .optional()
+                .map_err(|e| format!("Failed to query folder: {}", e))?;
+            let Some((parent_id, deleted_at, trashed_with)) = row else {
+                break;
+            };
+            if deleted_at.is_some() {
+                self.conn.execute(
+                    "UPDATE folders SET deleted_at = NULL, trashed_with = NULL, trash_path = NULL WHERE id = ?1",
+                    [&id],
+                ).map_err(|e| format!("Failed to restore folder: {}", e))?;
+                batches.push(trashed_with.unwrap_or(id));
+            }
+            depth += 1;
+            current = if depth < MAX_PATH_DEPTH { parent_id } else { None };
+        }
+
+        for batch in batches {
+            for folder in self.ids("SELECT id FROM folders WHERE trashed_with = ?1", &batch)? {
+                let parent_id: Option<String> = self.conn
+                    .query_row("SELECT parent_id FROM folders WHERE id = ?1", [&folder], |row| row.get(0))
+                    .map_err(|e| format!("Failed to query folder: {}", e))?;
+                self.split_off("folders", &folder, parent_id.as_deref())?;
+            }
+            for document in self.ids("SELECT id FROM documents WHERE trashed_with = ?1", &batch)? {
+                let folder_id: Option<String> = self.conn
+                    .query_row("SELECT folder_id FROM documents WHERE id = ?1", [&document], |row| row.get(0))
+                    .map_err(|e| format!("Failed to query document: {}", e))?;
+                self.split_off("documents", &document, folder_id.as_deref())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 把原来随文件夹删除的条目改挂到仍在回收站中的最上层文件夹；上级已经恢复时成为单独的条目
+    fn split_off(&self, table: &str, id: &str, parent_id: Option<&str>) -> Result<(), String> {
+        let head = self.trashed_root(parent_id)?;
+        let path = if head.is_none() { self.path_of(parent_id)? } else { None };
+        self.conn.execute(
+            &format!("UPDATE {} SET trashed_with = ?1, trash_path = ?2 WHERE id = ?3", table),
+            params![head, path, id],
+        ).map_err(|e| format!("Failed to update trash: {}", e))?;
+        Ok(())
+    }
+
+    /// `folder_id` 向上连续在回收站中的最上层文件夹；`folder_id` 本身不在回收站中时为 None
+    fn trashed_root(&self, folder_id: Option<&str>) -> Result<Option<String>, String> {
+        let mut root = None;
+        let mut current = folder_id.map(|s| s.to_string());
+        let mut depth = 0;
+        while let Some(id) = current.take() {
+            let row: Option<(Option<String>, Option<i64>)> = self.conn
+                .query_row("SELECT parent_id, deleted_at FROM folders WHERE id = ?1", [&id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .optional()
+                .map_err(|e| format!("Failed to query folder: {}", e))?;
+            match row {
+                Some((parent_id, Some(_))) => {
+                    root = Some(id);
+                    depth += 1;
+                    current = if depth < MAX_PATH_DEPTH { parent_id } else { None };
+                }
+                _ => break,
+            }
+        }
+        Ok(root)
+    }
+}
+
+/// 从搜索和链接索引中移除（回收站中的文档不参与搜索，它的链接也不计入反向链接）
+fn unindex(conn: &Connection, document_id: &str) -> Result<(), String> {
+    search::remove_document(conn, document_id)?;
+    links::remove_document(conn, document_id)
+}
+
+fn reindex(conn: &Connection, document_id: &str) -> Result<(), String> {
+    search::index_document(conn, document_id)?;
+    links::index_document(conn, document_id)?;
+    links::clear_tombstone(conn, document_id)
+}
+
+/// 永久删除文档；还有链接指向它时留下墓碑
+fn purge_document(conn: &Connection, document_id: &str) -> Result<(), String> {
+    if !links::backlinks(conn, document_id)?.is_empty() {
+        let title: String = conn
+            .query_row("SELECT title FROM documents WHERE id = ?1", [document_id], |row| row.get(0))
+            .map_err(|e| format!("Document not found: {}", e))?;
+        links::add_tombstone(conn, document_id, &title)?;
+    }
+    DocumentRepo::new(conn).delete(document_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::test_connection;
+
+    fn live_documents(conn: &Connection) -> Vec<String> {
+        DocumentRepo::new(conn).list_all().unwrap().into_iter().map(|d| d.title).collect()
+    }
+
+    fn live_folders(conn: &Connection) -> Vec<String> {
+        FolderRepo::new(conn).list_all().unwrap().into_iter().map(|f| f.name).collect()
+    }
+
+    #[test]
+    fn trashed_document_is_hidden_and_restorable() {
+        let conn = test_connection();
+        let docs = DocumentRepo::new(&conn);
+        let doc = docs.create("Draft", None).unwrap();
+        docs.update_content(&doc.id, r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"needle"}]}]}"#).unwrap();
+
+        let trash = TrashRepo::new(&conn);
+        trash.trash_document(&doc.id).unwrap();
+        assert!(live_documents(&conn).is_empty());
+        assert!(search::search(&conn, "needle", 10).unwrap().is_empty());
+        let items = trash.list().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, "document");
+        assert_eq!(items[0].title, "Draft");
+
+        trash.restore(&doc.id).unwrap();
+        assert_eq!(live_documents(&conn), vec!["Draft"]);
+        assert_eq!(search::search(&conn, "needle", 10).unwrap().len(), 1);
+        assert!(trash.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn trashed_folder_takes_its_subtree() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let docs = DocumentRepo::new(&conn);
+        let work = folders.create("Work", None).unwrap();
+        let sub = folders.create("Sub", Some(&work.id)).unwrap();
+        docs.create("Top", Some(&work.id)).unwrap();
+        docs.create("Nested", Some(&sub.id)).unwrap();
+        docs.create("Elsewhere", None).unwrap();
+
+        let trash = TrashRepo::new(&conn);
+        trash.trash_folder(&work.id).unwrap();
+        assert_eq!(live_documents(&conn), vec!["Elsewhere"]);
+        assert!(live_folders(&conn).is_empty());
+        let items = trash.list().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item_count, 3);
+
+        trash.restore(&work.id).unwrap();
+        assert_eq!(live_documents(&conn).len(), 3);
+        assert_eq!(docs.get(&docs.list_all().unwrap().iter().find(|d| d.title == "Nested").unwrap().id).unwrap().folder_id, Some(sub.id));
+        assert!(trash.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn restoring_into_trashed_folder_splits_the_batch() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let docs = DocumentRepo::new(&conn);
+        let work = folders.create("Work", None).unwrap();
+        let sub = folders.create("Sub", Some(&work.id)).unwrap();
+        let nested = docs.create("Nested", Some(&sub.id)).unwrap();
+        docs.create("Sibling", Some(&sub.id)).unwrap();
+        docs.create("Top", Some(&work.id)).unwrap();
+
+        let trash = TrashRepo::new(&conn);
+        trash.trash_document(&nested.id).unwrap();
+        trash.trash_folder(&work.id).unwrap();
+        assert_eq!(trash.list().unwrap().len(), 2);
+
+        trash.restore(&nested.id).unwrap();
+        assert_eq!(live_documents(&conn), vec!["Nested"]);
+        assert_eq!(live_folders(&conn), vec!["Sub", "Work"]);
+
+        let mut left: Vec<(String, Vec<String>)> = trash.list().unwrap().into_iter().map(|i| (i.title, i.path)).collect();
+        left.sort();
+        assert_eq!(left, vec![
+            ("Sibling".to_string(), vec!["Work".to_string(), "Sub".to_string()]),
+            ("Top".to_string(), vec!["Work".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn restore_recreates_purged_folders() {
+        let conn = test_connection();
+        let folders = FolderRepo::new(&conn);
+        let docs = DocumentRepo::new(&conn);
+        let work = folders.create("Work", None).unwrap();
+        let sub = folders.create("Sub", Some(&work.id)).unwrap();
+        let doc = docs.create("Note", Some(&sub.id)).unwrap();
+
+        let trash = TrashRepo::new(&conn);
+        trash.trash_document(&doc.id).unwrap();
+        trash.trash_folder(&work.id).unwrap();
+        trash.purge(&work.id).unwrap();
+        assert!(folders.get(&sub.id).is_err());
+        assert_eq!(trash.list().unwrap().len(), 1);
+
+        trash.restore(&doc.id).unwrap();
+        let restored = docs.get(&doc.id).unwrap();
+        let parent = folders.get(restored.folder_id.as_deref().unwrap()).unwrap();
+        assert_eq!(parent.name, "Sub");
+        assert_eq!(folders.get(parent.parent_id.as_deref().unwrap()).unwrap().name, "Work");
+    }
+
+    #[test]
+    fn purge_removes_rows_and_leaves_tombstones() {
+        let conn = test_connection();
+        let docs = DocumentRepo::new(&conn);
+        let target = docs.create("Target", None).unwrap();
+        let source = docs.create("Source", None).unwrap();
+        docs.update_content(
+            &source.id,
+            &format!(r#"{{"type":"doc","content":[{{"type":"paragraph","content":[{{"type":"text","text":"t","marks":[{{"type":"documentLink","attrs":{{"documentId":"{}","title":null}}}}]}}]}}]}}"#, target.id),
+        ).unwrap();
+
+        let trash = TrashRepo::new(&conn);
+        trash.trash_document(&target.id).unwrap();
+        let broken = links::broken_links(&conn).unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].target_title.as_deref(), Some("Target"));
+
+        assert_eq!(trash.list().unwrap().len(), 1);
+        assert_eq!(trash.purge_expired(30).unwrap(), 0);
+        assert_eq!(trash.empty().unwrap(), 1);
+        assert!(docs.get(&target.id).is_err());
+        assert!(trash.list().unwrap().is_empty());
+        assert_eq!(links::broken_links(&conn).unwrap()[0].target_title.as_deref(), Some("Target"));
+    }
+}
@@ -0,0 +1,619 @@
+use crate::delta;
+use crate::diff;
+use crate::models::{
+    DocumentVersion, DocumentVersionStorage, Settings, VersionStorageReport, DEFAULT_VERSION_DAILY_DAYS,
+    DEFAULT_VERSION_HOURLY_DAYS, DEFAULT_VERSION_INTERVAL_MINUTES, DEFAULT_VERSION_KEEP_ALL_HOURS,
+    DEFAULT_VERSION_MIN_CHANGE_CHARS,
+};
+use crate::repo::DocumentRepo;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+/// 每隔这么多个版本保存一次完整快照，其余保存相对最近快照的增量
+const SNAPSHOT_EVERY: i32 = 10;
+
+const VERSION_COLUMNS: &str =
+    "id, document_id, content, created_at, version_number, label, note, snapshot_id, base_version, data";
+
+/// 自动快照策略：距上一个版本（没有版本时为文档创建时间）超过 `interval_secs`，
+/// 或一次修改删除和插入的文字（按词级差异计算，内容无法解析时退回比较长度）至少 `min_change_chars` 时，保存被覆盖的内容
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotPolicy {
+    pub interval_secs: i64,
+    pub min_change_chars: usize,
+}
+
+impl SnapshotPolicy {
+    /// 间隔设为 0 时关闭自动快照
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let minutes = settings.version_interval_minutes.unwrap_or(DEFAULT_VERSION_INTERVAL_MINUTES);
+        let min_change = settings.version_min_change_chars.unwrap_or(DEFAULT_VERSION_MIN_CHANGE_CHARS);
+        (minutes > 0).then(|| SnapshotPolicy {
+            interval_secs: minutes as i64 * 60,
+            min_change_chars: min_change.max(0) as usize,
+        })
+    }
+}
+
+/// 历史版本保留策略（按版本的年龄，单位秒）：`keep_all_secs` 内全部保留，
+/// `hourly_secs` 内每小时保留最新的一个，`daily_secs` 内每天保留一个，更早的删除。
+/// 每个文档的最新版本和加了标签的版本总是保留
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub keep_all_secs: i64,
+    pub hourly_secs: i64,
+    pub daily_secs: i64,
+}
+
+impl RetentionPolicy {
+    pub fn from_settings(settings: &Settings) -> Self {
+        RetentionPolicy {
+            keep_all_secs: settings.version_keep_all_hours.unwrap_or(DEFAULT_VERSION_KEEP_ALL_HOURS) as i64 * 3600,
+            hourly_secs: settings.version_hourly_days.unwrap_or(DEFAULT_VERSION_HOURLY_DAYS) as i64 * 86400,
+            daily_secs: settings.version_daily_days.unwrap_or(DEFAULT_VERSION_DAILY_DAYS) as i64 * 86400,
+        }
+    }
+
+    /// 从新到旧排列的创建时间中应当删除的下标
+    fn expired(&self, created_at: &[i64], now: i64) -> Vec<usize> {
+        let mut buckets = HashSet::new();
+        let mut expired = Vec::new();
+        for (index, &at) in created_at.iter().enumerate().skip(1) {
+            let age = now - at;
+            let keep = if age < self.keep_all_secs {
+                true
+            } else if age < self.hourly_secs {
+                buckets.insert((0, at.div_euclid(3600)))
+            } else if age < self.daily_secs {
+                buckets.insert((1, at.div_euclid(86400)))
+            } else {
+                false
+            };
+            if !keep {
+                expired.push(index);
+            }
+        }
+        expired
+    }
+}
+
+/// 数据库中的一行：`data` 为空时是旧格式，内容直接存在 `content`；
+/// `base_version` 为空时 `data` 是压缩的完整内容，否则是相对该版本号快照的增量
+struct StoredVersion {
+    version: DocumentVersion,
+    base_version: Option<i32>,
+    data: Option<Vec<u8>>,
+}
+
+pub struct VersionRepo<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> VersionRepo<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    /// 保存一个新版本，返回版本 id
+    pub fn save(&self, document_id: &str, content: &str) -> Result<String, String> {
+        // 获取当前版本号
+        let version_number: i32 = self.conn.query_row(
+            "SELECT COALESCE(MAX(version_number), 0) + 1 FROM document_versions WHERE document_id = ?1",
+            [document_id],
+            |row| row.get(0),
+        ).map_err(|e| format!("Failed to query version number: {}", e))?;
+
+        let base: Option<(i32, Vec<u8>)> = self.conn
+            .query_row(
+                "SELECT version_number, data FROM document_versions
+                 WHERE document_id = ?1 AND base_version IS NULL AND data IS NOT NULL
+                 ORDER BY version_number DESC LIMIT 1",
+                [document_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query versions: {}", e))?;
+        let base = match base {
+            Some((number, data)) => Some((number, delta::decompress(&data)?)),
+            None => None,
+        };
+        let (base_version, data) = encode(version_number, content, base.as_ref().map(|(n, c)| (*n, c.as_str())))?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        self.conn.execute(
+            "INSERT INTO document_versions (id, document_id, content, created_at, version_number, base_version, data, size)
+             VALUES (?1, ?2, '', ?3, ?4, ?5, ?6, ?7)",
+            params![&id, document_id, now, version_number, base_version, data, content.len() as i64],
+        ).map_err(|e| format!("Failed to save version: {}", e))?;
+
+        Ok(id)
+    }
+
+    /// 设置版本的标签和备注，标签为空时取消标签（不属于命名快照的版本之后按保留策略清理）
+    pub fn set_label(&self, document_id: &str, version_id: &str, label: Option<&str>, note: Option<&str>) -> Result<(), String> {
+        self.get(document_id, version_id)?;
+        let label = label.map(str::trim).filter(|label| !label.is_empty());
+        let note = note.map(str::trim).filter(|note| !note.is_empty());
+        self.conn.execute(
+            "UPDATE document_versions SET label = ?1, note = ?2 WHERE id = ?3",
+            params![label, note, version_id],
+        ).map_err(|e| format!("Failed to label version: {}", e))?;
+        Ok(())
+    }
+
+    /// 在 `new_content` 覆盖文档之前，按策略把当前内容保存为版本，返回是否保存
+    pub fn snapshot_before_update(&self, document_id: &str, new_content: &str, policy: SnapshotPolicy) -> Result<bool, String> {
+        let (current, created_at): (String, i64) = self.conn
+            .query_row(
+                "SELECT content, created_at FROM documents WHERE id = ?1",
+                [document_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("Document not found: {}", e))?;
+        if current == new_content {
+            return Ok(false);
+        }
+        let latest = self.latest(document_id)?;
+        if latest.as_ref().is_some_and(|(content, _)| *content == current) {
+            return Ok(false);
+        }
+
+        let since = latest.map_or(created_at, |(_, at)| at);
+        let elapsed = chrono::Utc::now().timestamp() - since;
+        // 按文字的实际改动计算，内容无法解析时退回比较长度
+        let change = diff::changed_chars(&current, new_content)
+            .unwrap_or_else(|_| current.chars().count().abs_diff(new_content.chars().count()));
+        if elapsed < policy.interval_secs && change < policy.min_change_chars {
+            return Ok(false);
+        }
+        self.save(document_id, &current)?;
+        Ok(true)
+    }
+
+    /// 把文档的当前内容保存为版本，与最新版本相同时不重复保存；返回是否保存
+    pub fn save_current(&self, document_id: &str) -> Result<bool, String> {
+        let current = DocumentRepo::new(self.conn).get(document_id)?.content;
+        if self.latest(document_id)?.is_some_and(|(latest, _)| latest == current) {
+            return Ok(false);
+        }
+        self.save(document_id, &current)?;
+        Ok(true)
+    }
+
+    /// 恢复到指定版本，恢复前总是先把当前内容保存为版本
+    pub fn restore(&self, document_id: &str, version_id: &str) -> Result<(), String> {
+        let content = self.get(document_id, version_id)?.content;
+        self.save_current(document_id)?;
+        DocumentRepo::new(self.conn).update_content(document_id, &content)?;
+        Ok(())
+    }
+
+    /// 文档的某个版本；版本不属于该文档时报错
+    pub fn get(&self, document_id: &str, version_id: &str) -> Result<DocumentVersion, String> {
+        let stored = self.conn
+            .query_row(
+                &format!("SELECT {} FROM document_versions WHERE id = ?1", VERSION_COLUMNS),
+                [version_id],
+                map_stored,
+            )
+            .map_err(|e| format!("Failed to get version: {}", e))?;
+        if stored.version.document_id != document_id {
+            return Err(format!("Version {} does not belong to document {}", version_id, document_id));
+        }
+        let mut snapshots = HashMap::new();
+        self.decode(stored, &mut snapshots)
+    }
+
+    /// 文档的全部历史版本，新的在前
+    pub fn list(&self, document_id: &str) -> Result<Vec<DocumentVersion>, String> {
+        let mut snapshots = HashMap::new();
+        self.stored(document_id)?
+            .into_iter()
+            .rev()
+            .map(|stored| self.decode(stored, &mut snapshots))
+            .collect()
+    }
+
+    /// 按保留策略删除文档的旧版本，返回删除的个数；有标签或属于命名快照的版本总是保留。
+    /// 被删除的快照上还有增量时，剩下的版本重新编码
+    pub fn prune(&self, document_id: &str, policy: RetentionPolicy) -> Result<usize, String> {
+        let versions = self.list(document_id)?;
+        let prunable: Vec<usize> = (0..versions.len())
+            .filter(|&i| versions[i].label.is_none() && versions[i].snapshot_id.is_none())
+            .collect();
+        let created_at: Vec<i64> = prunable.iter().map(|&i| versions[i].created_at).collect();
+        let expired: Vec<usize> = policy.expired(&created_at, chrono::Utc::now().timestamp())
+            .into_iter()
+            .map(|i| prunable[i])
+            .collect();
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        for &index in &expired {
+            self.conn.execute("DELETE FROM document_versions WHERE id = ?1", [&versions[index].id])
+                .map_err(|e| format!("Failed to delete version: {}", e))?;
+        }
+
+        let orphaned: bool = self.conn
+            .query_row(
+                "SELECT EXISTS(
+                    SELECT 1 FROM document_versions v WHERE v.document_id = ?1 AND v.base_version IS NOT NULL
+                    AND NOT EXISTS (SELECT 1 FROM document_versions b
+                                    WHERE b.document_id = v.document_id AND b.version_number = v.base_version)
+                 )",
+                [document_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to query versions: {}", e))?;
+        if orphaned {
+            let expired: HashSet<usize> = expired.iter().copied().collect();
+            let survivors: Vec<&DocumentVersion> = versions.iter().enumerate()
+                .filter(|(index, _)| !expired.contains(index))
+                .map(|(_, version)| version)
+                .collect();
+            self.rewrite(survivors.into_iter().rev())?;
+        }
+        Ok(expired.len())
+    }
+
+    /// 对所有文档执行保留策略（启动时调用）
+    pub fn prune_all(&self, policy: RetentionPolicy) -> Result<usize, String> {
+        let mut pruned = 0;
+        for document_id in self.document_ids()? {
+            pruned += self.prune(&document_id, policy)?;
+        }
+        Ok(pruned)
+    }
+
+    /// 历史版本占用的空间，按文档分列，占用多的在前
+    pub fn storage_report(&self) -> Result<VersionStorageReport, String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT v.document_id, COALESCE(d.title, ''), COUNT(*),
+                    SUM(CASE WHEN v.data IS NOT NULL AND v.base_version IS NULL THEN 1 ELSE 0 END),
+                    SUM(COALESCE(LENGTH(v.data), 0) + LENGTH(CAST(v.content AS BLOB))),
+                    SUM(CASE WHEN v.data IS NULL THEN LENGTH(CAST(v.content AS BLOB)) ELSE v.size END)
+             FROM document_versions v LEFT JOIN documents d ON d.id = v.document_id
+             GROUP BY v.document_id
+             ORDER BY 5 DESC"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let documents = stmt.query_map([], |row| {
+            Ok(DocumentVersionStorage {
+                document_id: row.get(0)?,
+                title: row.get(1)?,
+                version_count: row.get(2)?,
+                snapshot_count: row.get(3)?,
+                stored_bytes: row.get(4)?,
+                original_bytes: row.get(5)?,
+            })
+        })
+            .map_err(|e| format!("Failed to query version storage: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect version storage: {}", e))?;
+
+        Ok(VersionStorageReport {
+            version_count: documents.iter().map(|d| d.version_count).sum(),
+            snapshot_count: documents.iter().map(|d| d.snapshot_count).sum(),
+            stored_bytes: documents.iter().map(|d| d.stored_bytes).sum(),
+            original_bytes: documents.iter().map(|d| d.original_bytes).sum(),
+            documents,
+        })
+    }
+
+    /// 最新版本的内容和创建时间
+    fn latest(&self, document_id: &str) -> Result<Option<(String, i64)>, String> {
+        let stored = self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM document_versions WHERE document_id = ?1 ORDER BY version_number DESC LIMIT 1",
+                    VERSION_COLUMNS
+                ),
+                [document_id],
+                map_stored,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query versions: {}", e))?;
+        match stored {
+            Some(stored) => {
+                let version = self.decode(stored, &mut HashMap::new())?;
+                Ok(Some((version.content, version.created_at)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 文档的全部原始行，按版本号升序
+    fn stored(&self, document_id: &str) -> Result<Vec<StoredVersion>, String> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT {} FROM document_versions WHERE document_id = ?1 ORDER BY version_number ASC",
+                VERSION_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let versions = stmt.query_map([document_id], map_stored)
+            .map_err(|e| format!("Failed to query versions: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect versions: {}", e))?;
+
+        Ok(versions)
+    }
+
+    fn document_ids(&self) -> Result<Vec<String>, String> {
+        let mut stmt = self.conn
+            .prepare("SELECT DISTINCT document_id FROM document_versions")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let ids = stmt.query_map([], |row| row.get(0))
+            .map_err(|e| format!("Failed to query versions: {}", e))?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("Failed to collect versions: {}", e))?;
+        Ok(ids)
+    }
+
+    /// 还原一行的内容；`snapshots` 缓存已解压的快照（按版本号）
+    fn decode(&self, stored: StoredVersion, snapshots: &mut HashMap<i32, String>) -> Result<DocumentVersion, String> {
+        let mut version = stored.version;
+        let Some(data) = stored.data else {
+            return Ok(version);
+        };
+        version.content = match stored.base_version {
+            None => delta::decompress(&data)?,
+            Some(base) => {
+                if let Entry::Vacant(entry) = snapshots.entry(base) {
+                    let data: Vec<u8> = self.conn
+                        .query_row(
+                            "SELECT data FROM document_versions
+                             WHERE document_id = ?1 AND version_number = ?2 AND base_version IS NULL AND data IS NOT NULL",
+                            params![version.document_id, base],
+                            |row| row.get(0),
+                        )
+                        .map_err(|e| format!("Missing base snapshot for version {}: {}", version.id, e))?;
+                    entry.insert(delta::decompress(&data)?);
+                }
+                delta::apply(&snapshots[&base], &data)?
+            }
+        };
+        Ok(version)
+    }
+
+    /// 按版本号升序重写给定版本的存储
+    fn rewrite<'v>(&self, versions: impl Iterator<Item = &'v DocumentVersion>) -> Result<(), String> {
+        write_chain(self.conn, versions.map(|v| (v.id.as_str(), v.version_number, v.content.as_str())))
+    }
+}
+
+/// 把旧格式（内容直接存在 content 列）的版本改写为快照加增量（v10 迁移完成后使用）
+pub(crate) fn compress_legacy_versions(conn: &Connection) -> Result<(), String> {
+    let rows: Vec<(String, String, i32, String)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, document_id, version_number, content FROM document_versions
+                 WHERE data IS NULL ORDER BY document_id, version_number",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(|e| format!("Failed to query versions: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect versions: {}", e))?;
+        rows
+    };
+    for group in rows.chunk_by(|a, b| a.1 == b.1) {
+        write_chain(conn, group.iter().map(|(id, _, number, content)| (id.as_str(), *number, content.as_str())))?;
+    }
+    Ok(())
+}
+
+/// 按版本号升序依次写入一个文档的版本 (id, 版本号, 内容)，第一个总是完整快照
+fn write_chain<'v>(conn: &Connection, versions: impl Iterator<Item = (&'v str, i32, &'v str)>) -> Result<(), String> {
+    let mut base: Option<(i32, &str)> = None;
+    for (id, version_number, content) in versions {
+        let (base_version, data) = encode(version_number, content, base)?;
+        if base_version.is_none() {
+            base = Some((version_number, content));
+        }
+        conn.execute(
+            "UPDATE document_versions SET content = '', base_version = ?1, data = ?2, size = ?3 WHERE id = ?4",
+            params![base_version, data, content.len() as i64, id],
+        ).map_err(|e| format!("Failed to update version: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 选择存储方式：距离 `base` 快照不足 `SNAPSHOT_EVERY` 个版本且增量不到完整压缩内容的一半时存增量
+fn encode(version_number: i32, content: &str, base: Option<(i32, &str)>) -> Result<(Option<i32>, Vec<u8>), String> {
+    let full = delta::compress(content)?;
+    if let Some((base_number, base_content)) = base {
+        if version_number - base_number < SNAPSHOT_EVERY {
+            let delta = delta::encode(base_content, content)?;
+            if delta.len() * 2 < full.len() {
+                return Ok((Some(base_number), delta));
+            }
+        }
+    }
+    Ok((None, full))
+}
+
+fn map_stored(row: &Row) -> rusqlite::Result<StoredVersion> {
+    Ok(StoredVersion {
+        version: DocumentVersion {
+            id: row.get(0)?,
+            document_id: row.get(1)?,
+            content: row.get(2)?,
+            created_at: row.get(3)?,
+            version_number: row.get(4)?,
+            label: row.get(5)?,
+            note: row.get(6)?,
+            snapshot_id: row.get(7)?,
+        },
+        base_version: row.get(8)?,
+        data: row.get(9)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::test_connection;
+
+    const POLICY: SnapshotPolicy = SnapshotPolicy { interval_secs: 300, min_change_chars: 50 };
+
+    fn backdate(conn: &Connection, id: &str, seconds: i64) {
+        conn.execute("UPDATE documents SET created_at = created_at - ?1 WHERE id = ?2", params![seconds, id]).unwrap();
+        conn.execute("UPDATE document_versions SET created_at = created_at - ?1 WHERE document_id = ?2", params![seconds, id]).unwrap();
+    }
+
+    #[test]
+    fn snapshots_are_throttled() {
+        let conn = test_connection();
+        let documents = DocumentRepo::new(&conn);
+        let versions = VersionRepo::new(&conn);
+        let doc = documents.create("Draft", None).unwrap();
+
+        // 刚创建、改动很小：不保存
+        assert!(!versions.snapshot_before_update(&doc.id, "a", POLICY).unwrap());
+        documents.update_content(&doc.id, "a").unwrap();
+
+        // 超过间隔：保存被覆盖的内容
+        backdate(&conn, &doc.id, 600);
+        assert!(versions.snapshot_before_update(&doc.id, "ab", POLICY).unwrap());
+        documents.update_content(&doc.id, "ab").unwrap();
+        assert_eq!(versions.list(&doc.id).unwrap()[0].content, "a");
+
+        // 间隔内的小改动跳过，大改动保存
+        assert!(!versions.snapshot_before_update(&doc.id, "abc", POLICY).unwrap());
+        assert!(versions.snapshot_before_update(&doc.id, &"x".repeat(60), POLICY).unwrap());
+        assert_eq!(versions.list(&doc.id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn same_length_rewrites_count_as_changes() {
+        let conn = test_connection();
+        let documents = DocumentRepo::new(&conn);
+        let versions = VersionRepo::new(&conn);
+        let doc = documents.create("Draft", None).unwrap();
+        let paragraph = |text: &str| format!(r#"{{"type":"doc","content":[{{"type":"paragraph","content":[{{"type":"text","text":"{}"}}]}}]}}"#, text);
+        documents.update_content(&doc.id, &paragraph(&["one"; 20].join(" "))).unwrap();
+
+        // 长度不变的小改动跳过，整段改写保存
+        let mut words = ["one"; 20];
+        words[5] = "two";
+        assert!(!versions.snapshot_before_update(&doc.id, &paragraph(&words.join(" ")), POLICY).unwrap());
+        assert!(versions.snapshot_before_update(&doc.id, &paragraph(&["two"; 20].join(" ")), POLICY).unwrap());
+        assert_eq!(versions.list(&doc.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn restore_saves_current_content_first() {
+        let conn = test_connection();
+        let documents = DocumentRepo::new(&conn);
+        let versions = VersionRepo::new(&conn);
+        let doc = documents.create("Draft", None).unwrap();
+        documents.update_content(&doc.id, "first").unwrap();
+        versions.save(&doc.id, "first").unwrap();
+        documents.update_content(&doc.id, "second").unwrap();
+
+        let first = versions.list(&doc.id).unwrap().remove(0);
+        versions.restore(&doc.id, &first.id).unwrap();
+        assert_eq!(documents.get(&doc.id).unwrap().content, "first");
+        let history: Vec<_> = versions.list(&doc.id).unwrap().into_iter().map(|v| v.content).collect();
+        assert_eq!(history, vec!["second", "first"]);
+
+        let other = documents.create("Other", None).unwrap();
+        assert!(versions.restore(&other.id, &first.id).is_err());
+    }
+
+    fn paragraphs(n: usize, changed: usize) -> String {
+        let body: Vec<String> = (0..n)
+            .map(|i| {
+                let text = if i == changed { "edited".to_string() } else { format!("paragraph {}", i) };
+                format!(r#"{{"type":"paragraph","content":[{{"type":"text","text":"{}"}}]}}"#, text)
+            })
+            .collect();
+        format!(r#"{{"type":"doc","content":[{}]}}"#, body.join(","))
+    }
+
+    #[test]
+    fn versions_are_stored_as_snapshots_and_deltas() {
+        let conn = test_connection();
+        let doc = DocumentRepo::new(&conn).create("Draft", None).unwrap();
+        let versions = VersionRepo::new(&conn);
+        let contents: Vec<String> = (0..25).map(|i| paragraphs(100, i)).collect();
+        for content in &contents {
+            versions.save(&doc.id, content).unwrap();
+        }
+
+        let listed: Vec<String> = versions.list(&doc.id).unwrap().into_iter().rev().map(|v| v.content).collect();
+        assert_eq!(listed, contents);
+
+        let report = versions.storage_report().unwrap();
+        assert_eq!(report.version_count, 25);
+        assert_eq!(report.snapshot_count, 3);
+        assert!(report.stored_bytes * 10 < report.original_bytes);
+        assert_eq!(report.documents[0].document_id, doc.id);
+    }
+
+    #[test]
+    fn retention_thins_out_old_versions() {
+        let policy = RetentionPolicy { keep_all_secs: 3600, hourly_secs: 86400, daily_secs: 7 * 86400 };
+        let now = 100 * 86400;
+        let created_at = [
+            now - 60,                // 0：最新
+            now - 1800,              // 1：一小时内
+            now - 3 * 3600 - 60,     // 2：第一个小时桶
+            now - 3 * 3600 - 120,    // 3：同一小时，删除
+            now - 2 * 86400 - 60,    // 4：第一个天桶
+            now - 2 * 86400 - 3600,  // 5：同一天，删除
+            now - 30 * 86400,        // 6：超过一周，删除
+        ];
+        assert_eq!(policy.expired(&created_at, now), vec![3, 5, 6]);
+    }
+
+    #[test]
+    fn pruning_a_snapshot_rebases_its_deltas() {
+        let conn = test_connection();
+        let doc = DocumentRepo::new(&conn).create("Draft", None).unwrap();
+        let versions = VersionRepo::new(&conn);
+        let contents: Vec<String> = (0..5).map(|i| paragraphs(50, i)).collect();
+        for content in &contents {
+            versions.save(&doc.id, content).unwrap();
+        }
+        // 第一个版本（完整快照）过期，其余仍是它的增量
+        conn.execute(
+            "UPDATE document_versions SET created_at = created_at - 400 * 86400 WHERE document_id = ?1 AND version_number = 1",
+            [&doc.id],
+        ).unwrap();
+
+        let policy = RetentionPolicy { keep_all_secs: 86400, hourly_secs: 0, daily_secs: 0 };
+        assert_eq!(versions.prune(&doc.id, policy).unwrap(), 1);
+        let listed: Vec<String> = versions.list(&doc.id).unwrap().into_iter().rev().map(|v| v.content).collect();
+        assert_eq!(listed, contents[1..]);
+        assert_eq!(versions.storage_report().unwrap().snapshot_count, 1);
+    }
+
+    #[test]
+    fn labeled_versions_survive_pruning() {
+        let conn = test_connection();
+        let doc = DocumentRepo::new(&conn).create("Draft", None).unwrap();
+        let versions = VersionRepo::new(&conn);
+        let old = versions.save(&doc.id, "sent").unwrap();
+        versions.save(&doc.id, "unlabeled").unwrap();
+        versions.save(&doc.id, "latest").unwrap();
+        conn.execute("UPDATE document_versions SET created_at = created_at - 400 * 86400 WHERE content = '' AND version_number < 3", []).unwrap();
+
+        versions.set_label(&doc.id, &old, Some(" sent to client "), Some("v1 draft")).unwrap();
+        let policy = RetentionPolicy { keep_all_secs: 86400, hourly_secs: 0, daily_secs: 0 };
+        assert_eq!(versions.prune(&doc.id, policy).unwrap(), 1);
+
+        let kept = versions.list(&doc.id).unwrap();
+        assert_eq!(kept.iter().map(|v| v.content.as_str()).collect::<Vec<_>>(), ["latest", "sent"]);
+        assert_eq!(kept[1].label.as_deref(), Some("sent to client"));
+        assert_eq!(kept[1].note.as_deref(), Some("v1 draft"));
+
+        // 取消标签后恢复按策略清理
+        versions.set_label(&doc.id, &old, Some(""), None).unwrap();
+        assert_eq!(versions.prune(&doc.id, policy).unwrap(), 1);
+    }
+}
@@ -0,0 +1,597 @@
+//! 全文搜索：`documents_fts`（FTS5）保存每篇文档抽取出的纯文本，
+//! 文档、标签、上下文总结的每次写入都会调用 `index_document` 同步索引。
+//! 可检索列存放经过 CJK 二元切分的文本（见 `tokenize`），`raw_*` 列保存原文，
+//! 用于生成片段和计算匹配位置。查询语法及过滤条件见 `query`
+
+mod query;
+mod tokenize;
+
+use crate::models::{Document, SearchError, SearchHit, SearchMatch};
+use crate::tiptap;
+use chrono::{Local, NaiveDate, NaiveTime};
+use query::{ClauseKind, Comparison, DateField, Flag, Query};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use tokenize::{query_terms, segment_for_index, QueryTerm};
+
+pub use tokenize::{is_cjk, word_count};
+
+/// 索引中可搜索的字段，顺序与 `documents_fts` 的列一致（第 0 列是 document_id）
+const FIELDS: [&str; 5] = ["title", "body", "ai", "tags", "summary"];
+
+/// 生成片段时优先选择的字段
+const SNIPPET_FIELD_PRIORITY: [usize; 5] = [1, 2, 4, 3, 0];
+
+/// bm25 列权重：document_id 不参与打分，标题权重最高
+const BM25_WEIGHTS: &str = "0.0, 10.0, 1.0, 2.0, 3.0, 1.0";
+
+/// 每条结果最多返回的匹配位置数
+const MAX_MATCHES_PER_HIT: usize = 50;
+
+/// 片段长度（字符）及匹配位置之前保留的上下文长度
+const SNIPPET_CHARS: usize = 64;
+const SNIPPET_LEADING_CHARS: usize = 20;
+
+/// 重新索引单篇文档；文档已不存在时从索引中删除
+pub fn index_document(conn: &Connection, document_id: &str) -> Result<(), String> {
+    let row: Option<(String, String, Option<String>)> = conn
+        .query_row(
+            "SELECT title, content, context_summary FROM documents WHERE id = ?1",
+            [document_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read document for indexing: {}", e))?;
+
+    remove_document(conn, document_id)?;
+
+    let Some((title, content, summary)) = row else {
+        return Ok(());
+    };
+
+    // 内容不是合法 JSON 时只索引标题等字段，不影响保存
+    let text = tiptap::parse(&content).map(|doc| tiptap::plain_text(&doc)).unwrap_or_default();
+    let tags = tag_texts(conn, document_id)?;
+    let summary = summary.unwrap_or_default();
+
+    conn.execute(
+        "INSERT INTO documents_fts (
+            document_id, title, body, ai, tags, summary,
+            raw_title, raw_body, raw_ai, raw_tags, raw_summary
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            document_id,
+            segment_for_index(&title),
+            segment_for_index(&text.body),
+            segment_for_index(&text.ai),
+            segment_for_index(&tags),
+            segment_for_index(&summary),
+            title,
+            text.body,
+            text.ai,
+            tags,
+            summary,
+        ],
+    )
+    .map_err(|e| format!("Failed to index document: {}", e))?;
+
+    Ok(())
+}
+
+pub fn remove_document(conn: &Connection, document_id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM documents_fts WHERE document_id = ?1", [document_id])
+        .map_err(|e| format!("Failed to remove document from index: {}", e))?;
+    Ok(())
+}
+
+/// 清空并重建整个索引
+pub fn rebuild(conn: &Connection) -> Result<(), String> {
+    conn.execute("DELETE FROM documents_fts", [])
+        .map_err(|e| format!("Failed to clear search index: {}", e))?;
+
+    let ids: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT id FROM documents")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let ids = stmt.query_map([], |row| row.get(0))
+            .map_err(|e| format!("Failed to query documents: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect documents: {}", e))?;
+        ids
+    };
+
+    for id in ids {
+        index_document(conn, &id)?;
+    }
+    Ok(())
+}
+
+/// 解析查询语法（见 `query`），按 BM25 排序返回匹配的文档，附带高亮片段和各字段中的匹配位置；
+/// 只有过滤条件、没有检索词时按置顶、更新时间排序
+pub fn search(conn: &Connection, query: &str, limit: usize) -> Result<Vec<SearchHit>, SearchError> {
+    let parsed = query::parse(query).map_err(|e| SearchError::Syntax {
+        message: e.message,
+        start: e.start,
+        end: e.end,
+    })?;
+    if parsed.clauses.is_empty() {
+        return Ok(Vec::new());
+    }
+    let compiled = compile(&parsed);
+
+    let (from, score, order) = if compiled.match_terms.is_empty() {
+        ("documents d LEFT JOIN documents_fts f ON f.document_id = d.id", "0.0".to_string(), "d.is_pinned DESC, d.updated_at DESC")
+    } else {
+        ("documents_fts f JOIN documents d ON d.id = f.document_id", format!("bm25(documents_fts, {})", BM25_WEIGHTS), "score")
+    };
+
+    let mut conditions = vec!["d.deleted_at IS NULL".to_string()];
+    let mut params = Vec::new();
+    if !compiled.match_terms.is_empty() {
+        conditions.push("documents_fts MATCH ?".to_string());
+        params.push(SqlValue::Text(compiled.match_terms.join(" ")));
+    }
+    conditions.extend(compiled.conditions);
+    params.extend(compiled.params);
+    params.push(SqlValue::Integer(limit as i64));
+    let where_clause = conditions.join(" AND ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT d.id, d.title, d.content, d.created_at, d.updated_at, d.context_summary, d.folder_id, d.is_pinned, d.is_important, d.revision,
+                {score} AS score,
+                f.raw_title, f.raw_body, f.raw_ai, f.raw_tags, f.raw_summary
+         FROM {from}
+         WHERE {where_clause}
+         ORDER BY {order}
+         LIMIT ?"
+    )).map_err(|e| format!("Failed to prepare search: {}", e))?;
+
+    let rows = stmt.query_map(params_from_iter(params), |row| {
+        let document = Document {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            context_summary: row.get(5)?,
+            folder_id: row.get(6)?,
+            is_pinned: row.get(7).ok(),
+            is_important: row.get(8).ok(),
+            revision: row.get(9)?,
+        };
+        let score: f64 = row.get(10)?;
+        let mut fields = Vec::with_capacity(FIELDS.len());
+        for i in 0..FIELDS.len() {
+            fields.push(row.get::<_, Option<String>>(11 + i)?.unwrap_or_default());
+        }
+        Ok((document, score, fields))
+    }).map_err(|e| format!("Failed to search documents: {}", e))?;
+
+    let mut hits = Vec::new();
+    for row in rows {
+        let (document, score, fields) = row.map_err(|e| format!("Failed to read search result: {}", e))?;
+        let spans = find_spans(&fields, &compiled.highlights);
+        hits.push(SearchHit {
+            document,
+            // bm25 越小越相关，取反后分数越高越相关
+            score: -score,
+            snippet: build_snippet(&fields, &spans),
+            matches: spans.iter().take(MAX_MATCHES_PER_HIT).map(Span::to_match).collect(),
+        });
+    }
+    Ok(hits)
+}
+
+/// 编译后的查询：正向检索词合并成一个 MATCH 表达式，其余子句变成 documents（别名 d）上的条件，
+/// `params` 按条件中 `?` 出现的顺序排列
+#[derive(Default)]
+struct CompiledQuery {
+    match_terms: Vec<String>,
+    conditions: Vec<String>,
+    params: Vec<SqlValue>,
+    /// 需要在结果中高亮的原文
+    highlights: Vec<String>,
+}
+
+impl CompiledQuery {
+    fn add_text(&mut self, negated: bool, expr: String, highlights: impl IntoIterator<Item = String>) {
+        if negated {
+            self.add_condition(false, "d.id NOT IN (SELECT document_id FROM documents_fts WHERE documents_fts MATCH ?)", vec![SqlValue::Text(expr)]);
+        } else {
+            self.match_terms.push(expr);
+            self.highlights.extend(highlights);
+        }
+    }
+
+    /// 取反时把 NULL（如根目录文档的 folder_id）当作不满足，避免整行被过滤掉
+    fn add_condition(&mut self, negated: bool, sql: &str, params: Vec<SqlValue>) {
+        self.conditions.push(if negated {
+            format!("NOT COALESCE(({}), 0)", sql)
+        } else {
+            format!("({})", sql)
+        });
+        self.params.extend(params);
+    }
+}
+
+fn compile(query: &Query) -> CompiledQuery {
+    let mut compiled = CompiledQuery::default();
+    for clause in &query.clauses {
+        let negated = clause.negated;
+        match &clause.kind {
+            ClauseKind::Word(word) => {
+                let terms = query_terms(word);
+                if terms.is_empty() {
+                    continue;
+                }
+                let expr = terms.iter().map(QueryTerm::to_fts).collect::<Vec<_>>().join(" ");
+                compiled.add_text(negated, expr, terms.iter().map(|t| t.text().to_string()));
+            }
+            ClauseKind::Phrase(phrase) => {
+                if let Some(expr) = tokenize::phrase_to_fts(phrase) {
+                    compiled.add_text(negated, expr, [phrase.trim().to_string()]);
+                }
+            }
+            ClauseKind::Folder(name) => compiled.add_condition(
+                negated,
+                "d.folder_id IN (
+                    WITH RECURSIVE subtree(id) AS (
+                        SELECT id FROM folders WHERE name = ? COLLATE NOCASE
+                        UNION
+                        SELECT c.id FROM folders c JOIN subtree s ON c.parent_id = s.id
+                    )
+                    SELECT id FROM subtree
+                )",
+                vec![SqlValue::Text(name.clone())],
+            ),
+            ClauseKind::Tag(text) => compiled.add_condition(
+                negated,
+                "EXISTS (SELECT 1 FROM tags t WHERE t.document_id = d.id AND t.text = ? COLLATE NOCASE)",
+                vec![SqlValue::Text(text.clone())],
+            ),
+            ClauseKind::Is(flag) => compiled.add_condition(
+                negated,
+                match flag {
+                    Flag::Pinned => "d.is_pinned = 1",
+                    Flag::Important => "d.is_important = 1",
+                },
+                Vec::new(),
+            ),
+            ClauseKind::Date { field, op, date } => {
+                let column = match field {
+                    DateField::Created => "d.created_at",
+                    DateField::Updated => "d.updated_at",
+                };
+                let day_start = local_day_start(*date);
+                let next_day_start = date.succ_opt().map(local_day_start).unwrap_or(i64::MAX);
+                let (sql, params) = match op {
+                    Comparison::Lt => (format!("{} < ?", column), vec![day_start]),
+                    Comparison::Le => (format!("{} < ?", column), vec![next_day_start]),
+                    Comparison::Ge => (format!("{} >= ?", column), vec![day_start]),
+                    Comparison::Gt => (format!("{} >= ?", column), vec![next_day_start]),
+                    Comparison::Eq => (format!("{c} >= ? AND {c} < ?", c = column), vec![day_start, next_day_start]),
+                };
+                compiled.add_condition(negated, &sql, params.into_iter().map(SqlValue::Integer).collect());
+            }
+        }
+    }
+    compiled
+}
+
+/// 本地时区中某一天 0 点的时间戳（秒）
+fn local_day_start(date: NaiveDate) -> i64 {
+    let midnight = date.and_time(NaiveTime::MIN);
+    midnight.and_local_timezone(Local).earliest()
+        .map(|t| t.timestamp())
+        .unwrap_or_else(|| midnight.and_utc().timestamp())
+}
+
+fn tag_texts(conn: &Connection, document_id: &str) -> Result<String, String> {
+    let mut stmt = conn.prepare("SELECT text FROM tags WHERE document_id = ?1 ORDER BY position ASC")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let texts = stmt.query_map([document_id], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to query tags: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect tags: {}", e))?;
+    Ok(texts.join("\n"))
+}
+
+/// 字段纯文本中的一处匹配，同时记录字符下标（生成片段用）和 UTF-16 偏移（返回给前端）
+struct Span {
+    field: usize,
+    char_start: usize,
+    char_end: usize,
+    utf16_start: usize,
+    utf16_end: usize,
+}
+
+impl Span {
+    fn to_match(&self) -> SearchMatch {
+        SearchMatch {
+            field: FIELDS[self.field].to_string(),
+            start: self.utf16_start,
+            end: self.utf16_end,
+        }
+    }
+}
+
+/// 在各字段中定位查询词（大小写不敏感），同一字段内按位置排序
+fn find_spans(fields: &[String], terms: &[String]) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for (field, text) in fields.iter().enumerate() {
+        let haystack = utf16_chars(text);
+        let total_utf16 = text.encode_utf16().count();
+        let mut field_spans = Vec::new();
+        for term in terms {
+            let needle: Vec<char> = term.chars().map(lowercase).collect();
+            if needle.is_empty() || needle.len() > haystack.len() {
+                continue;
+            }
+            for start in 0..=(haystack.len() - needle.len()) {
+                if haystack[start..start + needle.len()].iter().map(|(_, c)| *c).eq(needle.iter().copied()) {
+                    let end = start + needle.len();
+                    field_spans.push(Span {
+                        field,
+                        char_start: start,
+                        char_end: end,
+                        utf16_start: haystack[start].0,
+                        utf16_end: haystack.get(end).map(|(o, _)| *o).unwrap_or(total_utf16),
+                    });
+                }
+            }
+        }
+        field_spans.sort_by_key(|s| (s.char_start, s.char_end));
+        spans.extend(field_spans);
+    }
+    spans
+}
+
+/// 从最合适的字段截取一段原文，匹配处用 <mark></mark> 包起来
+fn build_snippet(fields: &[String], spans: &[Span]) -> String {
+    let field = SNIPPET_FIELD_PRIORITY.iter().copied()
+        .find(|f| spans.iter().any(|s| s.field == *f))
+        .unwrap_or(1);
+    let chars: Vec<char> = fields[field].chars().map(|c| if c == '\n' { ' ' } else { c }).collect();
+    let field_spans: Vec<&Span> = spans.iter().filter(|s| s.field == field).collect();
+
+    let start = field_spans.first()
+        .map(|s| s.char_start.saturating_sub(SNIPPET_LEADING_CHARS))
+        .unwrap_or(0);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut pos = start;
+    for span in field_spans {
+        // 跳过与前一个高亮重叠或超出窗口的匹配
+        if span.char_start < pos || span.char_start >= end {
+            continue;
+        }
+        let span_end = span.char_end.min(end);
+        snippet.extend(&chars[pos..span.char_start]);
+        snippet.push_str("<mark>");
+        snippet.extend(&chars[span.char_start..span_end]);
+        snippet.push_str("</mark>");
+        pos = span_end;
+    }
+    snippet.extend(&chars[pos..end]);
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// 每个字符的 UTF-16 起始偏移和小写形式
+fn utf16_chars(text: &str) -> Vec<(usize, char)> {
+    let mut offset = 0;
+    text.chars()
+        .map(|c| {
+            let item = (offset, lowercase(c));
+            offset += c.len_utf16();
+            item
+        })
+        .collect()
+}
+
+/// 多字符小写只取第一个，保持字符一一对应
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{test_connection, DocumentRepo, FolderRepo, TagRepo};
+
+    fn paragraph_doc(text: &str) -> String {
+        serde_json::json!({
+            "type": "doc",
+            "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": text }] }]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn does_not_match_json_structure() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let doc = repo.create("Notes", None).unwrap();
+        repo.update_content(&doc.id, &paragraph_doc("hello world")).unwrap();
+
+        assert!(search(&conn, "paragraph", 20).unwrap().is_empty());
+        assert_eq!(search(&conn, "hello", 20).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn ranks_title_matches_first_and_reports_offsets() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let body_only = repo.create("Meeting", None).unwrap();
+        repo.update_content(&body_only.id, &paragraph_doc("we discussed the roadmap")).unwrap();
+        let titled = repo.create("Roadmap 2026", None).unwrap();
+
+        let hits = search(&conn, "roadmap", 20).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].document.id, titled.id);
+        assert!(hits[0].score > hits[1].score);
+
+        let body_match = hits[1].matches.iter().find(|m| m.field == "body").unwrap();
+        assert_eq!((body_match.start, body_match.end), (17, 24));
+        assert!(hits[1].snippet.contains("<mark>roadmap</mark>"));
+    }
+
+    #[test]
+    fn indexes_ai_blocks_tags_and_summary() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let doc = repo.create("Untitled", None).unwrap();
+        let content = serde_json::json!({
+            "type": "doc",
+            "content": [{ "type": "aiBlock", "attrs": {
+                "prompt": "explain lifetimes",
+                "response": "borrow checker",
+                "status": "accepted",
+                "model": "Claude-Sonnet-4.5",
+                "timestamp": 1735689600000i64
+            } }]
+        });
+        repo.update_content(&doc.id, &content.to_string()).unwrap();
+        repo.update_context_summary(&doc.id, "quarterly planning").unwrap();
+        TagRepo::new(&conn).create(&doc.id, "urgent", "Untitled", 0, None, None).unwrap();
+
+        for query in ["lifetimes", "borrow", "quarterly", "urgent"] {
+            assert_eq!(search(&conn, query, 20).unwrap().len(), 1, "{}", query);
+        }
+
+        repo.delete(&doc.id).unwrap();
+        assert!(search(&conn, "urgent", 20).unwrap().is_empty());
+    }
+
+    fn create_with_body(repo: &DocumentRepo, title: &str, body: &str) -> String {
+        let doc = repo.create(title, None).unwrap();
+        repo.update_content(&doc.id, &paragraph_doc(body)).unwrap();
+        doc.id
+    }
+
+    #[test]
+    fn searches_mixed_language_corpus() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let zh = create_with_body(&repo, "会议记录", "今天讨论了北京大学的合作项目，下周提交 Rust 编程方案。");
+        let zh_other = create_with_body(&repo, "旅行", "计划去京都看红叶。");
+        let ja = create_with_body(&repo, "日記", "東京タワーへ行きました。とても楽しかった。");
+        let ko = create_with_body(&repo, "메모", "한국어 자연어 처리 공부하기");
+        let en = create_with_body(&repo, "Notes", "Plain English notes about the Beijing trip.");
+
+        let ids = |q: &str| search(&conn, q, 20).unwrap().into_iter().map(|h| h.document.id).collect::<Vec<_>>();
+
+        assert_eq!(ids("北京大学"), vec![zh.clone()]);
+        assert_eq!(ids("合作"), vec![zh.clone()]);
+        // 单字：既能命中段中间的字（北京、京都），也能命中段末尾的字（東京）
+        let mut single = ids("京");
+        single.sort();
+        let mut expected = vec![zh.clone(), zh_other.clone(), ja.clone()];
+        expected.sort();
+        assert_eq!(single, expected);
+        assert_eq!(ids("叶"), vec![zh_other.clone()]);
+        // 不连续的字不应该当作短语命中
+        assert!(ids("北合").is_empty());
+        assert_eq!(ids("東京タワー"), vec![ja.clone()]);
+        assert_eq!(ids("京都"), vec![zh_other.clone()]);
+        assert_eq!(ids("楽しかった"), vec![ja]);
+        assert_eq!(ids("자연어"), vec![ko]);
+        assert_eq!(ids("Rust编程"), vec![zh.clone()]);
+        assert_eq!(ids("beijing"), vec![en]);
+        assert_eq!(ids("会议"), vec![zh]);
+    }
+
+    #[test]
+    fn ranks_and_highlights_cjk_matches() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let once = create_with_body(&repo, "周报", "本周完成了搜索功能的开发。");
+        let titled = create_with_body(&repo, "搜索功能设计", "索引、排序和高亮。");
+
+        let hits = search(&conn, "搜索", 20).unwrap();
+        assert_eq!(hits.iter().map(|h| h.document.id.clone()).collect::<Vec<_>>(), vec![titled, once]);
+
+        let body = &hits[1];
+        assert!(body.snippet.contains("<mark>搜索</mark>"), "{}", body.snippet);
+        let m = body.matches.iter().find(|m| m.field == "body").unwrap();
+        assert_eq!((m.start, m.end), (5, 7));
+    }
+
+    #[test]
+    fn filters_by_folder_tag_flags_and_dates() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let folders = FolderRepo::new(&conn);
+        let work = folders.create("Work", None).unwrap();
+        let projects = folders.create("Projects", Some(&work.id)).unwrap();
+
+        let in_work = create_with_body(&repo, "Plan", "release plan");
+        repo.move_to_folder(&in_work, Some(&work.id)).unwrap();
+        let nested = create_with_body(&repo, "Spec", "release spec");
+        repo.move_to_folder(&nested, Some(&projects.id)).unwrap();
+        repo.toggle_pinned(&nested).unwrap();
+        let root = create_with_body(&repo, "Diary", "release party");
+        repo.toggle_important(&root).unwrap();
+        TagRepo::new(&conn).create(&root, "Urgent", "Diary", 0, None, None).unwrap();
+
+        let old = local_day_start(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()) + 3600;
+        conn.execute("UPDATE documents SET updated_at = ?1 WHERE id = ?2", params![old, in_work]).unwrap();
+
+        let ids = |q: &str| {
+            let mut ids = search(&conn, q, 20).unwrap().into_iter().map(|h| h.document.id).collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut v: Vec<String>| {
+            v.sort();
+            v
+        };
+
+        assert_eq!(ids("folder:work"), sorted(vec![in_work.clone(), nested.clone()]));
+        assert_eq!(ids("folder:Projects release"), vec![nested.clone()]);
+        assert_eq!(ids("-folder:Work"), vec![root.clone()]);
+        assert_eq!(ids("tag:urgent"), vec![root.clone()]);
+        assert_eq!(ids("is:pinned"), vec![nested.clone()]);
+        assert_eq!(ids("is:important release"), vec![root.clone()]);
+        assert_eq!(ids("updated:<2026-01-01"), vec![in_work.clone()]);
+        assert_eq!(ids("updated:2025-06-01"), vec![in_work.clone()]);
+        assert_eq!(ids("release updated:>2026-01-01"), sorted(vec![nested, root]));
+    }
+
+    #[test]
+    fn matches_phrases_and_excludes_terms() {
+        let conn = test_connection();
+        let repo = DocumentRepo::new(&conn);
+        let exact = create_with_body(&repo, "A", "the exact phrase appears here");
+        let scattered = create_with_body(&repo, "B", "a phrase that is not exact");
+        let zh = create_with_body(&repo, "C", "北京大学的合作项目");
+
+        let ids = |q: &str| search(&conn, q, 20).unwrap().into_iter().map(|h| h.document.id).collect::<Vec<_>>();
+
+        assert_eq!(ids("\"exact phrase\""), vec![exact.clone()]);
+        assert_eq!(ids("phrase -appears"), vec![scattered.clone()]);
+        assert_eq!(ids("-\"exact phrase\" phrase"), vec![scattered]);
+        assert_eq!(ids("\"京大学\""), vec![zh.clone()]);
+        assert!(ids("\"大学合作\"").is_empty());
+
+        let hit = &search(&conn, "\"exact phrase\"", 20).unwrap()[0];
+        assert!(hit.snippet.contains("<mark>exact phrase</mark>"), "{}", hit.snippet);
+    }
+
+    #[test]
+    fn returns_syntax_errors_with_positions() {
+        let conn = test_connection();
+        assert_eq!(
+            search(&conn, "notes is:archived", 20).unwrap_err(),
+            SearchError::Syntax {
+                message: "Unknown flag 'archived', expected 'pinned' or 'important'".into(),
+                start: 9,
+                end: 17,
+            }
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! 搜索查询语法：
+//!
+//! ```text
+//! folder:Work tag:urgent is:pinned is:important updated:>2026-01-01 "exact phrase" -exclude
+//! ```
+//!
+//! - 普通词、`"短语"` 走全文索引；前缀 `-` 表示排除
+//! - `folder:名称`（包含子文件夹）、`tag:文本`、`is:pinned|important`
+//! - `created:` / `updated:` 后跟 `YYYY-MM-DD`，可加 `>` `>=` `<` `<=` `=`，按本地日期比较
+//! - 字段值可以加引号：`folder:"My Work"`；未知字段名按普通词处理（如 `10:30`）
+//!
+//! 所有位置都是 UTF-16 偏移，前端可以直接用来标出错误位置
+
+use chrono::NaiveDate;
+
+/// 查询语法错误，`start..end` 为出错片段在查询字符串中的 UTF-16 偏移
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause {
+    pub negated: bool,
+    pub kind: ClauseKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClauseKind {
+    Word(String),
+    Phrase(String),
+    Folder(String),
+    Tag(String),
+    Is(Flag),
+    Date { field: DateField, op: Comparison, date: NaiveDate },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag {
+    Pinned,
+    Important,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Created,
+    Updated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+pub fn parse(input: &str) -> Result<Query, ParseError> {
+    Parser::new(input).parse()
+}
+
+struct Parser {
+    /// (UTF-16 偏移, 字符)
+    chars: Vec<(usize, char)>,
+    end_offset: usize,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        let mut offset = 0;
+        let chars = input.chars()
+            .map(|c| {
+                let item = (offset, c);
+                offset += c.len_utf16();
+                item
+            })
+            .collect();
+        Self { chars, end_offset: offset, pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<Query, ParseError> {
+        let mut clauses = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek().is_none() {
+                break;
+            }
+            clauses.push(self.clause()?);
+        }
+        Ok(Query { clauses })
+    }
+
+    fn clause(&mut self) -> Result<Clause, ParseError> {
+        let start = self.offset();
+        let negated = self.peek() == Some('-');
+        if negated {
+            self.pos += 1;
+            if self.peek().is_none_or(char::is_whitespace) {
+                return Err(self.error("Expected a term after '-'", start, self.offset()));
+            }
+        }
+
+        let kind = if self.peek() == Some('"') {
+            let text = self.quoted()?;
+            if text.trim().is_empty() {
+                return Err(self.error("Empty phrase", start, self.offset()));
+            }
+            ClauseKind::Phrase(text)
+        } else {
+            let word_start = self.offset();
+            let word = self.bare_word();
+            match word.split_once(':') {
+                Some((key, rest)) if is_field(key) => {
+                    let value_start = word_start + key.encode_utf16().count() + 1;
+                    let value = if rest.is_empty() && self.peek() == Some('"') {
+                        self.quoted()?
+                    } else {
+                        rest.to_string()
+                    };
+                    self.field(key, &value, value_start)?
+                }
+                _ => ClauseKind::Word(word),
+            }
+        };
+
+        Ok(Clause { negated, kind })
+    }
+
+    fn field(&self, key: &str, value: &str, value_start: usize) -> Result<ClauseKind, ParseError> {
+        let value_end = self.offset();
+        if value.trim().is_empty() {
+            return Err(self.error(&format!("Missing value for '{}:'", key), value_start, value_end));
+        }
+
+        match key {
+            "folder" => Ok(ClauseKind::Folder(value.to_string())),
+            "tag" => Ok(ClauseKind::Tag(value.to_string())),
+            "is" => match value.to_lowercase().as_str() {
+                "pinned" => Ok(ClauseKind::Is(Flag::Pinned)),
+                "important" => Ok(ClauseKind::Is(Flag::Important)),
+                _ => Err(self.error(
+                    &format!("Unknown flag '{}', expected 'pinned' or 'important'", value),
+                    value_start,
+                    value_end,
+                )),
+            },
+            "created" | "updated" => {
+                let field = if key == "created" { DateField::Created } else { DateField::Updated };
+                let (op, date_str) = split_comparison(value);
+                let op_len = value.len() - date_str.len();
+                let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_| {
+                    self.error(
+                        &format!("Invalid date '{}', expected YYYY-MM-DD", date_str),
+                        value_start + op_len,
+                        value_end,
+                    )
+                })?;
+                Ok(ClauseKind::Date { field, op, date })
+            }
+            _ => unreachable!("is_field guards the key"),
+        }
+    }
+
+    /// 读取引号内的文本，`""` 表示一个引号字符
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let start = self.offset();
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated quote", start, self.end_offset)),
+                Some('"') => {
+                    self.pos += 1;
+                    if self.peek() == Some('"') {
+                        text.push('"');
+                        self.pos += 1;
+                    } else {
+                        return Ok(text);
+                    }
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// 读取到下一个空白为止；遇到 `key:"` 时停在引号前，由调用方读取引号值
+    fn bare_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || (c == '"' && word.ends_with(':')) {
+                break;
+            }
+            word.push(c);
+            self.pos += 1;
+        }
+        word
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|(_, c)| *c)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars.get(self.pos).map(|(o, _)| *o).unwrap_or(self.end_offset)
+    }
+
+    fn error(&self, message: &str, start: usize, end: usize) -> ParseError {
+        ParseError { message: message.to_string(), start, end }
+    }
+}
+
+fn is_field(key: &str) -> bool {
+    matches!(key, "folder" | "tag" | "is" | "created" | "updated")
+}
+
+fn split_comparison(value: &str) -> (Comparison, &str) {
+    for (prefix, op) in [(">=", Comparison::Ge), ("<=", Comparison::Le), (">", Comparison::Gt), ("<", Comparison::Lt), ("=", Comparison::Eq)] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (op, rest);
+        }
+    }
+    (Comparison::Eq, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<(bool, ClauseKind)> {
+        parse(input).unwrap().clauses.into_iter().map(|c| (c.negated, c.kind)).collect()
+    }
+
+    #[test]
+    fn parses_full_example() {
+        assert_eq!(
+            kinds(r#"folder:Work tag:urgent is:pinned is:important updated:>2026-01-01 "exact phrase" -exclude"#),
+            vec![
+                (false, ClauseKind::Folder("Work".into())),
+                (false, ClauseKind::Tag("urgent".into())),
+                (false, ClauseKind::Is(Flag::Pinned)),
+                (false, ClauseKind::Is(Flag::Important)),
+                (false, ClauseKind::Date {
+                    field: DateField::Updated,
+                    op: Comparison::Gt,
+                    date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+                }),
+                (false, ClauseKind::Phrase("exact phrase".into())),
+                (true, ClauseKind::Word("exclude".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_quoted_values_and_unknown_fields() {
+        assert_eq!(
+            kinds(r#"folder:"My Work" -tag:done 10:30 会议"#),
+            vec![
+                (false, ClauseKind::Folder("My Work".into())),
+                (true, ClauseKind::Tag("done".into())),
+                (false, ClauseKind::Word("10:30".into())),
+                (false, ClauseKind::Word("会议".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let err = parse(r#"tag:a "open"#).unwrap_err();
+        assert_eq!((err.start, err.end), (6, 11));
+
+        let err = parse("is:archived").unwrap_err();
+        assert_eq!((err.start, err.end), (3, 11));
+
+        let err = parse("updated:>2026-13-01").unwrap_err();
+        assert_eq!((err.start, err.end), (9, 19));
+
+        let err = parse("notes tag:").unwrap_err();
+        assert_eq!((err.start, err.end), (10, 10));
+
+        let err = parse("a - b").unwrap_err();
+        assert_eq!((err.start, err.end), (2, 3));
+    }
+
+    #[test]
+    fn positions_are_utf16() {
+        let err = parse("😀 is:x").unwrap_err();
+        assert_eq!((err.start, err.end), (6, 7));
+    }
+}
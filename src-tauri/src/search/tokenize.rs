@@ -0,0 +1,245 @@
+//! 中日韩文本分词：FTS5 自带的 unicode61 分词器把连续的汉字/假名/谚文当成一个词，
+//! 所以写入索引前把 CJK 连续段切成相互重叠的二元组（bigram），查询时做同样的切分。
+//!
+//! 索引：`北京大学` → `北京 京大 大学 学`（末尾补一个单字，保证任意单字都能前缀命中）
+//! 查询：`京大` → 短语 `"京大"`；`北京大` → 短语 `"北京 京大"`；单字 `京` → 前缀 `"京"*`
+
+/// 查询中的一个检索单元
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryTerm {
+    /// 普通词（拉丁字母、数字等），按前缀匹配
+    Word(String),
+    /// 连续的 CJK 字符
+    Cjk(String),
+}
+
+impl QueryTerm {
+    /// 原始文本，用于在纯文本中定位匹配位置
+    pub fn text(&self) -> &str {
+        match self {
+            QueryTerm::Word(s) | QueryTerm::Cjk(s) => s,
+        }
+    }
+
+    /// 转成 FTS5 MATCH 表达式片段
+    pub fn to_fts(&self) -> String {
+        match self {
+            QueryTerm::Word(word) => format!("{}*", quote(word)),
+            QueryTerm::Cjk(run) => {
+                let chars: Vec<char> = run.chars().collect();
+                if chars.len() == 1 {
+                    format!("{}*", quote(run))
+                } else {
+                    quote(&bigrams(&chars).join(" "))
+                }
+            }
+        }
+    }
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x309F      // 平假名
+        | 0x30A0..=0x30FF    // 片假名
+        | 0x31F0..=0x31FF    // 片假名扩展
+        | 0x3400..=0x4DBF    // CJK 扩展 A
+        | 0x4E00..=0x9FFF    // CJK 统一汉字
+        | 0xF900..=0xFAFF    // CJK 兼容汉字
+        | 0xFF66..=0xFF9F    // 半角片假名
+        | 0x1100..=0x11FF    // 谚文字母
+        | 0x3130..=0x318F    // 谚文兼容字母
+        | 0xAC00..=0xD7AF    // 谚文音节
+        | 0x20000..=0x2FA1F  // CJK 扩展 B 及以后
+    )
+}
+
+/// 字数：CJK 字符每个算一个词，其余按连续的字母数字算一个词（词中的撇号不断开）
+pub fn word_count(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                count += 1;
+            }
+            in_word = true;
+        } else if !(in_word && matches!(c, '\'' | '\u{2019}')) {
+            in_word = false;
+        }
+    }
+    count
+}
+
+/// 把文本改写成写入 FTS 索引的形式：CJK 段替换为二元组，其余文本原样保留
+pub fn segment_for_index(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    for (run, cjk) in script_runs(text) {
+        if cjk {
+            let chars: Vec<char> = run.chars().collect();
+            out.push(' ');
+            out.push_str(&bigrams(&chars).join(" "));
+            if chars.len() > 1 {
+                out.push(' ');
+                out.push(chars[chars.len() - 1]);
+            }
+            out.push(' ');
+        } else {
+            out.push_str(run);
+        }
+    }
+    out
+}
+
+/// 把用户输入切成检索单元：先按空白切分，再按 CJK / 非 CJK 切开（`rust编程` → `rust`、`编程`）
+pub fn query_terms(query: &str) -> Vec<QueryTerm> {
+    let mut terms = Vec::new();
+    for word in query.split_whitespace() {
+        for (run, cjk) in script_runs(word) {
+            if cjk {
+                terms.push(QueryTerm::Cjk(run.to_string()));
+            } else {
+                // 标点只作为分隔符，避免传给 FTS5 产生语法错误
+                for part in run.split(|c: char| !c.is_alphanumeric() && c != '_') {
+                    if !part.is_empty() {
+                        terms.push(QueryTerm::Word(part.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    terms
+}
+
+/// 把精确短语转成 FTS5 短语表达式，切分方式与 `segment_for_index` 保持一致，保证词序相邻：
+/// 中间的 CJK 段后面要带上索引里补的单字，末尾的 CJK 段不带（原文里该段可能还没结束），
+/// 末尾是单个 CJK 字时改为前缀匹配。没有可检索的内容时返回 None
+pub fn phrase_to_fts(phrase: &str) -> Option<String> {
+    let runs = script_runs(phrase.trim());
+    let mut tokens = Vec::new();
+    let mut prefix = false;
+    for (i, (run, cjk)) in runs.iter().enumerate() {
+        if *cjk {
+            let chars: Vec<char> = run.chars().collect();
+            tokens.extend(bigrams(&chars));
+            if i + 1 == runs.len() {
+                prefix = chars.len() == 1;
+            } else if chars.len() > 1 {
+                tokens.push(chars[chars.len() - 1].to_string());
+            }
+        } else {
+            tokens.extend(
+                run.split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .filter(|part| !part.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+    if tokens.is_empty() {
+        return None;
+    }
+    let expr = quote(&tokens.join(" "));
+    Some(if prefix { format!("{}*", expr) } else { expr })
+}
+
+/// 按是否 CJK 把文本切成连续段
+fn script_runs(text: &str) -> Vec<(&str, bool)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut current: Option<bool> = None;
+    for (i, c) in text.char_indices() {
+        let cjk = is_cjk(c);
+        match current {
+            Some(prev) if prev != cjk => {
+                runs.push((&text[start..i], prev));
+                start = i;
+                current = Some(cjk);
+            }
+            None => current = Some(cjk),
+            _ => {}
+        }
+    }
+    if let Some(cjk) = current {
+        runs.push((&text[start..], cjk));
+    }
+    runs
+}
+
+fn bigrams(chars: &[char]) -> Vec<String> {
+    if chars.len() < 2 {
+        return chars.iter().map(|c| c.to_string()).collect();
+    }
+    chars.windows(2).map(|w| w.iter().collect()).collect()
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_cjk_runs_into_bigrams() {
+        assert_eq!(segment_for_index("北京大学").split_whitespace().collect::<Vec<_>>(), ["北京", "京大", "大学", "学"]);
+        assert_eq!(segment_for_index("学").split_whitespace().collect::<Vec<_>>(), ["学"]);
+    }
+
+    #[test]
+    fn keeps_latin_text_and_splits_mixed_scripts() {
+        assert_eq!(
+            segment_for_index("Rust编程 is fun").split_whitespace().collect::<Vec<_>>(),
+            ["Rust", "编程", "程", "is", "fun"]
+        );
+        assert_eq!(
+            segment_for_index("東京タワーへ行く").split_whitespace().collect::<Vec<_>>(),
+            ["東京", "京タ", "タワ", "ワー", "ーへ", "へ行", "行く", "く"]
+        );
+    }
+
+    #[test]
+    fn counts_words_across_scripts() {
+        assert_eq!(word_count("Don't panic, 42 times"), 4);
+        assert_eq!(word_count("Rust编程 is fun"), 5);
+        assert_eq!(word_count("  \n "), 0);
+    }
+
+    #[test]
+    fn builds_query_terms() {
+        assert_eq!(
+            query_terms("rust编程 한국어"),
+            vec![
+                QueryTerm::Word("rust".into()),
+                QueryTerm::Cjk("编程".into()),
+                QueryTerm::Cjk("한국어".into()),
+            ]
+        );
+        assert_eq!(QueryTerm::Cjk("北京大".into()).to_fts(), "\"北京 京大\"");
+        assert_eq!(QueryTerm::Cjk("京".into()).to_fts(), "\"京\"*");
+        assert_eq!(QueryTerm::Word("note".into()).to_fts(), "\"note\"*");
+    }
+
+    #[test]
+    fn builds_phrases_matching_index_segmentation() {
+        assert_eq!(phrase_to_fts("exact phrase").as_deref(), Some("\"exact phrase\""));
+        assert_eq!(phrase_to_fts("世界 hello").as_deref(), Some("\"世界 界 hello\""));
+        assert_eq!(phrase_to_fts("hello 京").as_deref(), Some("\"hello 京\"*"));
+        assert_eq!(phrase_to_fts("  !? "), None);
+    }
+
+    #[test]
+    fn strips_fts_syntax_from_words() {
+        assert_eq!(
+            query_terms("\"hello\" (world) a-b"),
+            vec![
+                QueryTerm::Word("hello".into()),
+                QueryTerm::Word("world".into()),
+                QueryTerm::Word("a".into()),
+                QueryTerm::Word("b".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! `Document.content` 中 TipTap / ProseMirror JSON 的类型化模型。
+//!
+//! 编辑器用到的节点和标记都有对应的类型；未声明的属性、字段保存在 `extra` 中，
+//! 未知类型的节点落到 `Node::Other`（子节点仍然是类型化的），连结构都不符合的落到 `Node::Raw`，
+//! 所以任何内容都可以无损地反序列化再序列化回去。遍历见 `visit`
+
+pub mod visit;
+
+pub use visit::plain_text;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// 未类型化的属性
+pub type Attrs = Map<String, Value>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Node {
+    Doc(Element),
+    Paragraph(Element),
+    Heading(Element<HeadingAttrs>),
+    Blockquote(Element),
+    BulletList(Element),
+    OrderedList(Element<OrderedListAttrs>),
+    ListItem(Element),
+    TaskList(Element),
+    TaskItem(Element<TaskItemAttrs>),
+    CodeBlock(Element<CodeBlockAttrs>),
+    HorizontalRule(Element),
+    HardBreak(Element),
+    Image(Element<ImageAttrs>),
+    Table(Element),
+    TableRow(Element),
+    TableHeader(Element),
+    TableCell(Element),
+    AiBlock(Element<AiBlockAttrs>),
+    ContextSeparator(Element),
+    Text(TextNode),
+    /// 未建模的节点类型，或属性与类型化定义不符的已知节点
+    #[serde(untagged)]
+    Other(OtherNode),
+    /// 不是合法节点结构的 JSON，原样保留
+    #[serde(untagged)]
+    Raw(Value),
+}
+
+/// 非文本节点的公共结构，`A` 为属性类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(deserialize = "A: Deserialize<'de>"))]
+pub struct Element<A = Attrs> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attrs: Option<A>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<Vec<Node>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marks: Option<Vec<Mark>>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+impl<A> Element<A> {
+    /// 新建节点；没有子节点时不输出 content，与 TipTap 一致
+    pub fn new(attrs: Option<A>, content: Vec<Node>) -> Self {
+        Self {
+            attrs,
+            content: if content.is_empty() { None } else { Some(content) },
+            marks: None,
+            extra: Attrs::new(),
+        }
+    }
+
+    pub fn children(&self) -> &[Node] {
+        self.content.as_deref().unwrap_or(&[])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextNode {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marks: Option<Vec<Mark>>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+impl TextNode {
+    pub fn new(text: impl Into<String>, marks: Vec<Mark>) -> Self {
+        Self {
+            text: text.into(),
+            marks: if marks.is_empty() { None } else { Some(marks) },
+            extra: Attrs::new(),
+        }
+    }
+}
+
+/// 未建模的节点：保留类型名和子节点，其余字段（含 attrs）原样放在 `extra`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtherNode {
+    #[serde(rename = "type")]
+    pub node_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<Vec<Node>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marks: Option<Vec<Mark>>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+// TipTap 总是输出全部属性（未设置时为 null），可空属性用 `Option::deserialize`
+// 要求字段必须出现，缺失时整个节点落到 `Node::Other`，保证序列化回去不会多出字段
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeadingAttrs {
+    pub level: u8,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderedListAttrs {
+    pub start: i64,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskItemAttrs {
+    pub checked: bool,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeBlockAttrs {
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub language: Option<String>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageAttrs {
+    pub src: String,
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub alt: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub title: Option<String>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+/// AI 对话块，对应前端 `AIBlockAttrs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AiBlockAttrs {
+    pub prompt: String,
+    pub response: String,
+    /// streaming / complete / accepted / discarded
+    pub status: String,
+    pub model: String,
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+impl AiBlockAttrs {
+    /// 已完成或已采纳的对话；生成中、已丢弃的对话不导出
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "complete" | "accepted")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Mark {
+    Bold(MarkData),
+    Italic(MarkData),
+    Strike(MarkData),
+    Code(MarkData),
+    Link(MarkData<LinkAttrs>),
+    DocumentLink(MarkData<DocumentLinkAttrs>),
+    #[serde(rename = "tagMark")]
+    Tag(MarkData<TagMarkAttrs>),
+    #[serde(untagged)]
+    Other(Value),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(deserialize = "A: Deserialize<'de>"))]
+pub struct MarkData<A = Attrs> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attrs: Option<A>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+impl<A> MarkData<A> {
+    pub fn new(attrs: Option<A>) -> Self {
+        Self { attrs, extra: Attrs::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkAttrs {
+    pub href: String,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+/// 文档间链接，`documentId` 指向被引用的文档
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLinkAttrs {
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub document_id: Option<String>,
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub title: Option<String>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+/// 标签高亮，`tagId` 对应 tags 表中的记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagMarkAttrs {
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub tag_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Attrs,
+}
+
+impl Node {
+    pub fn children(&self) -> &[Node] {
+        match self {
+            Node::Doc(e)
+            | Node::Paragraph(e)
+            | Node::Blockquote(e)
+            | Node::BulletList(e)
+            | Node::ListItem(e)
+            | Node::TaskList(e)
+            | Node::HorizontalRule(e)
+            | Node::HardBreak(e)
+            | Node::Table(e)
+            | Node::TableRow(e)
+            | Node::TableHeader(e)
+            | Node::TableCell(e)
+            | Node::ContextSeparator(e) => e.children(),
+            Node::Heading(e) => e.children(),
+            Node::OrderedList(e) => e.children(),
+            Node::TaskItem(e) => e.children(),
+            Node::CodeBlock(e) => e.children(),
+            Node::Image(e) => e.children(),
+            Node::AiBlock(e) => e.children(),
+            Node::Other(other) => other.content.as_deref().unwrap_or(&[]),
+            Node::Text(_) | Node::Raw(_) => &[],
+        }
+    }
+
+    /// 可修改的子节点列表；没有 content 的节点返回 None
+    pub fn content_mut(&mut self) -> Option<&mut Vec<Node>> {
+        let content = match self {
+            Node::Doc(e)
+            | Node::Paragraph(e)
+            | Node::Blockquote(e)
+            | Node::BulletList(e)
+            | Node::ListItem(e)
+            | Node::TaskList(e)
+            | Node::HorizontalRule(e)
+            | Node::HardBreak(e)
+            | Node::Table(e)
+            | Node::TableRow(e)
+            | Node::TableHeader(e)
+            | Node::TableCell(e)
+            | Node::ContextSeparator(e) => &mut e.content,
+            Node::Heading(e) => &mut e.content,
+            Node::OrderedList(e) => &mut e.content,
+            Node::TaskItem(e) => &mut e.content,
+            Node::CodeBlock(e) => &mut e.content,
+            Node::Image(e) => &mut e.content,
+            Node::AiBlock(e) => &mut e.content,
+            Node::Other(other) => &mut other.content,
+            Node::Text(_) | Node::Raw(_) => return None,
+        };
+        content.as_mut()
+    }
+
+    /// 文本节点上的标记，其他节点返回空
+    pub fn marks(&self) -> &[Mark] {
+        match self {
+            Node::Text(text) => text.marks.as_deref().unwrap_or(&[]),
+            _ => &[],
+        }
+    }
+}
+
+/// 解析 `Document.content`
+pub fn parse(content: &str) -> Result<Node, String> {
+    serde_json::from_str(content).map_err(|e| format!("Failed to parse document content: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 覆盖编辑器中所有节点、标记的文档，格式与 TipTap `getJSON()` 一致
+    fn sample_doc() -> Value {
+        json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "attrs": { "level": 2 }, "content": [{ "type": "text", "text": "Plan" }] },
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "Ship " },
+                    { "type": "text", "marks": [{ "type": "bold" }, { "type": "italic" }], "text": "search" },
+                    { "type": "hardBreak" },
+                    { "type": "text", "marks": [{ "type": "link", "attrs": { "href": "https://example.com", "target": "_blank", "rel": null, "class": null } }], "text": "docs" },
+                    { "type": "text", "marks": [{ "type": "documentLink", "attrs": { "documentId": "doc-2", "title": "Spec" } }], "text": "Spec" },
+                    { "type": "text", "marks": [{ "type": "tagMark", "attrs": { "tagId": "tag-1" } }], "text": "urgent" }
+                ] },
+                { "type": "paragraph" },
+                { "type": "bulletList", "content": [
+                    { "type": "listItem", "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "one" }] }] }
+                ] },
+                { "type": "orderedList", "attrs": { "start": 3, "type": null }, "content": [
+                    { "type": "listItem", "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "three" }] }] }
+                ] },
+                { "type": "taskList", "content": [
+                    { "type": "taskItem", "attrs": { "checked": true }, "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "done" }] }] },
+                    { "type": "taskItem", "attrs": { "checked": false }, "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "todo" }] }] }
+                ] },
+                { "type": "codeBlock", "attrs": { "language": null }, "content": [{ "type": "text", "text": "fn main() {}" }] },
+                { "type": "blockquote", "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "quoted" }] }] },
+                { "type": "horizontalRule" },
+                { "type": "table", "content": [
+                    { "type": "tableRow", "content": [
+                        { "type": "tableHeader", "attrs": { "colspan": 1, "rowspan": 1, "colwidth": null }, "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "A" }] }] }
+                    ] },
+                    { "type": "tableRow", "content": [
+                        { "type": "tableCell", "attrs": { "colspan": 1, "rowspan": 1, "colwidth": [120] }, "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "1" }] }] }
+                    ] }
+                ] },
+                { "type": "image", "attrs": { "src": "data:image/png;base64,AAAA", "alt": null, "title": null } },
+                { "type": "aiBlock", "attrs": { "prompt": "explain", "response": "because", "status": "accepted", "model": "gpt", "timestamp": 1735689600000i64 } },
+                { "type": "contextSeparator" }
+            ]
+        })
+    }
+
+    #[test]
+    fn round_trips_editor_content() {
+        let value = sample_doc();
+        let node: Node = serde_json::from_value(value.clone()).unwrap();
+        assert!(matches!(node, Node::Doc(_)));
+        assert_eq!(serde_json::to_value(&node).unwrap(), value);
+
+        let Node::Doc(doc) = &node else { unreachable!() };
+        assert!(matches!(&doc.children()[1].children()[5], Node::Text(t) if matches!(t.marks.as_deref(), Some([Mark::Tag(_)]))));
+        assert!(doc.children().iter().all(|n| !matches!(n, Node::Other(_) | Node::Raw(_))));
+    }
+
+    #[test]
+    fn keeps_unknown_and_malformed_nodes() {
+        let value = json!({
+            "type": "doc",
+            "meta": { "version": 2 },
+            "content": [
+                { "type": "mention", "attrs": { "id": "u1" }, "content": [{ "type": "text", "text": "@bob" }] },
+                { "type": "heading", "attrs": { "level": "big" }, "content": [{ "type": "text", "text": "Title" }] },
+                { "type": "codeBlock", "attrs": {}, "content": [] },
+                { "type": "paragraph", "content": [{ "type": "text", "text": "x", "marks": [{ "type": "highlight", "attrs": { "color": "red" } }] }] },
+                "stray",
+                { "no_type": true }
+            ]
+        });
+        let node: Node = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&node).unwrap(), value);
+
+        let children = node.children();
+        assert!(matches!(&children[0], Node::Other(o) if o.node_type == "mention"));
+        assert_eq!(children[0].children().len(), 1);
+        assert!(matches!(&children[1], Node::Other(o) if o.node_type == "heading"));
+        assert!(matches!(&children[2], Node::Other(_)));
+        assert!(matches!(&children[4], Node::Raw(_)));
+    }
+}
@@ -0,0 +1,108 @@
+use super::Node;
+
+/// 深度优先遍历文档树的回调
+pub trait Visitor {
+    /// 进入节点；返回 false 时跳过它的子节点（`leave` 仍会调用）
+    fn enter(&mut self, _node: &Node, _depth: usize) -> bool {
+        true
+    }
+
+    fn leave(&mut self, _node: &Node, _depth: usize) {}
+}
+
+/// 按文档顺序遍历 `node` 及其所有子孙节点
+pub fn walk<V: Visitor + ?Sized>(node: &Node, visitor: &mut V) {
+    walk_at(node, 0, visitor);
+}
+
+fn walk_at<V: Visitor + ?Sized>(node: &Node, depth: usize, visitor: &mut V) {
+    if visitor.enter(node, depth) {
+        for child in node.children() {
+            walk_at(child, depth + 1, visitor);
+        }
+    }
+    visitor.leave(node, depth);
+}
+
+/// 文档的纯文本：正文（块之间换行）和 AI 块的提问/回答分开存放
+#[derive(Debug, Default, PartialEq)]
+pub struct PlainText {
+    pub body: String,
+    pub ai: String,
+}
+
+pub fn plain_text(node: &Node) -> PlainText {
+    let mut text = PlainText::default();
+    walk(node, &mut text);
+    text.body = text.body.trim().to_string();
+    text.ai = text.ai.trim().to_string();
+    text
+}
+
+impl Visitor for PlainText {
+    fn enter(&mut self, node: &Node, _depth: usize) -> bool {
+        match node {
+            Node::Text(text) => self.body.push_str(&text.text),
+            Node::HardBreak(_) => self.body.push('\n'),
+            Node::AiBlock(block) => {
+                if let Some(attrs) = &block.attrs {
+                    for part in [&attrs.prompt, &attrs.response] {
+                        if !part.is_empty() {
+                            self.ai.push_str(part);
+                            self.ai.push('\n');
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn leave(&mut self, node: &Node, _depth: usize) {
+        if !node.children().is_empty() && !self.body.is_empty() && !self.body.ends_with('\n') {
+            self.body.push('\n');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiptap::parse;
+
+    #[test]
+    fn visits_in_document_order_with_depth() {
+        struct Collect(Vec<(String, usize)>);
+        impl Visitor for Collect {
+            fn enter(&mut self, node: &Node, depth: usize) -> bool {
+                let value = serde_json::to_value(node).unwrap();
+                self.0.push((value["type"].as_str().unwrap().to_string(), depth));
+                // 不进入代码块
+                !matches!(node, Node::CodeBlock(_))
+            }
+        }
+
+        let doc = parse(r#"{"type":"doc","content":[
+            {"type":"taskList","content":[{"type":"taskItem","attrs":{"checked":false},"content":[{"type":"paragraph","content":[{"type":"text","text":"a"}]}]}]},
+            {"type":"codeBlock","attrs":{"language":"rust"},"content":[{"type":"text","text":"b"}]}
+        ]}"#).unwrap();
+        let mut collect = Collect(Vec::new());
+        walk(&doc, &mut collect);
+
+        let expected = [("doc", 0), ("taskList", 1), ("taskItem", 2), ("paragraph", 3), ("text", 4), ("codeBlock", 1)];
+        assert_eq!(collect.0, expected.map(|(t, d)| (t.to_string(), d)));
+    }
+
+    #[test]
+    fn extracts_body_and_ai_text() {
+        let doc = parse(r#"{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"Title"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"line"},{"type":"hardBreak"},{"type":"text","text":"break"}]},
+            {"type":"mention","content":[{"type":"text","text":"@bob"}]},
+            {"type":"aiBlock","attrs":{"prompt":"q","response":"a","status":"complete","model":"m","timestamp":1}}
+        ]}"#).unwrap();
+
+        assert_eq!(plain_text(&doc), PlainText { body: "Title\nline\nbreak\n@bob".into(), ai: "q\na".into() });
+    }
+}
@@ -10,7 +10,7 @@ import { BackupPanel } from './BackupPanel';
 
 export const AppLayout: React.FC = () => {
   const { t } = useTranslation();
-  const { theme, sidebarOpen, settingsOpen, backupOpen, loadDocuments, loadFolders, loadLastOpenedDocument, loadSettings } = useAppStore();
+  const { theme, sidebarOpen, settingsOpen, backupOpen, loadDocuments, loadFolders, loadLastOpenedDocument, loadSettings, subscribeToChanges } = useAppStore();
   const { ToastContainer } = useToast();
   const [sidebarWidth, setSidebarWidth] = React.useState(320); // 默认宽度从 256px (w-64) 增加到 320px
   const [isResizing, setIsResizing] = React.useState(false);
@@ -40,6 +40,14 @@ export const AppLayout: React.FC = () => {
     init();
   }, [loadDocuments, loadFolders, loadLastOpenedDocument, loadSettings]);
 
+  // 后端的变更事件（包括其他窗口的修改）同步到 store
+  useEffect(() => {
+    const subscription = subscribeToChanges();
+    return () => {
+      subscription.then((unsubscribe) => unsubscribe());
+    };
+  }, [subscribeToChanges]);
+
   // 自动备份功能
   useEffect(() => {
     let lastBackupHash = '';
@@ -237,7 +237,7 @@ export const EditorPanel: React.FC = () => {
                       onClick={async () => {
                         console.log('📥 导出为 Markdown');
                         const { ExportUtils } = await import('../utils/exportUtils');
-                        const markdown = ExportUtils.exportAsMarkdown(currentDocument);
+                        const markdown = await ExportUtils.exportFromBackend(currentDocument, 'markdown');
                         ExportUtils.downloadFile(markdown, `${currentDocument.title}.md`, 'text/markdown');
                         setShowExportMenu(false);
                       }}
@@ -248,11 +248,26 @@ export const EditorPanel: React.FC = () => {
                       </svg>
                       <span>{t('editorPanel.exportMarkdown')}</span>
                     </button>
+                    <button
+                      onClick={async () => {
+                        console.log('📥 导出为 HTML');
+                        const { ExportUtils } = await import('../utils/exportUtils');
+                        const html = await ExportUtils.exportFromBackend(currentDocument, 'html');
+                        ExportUtils.downloadFile(html, `${currentDocument.title}.html`, 'text/html');
+                        setShowExportMenu(false);
+                      }}
+                      className="w-full text-left px-4 py-3 hover:bg-[var(--bg-hover)] text-sm flex items-center gap-3 transition-colors"
+                    >
+                      <svg className="w-4 h-4 text-orange-500" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                        <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M21 12a9 9 0 01-9 9m9-9a9 9 0 00-9-9m9 9H3m9 9a9 9 0 01-9-9m9 9c1.657 0 3-4.03 3-9s-1.343-9-3-9m0 18c-1.657 0-3-4.03-3-9s1.343-9 3-9m-9 9a9 9 0 019-9" />
+                      </svg>
+                      <span>{t('editorPanel.exportHTML')}</span>
+                    </button>
                     <button
                       onClick={async () => {
                         console.log('📥 导出为纯文本');
                         const { ExportUtils } = await import('../utils/exportUtils');
-                        const text = ExportUtils.exportAsText(currentDocument);
+                        const text = await ExportUtils.exportFromBackend(currentDocument, 'text');
                         ExportUtils.downloadFile(text, `${currentDocument.title}.txt`, 'text/plain');
                         setShowExportMenu(false);
                       }}
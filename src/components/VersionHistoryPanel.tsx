@@ -1,6 +1,9 @@
 import React, { useState, useEffect } from 'react';
 import { useAppStore } from '../store/useAppStore';
-import { DocumentVersion } from '../types';
+import { DocumentService } from '../services/DocumentService';
+import { BlockDiff, DocumentVersion, VersionDiff } from '../types';
+
+const docService = new DocumentService();
 
 interface VersionHistoryPanelProps {
   documentId: string;
@@ -11,12 +14,22 @@ export const VersionHistoryPanel: React.FC<VersionHistoryPanelProps> = ({ docume
   const [versions, setVersions] = useState<DocumentVersion[]>([]);
   const [loading, setLoading] = useState(true);
   const [selectedVersion, setSelectedVersion] = useState<DocumentVersion | null>(null);
+  const [diff, setDiff] = useState<VersionDiff | null>(null);
   const { getDocumentVersions, restoreDocumentVersion } = useAppStore();
 
   useEffect(() => {
     loadVersions();
   }, [documentId]);
 
+  // 选中版本后与当前内容比较
+  useEffect(() => {
+    setDiff(null);
+    if (!selectedVersion) return;
+    docService.diffVersions(documentId, selectedVersion.id)
+      .then(setDiff)
+      .catch((error) => console.error('比较版本失败:', error));
+  }, [documentId, selectedVersion]);
+
   const loadVersions = async () => {
     setLoading(true);
     try {
@@ -44,6 +57,21 @@ export const VersionHistoryPanel: React.FC<VersionHistoryPanelProps> = ({ docume
     }
   };
 
+  const handleLabel = async (version: DocumentVersion) => {
+    const label = prompt('版本标签（留空取消标签）：', version.label ?? '');
+    if (label === null) return;
+    const note = label.trim() ? prompt('备注（可选）：', version.note ?? '') : null;
+    try {
+      await docService.labelDocumentVersion(documentId, version.id, label, note ?? undefined);
+      const updated = { ...version, label: label.trim() || undefined, note: (label.trim() && note?.trim()) || undefined };
+      setVersions(versions.map(v => (v.id === version.id ? updated : v)));
+      setSelectedVersion(updated);
+    } catch (error) {
+      console.error('设置版本标签失败:', error);
+      alert('设置版本标签失败');
+    }
+  };
+
   const formatDate = (timestamp: number) => {
     const date = new Date(timestamp * 1000);
     return date.toLocaleString('zh-CN', {
@@ -71,6 +99,33 @@ export const VersionHistoryPanel: React.FC<VersionHistoryPanelProps> = ({ docume
     return text.substring(0, 100) + (text.length > 100 ? '...' : '');
   };
 
+  const renderBlock = (block: BlockDiff, index: number) => {
+    const style = {
+      equal: 'text-[var(--text-secondary)]',
+      insert: 'bg-green-50 text-green-800 border-l-4 border-green-500',
+      delete: 'bg-red-50 text-red-800 line-through border-l-4 border-red-500',
+      modify: 'text-[var(--text-primary)] border-l-4 border-yellow-500',
+    }[block.kind];
+    return (
+      <p key={index} className={`whitespace-pre-wrap text-sm px-3 py-1 mb-1 rounded ${style}`}>
+        {block.kind === 'modify'
+          ? block.words.map((word, i) => (
+              <span
+                key={i}
+                className={
+                  word.kind === 'insert' ? 'bg-green-100 text-green-800'
+                    : word.kind === 'delete' ? 'bg-red-100 text-red-800 line-through'
+                    : undefined
+                }
+              >
+                {word.text}
+              </span>
+            ))
+          : block.text || '\u00a0'}
+      </p>
+    );
+  };
+
   return (
     <div className="fixed inset-0 bg-black bg-opacity-50 backdrop-blur-sm z-50 flex items-center justify-center animate-fadeIn">
       <div className="bg-[var(--bg-primary)] rounded-2xl shadow-2xl w-full max-w-4xl max-h-[80vh] flex flex-col border-2 border-[var(--border-color)] animate-slideUp">
@@ -160,6 +215,11 @@ export const VersionHistoryPanel: React.FC<VersionHistoryPanelProps> = ({ docume
                       }`}>
                         {index === 0 ? '最新' : `版本 ${version.versionNumber}`}
                       </span>
+                      {version.label && (
+                        <span className="px-2 py-0.5 rounded-full text-xs font-medium bg-purple-100 text-purple-700 truncate">
+                          {version.label}
+                        </span>
+                      )}
                     </div>
                     <p className="text-xs text-[var(--text-secondary)] mb-2">
                       {formatDate(version.createdAt)}
@@ -185,21 +245,41 @@ export const VersionHistoryPanel: React.FC<VersionHistoryPanelProps> = ({ docume
                     <p className="text-sm text-[var(--text-secondary)]">
                       {formatDate(selectedVersion.createdAt)}
                     </p>
+                    {selectedVersion.note && (
+                      <p className="text-sm text-[var(--text-tertiary)] mt-1">{selectedVersion.note}</p>
+                    )}
+                  </div>
+                  <div className="flex items-center gap-2">
+                    <button
+                      onClick={() => handleLabel(selectedVersion)}
+                      className="px-4 py-2 bg-[var(--bg-tertiary)] text-[var(--text-primary)] rounded-xl hover:bg-[var(--bg-hover)] transition-all font-medium"
+                    >
+                      {selectedVersion.label ? '编辑标签' : '添加标签'}
+                    </button>
+                    <button
+                      onClick={() => handleRestore(selectedVersion.id)}
+                      className="px-4 py-2 bg-gradient-to-r from-purple-500 to-pink-600 text-white rounded-xl hover:from-purple-600 hover:to-pink-700 transition-all font-medium shadow-md hover:shadow-lg flex items-center gap-2"
+                    >
+                      <svg className="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                        <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M4 4v5h.582m15.356 2A8.001 8.001 0 004.582 9m0 0H9m11 11v-5h-.581m0 0a8.003 8.003 0 01-15.357-2m15.357 2H15" />
+                      </svg>
+                      <span>恢复此版本</span>
+                    </button>
                   </div>
-                  <button
-                    onClick={() => handleRestore(selectedVersion.id)}
-                    className="px-4 py-2 bg-gradient-to-r from-purple-500 to-pink-600 text-white rounded-xl hover:from-purple-600 hover:to-pink-700 transition-all font-medium shadow-md hover:shadow-lg flex items-center gap-2"
-                  >
-                    <svg className="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
-                      <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M4 4v5h.582m15.356 2A8.001 8.001 0 004.582 9m0 0H9m11 11v-5h-.581m0 0a8.003 8.003 0 01-15.357-2m15.357 2H15" />
-                    </svg>
-                    <span>恢复此版本</span>
-                  </button>
                 </div>
-                <div className="prose prose-sm max-w-none bg-[var(--bg-secondary)] p-6 rounded-xl border border-[var(--border-color)]">
-                  <pre className="whitespace-pre-wrap text-sm text-[var(--text-primary)] font-mono">
-                    {extractPreview(selectedVersion.content)}
-                  </pre>
+                <div className="bg-[var(--bg-secondary)] p-6 rounded-xl border border-[var(--border-color)]">
+                  {diff ? (
+                    <>
+                      <p className="text-xs text-[var(--text-tertiary)] mb-3">
+                        与当前内容相比：新增 {diff.insertions} 段，删除 {diff.deletions} 段，修改 {diff.modifications} 段
+                      </p>
+                      {diff.blocks.map(renderBlock)}
+                    </>
+                  ) : (
+                    <pre className="whitespace-pre-wrap text-sm text-[var(--text-primary)] font-mono">
+                      {extractPreview(selectedVersion.content)}
+                    </pre>
+                  )}
                 </div>
               </div>
             ) : (
@@ -222,10 +302,10 @@ export const VersionHistoryPanel: React.FC<VersionHistoryPanelProps> = ({ docume
               <svg className="w-4 h-4" fill="currentColor" viewBox="0 0 20 20">
                 <path fillRule="evenodd" d="M18 10a8 8 0 11-16 0 8 8 0 0116 0zm-7-4a1 1 0 11-2 0 1 1 0 012 0zM9 9a1 1 0 000 2v3a1 1 0 001 1h1a1 1 0 100-2v-3a1 1 0 00-1-1H9z" clipRule="evenodd" />
               </svg>
-              <span>自动保存：内容变化且距上次保存超过 5 分钟</span>
+              <span>自动保存：内容变化且距上次保存超过设定间隔，或改动较大时</span>
             </div>
             <span>·</span>
-            <span>保留最近 20 个版本</span>
+            <span>保留策略：一天内全部保留，一周内每小时一个，一个月内每天一个；有标签的版本一直保留</span>
           </div>
           <button
             onClick={onClose}
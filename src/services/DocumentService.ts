@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { Document } from '../types';
+import { Backlink, BrokenLink, DeleteImpact, Document, DocumentPage, ImportResult, LinkGraph, MergeConflict, MergeResult, SearchHit, VaultImportReport, VersionDiff, VersionStorageReport, WorkspaceExportReport } from '../types';
 import { JSONContent } from '@tiptap/react';
 
 export class DocumentService {
@@ -20,6 +20,16 @@ export class DocumentService {
     }
   }
 
+  // folderId 传空字符串只列根目录，不传则不过滤
+  async listDocuments(folderId?: string, cursor?: string, limit?: number): Promise<DocumentPage> {
+    try {
+      return await invoke<DocumentPage>('list_documents', { folderId, cursor, limit });
+    } catch (error) {
+      console.error('❌ Failed to list documents:', error);
+      throw error;
+    }
+  }
+
   async getDocument(id: string): Promise<Document> {
     try {
       console.log('📂 DocumentService: 调用 Tauri get_document', id);
@@ -50,18 +60,96 @@ export class DocumentService {
     }
   }
 
-  async updateDocument(id: string, content: JSONContent): Promise<void> {
+  async getBacklinks(documentId: string): Promise<Backlink[]> {
+    try {
+      return await invoke<Backlink[]>('get_backlinks', { documentId });
+    } catch (error) {
+      console.error('Failed to get backlinks:', error);
+      throw error;
+    }
+  }
+
+  async getLinkGraph(): Promise<LinkGraph> {
+    try {
+      return await invoke<LinkGraph>('get_link_graph');
+    } catch (error) {
+      console.error('Failed to get link graph:', error);
+      throw error;
+    }
+  }
+
+  async getBrokenLinks(): Promise<BrokenLink[]> {
+    try {
+      return await invoke<BrokenLink[]>('get_broken_links');
+    } catch (error) {
+      console.error('Failed to get broken links:', error);
+      throw error;
+    }
+  }
+
+  async exportWorkspace(path: string, zip: boolean): Promise<WorkspaceExportReport> {
+    try {
+      return await invoke<WorkspaceExportReport>('export_workspace', { path, zip });
+    } catch (error) {
+      console.error('Failed to export workspace:', error);
+      throw error;
+    }
+  }
+
+  async importMarkdown(title: string, markdown: string, folderId?: string): Promise<ImportResult> {
+    try {
+      const result = await invoke<ImportResult>('import_markdown', { title, markdown, folderId });
+      return {
+        ...result,
+        document: {
+          ...result.document,
+          content: typeof result.document.content === 'string' ? JSON.parse(result.document.content) : result.document.content
+        }
+      };
+    } catch (error) {
+      console.error('Failed to import markdown:', error);
+      throw error;
+    }
+  }
+
+  async importVault(path: string, folderId?: string, dryRun: boolean = false): Promise<VaultImportReport> {
+    try {
+      return await invoke<VaultImportReport>('import_vault', { path, folderId, dryRun });
+    } catch (error) {
+      console.error('Failed to import vault:', error);
+      throw error;
+    }
+  }
+
+  // 返回新的修订号；expectedRevision 与后端不一致时抛出 WriteError（kind 为 conflict）
+  async updateDocument(id: string, content: JSONContent, expectedRevision?: number): Promise<number> {
     try {
       const contentStr = JSON.stringify(content);
       console.log('💾 DocumentService: 调用 Tauri update_document', id, '内容长度:', contentStr.length);
-      await invoke('update_document', { id, content: contentStr });
+      const revision = await invoke<number>('update_document', { id, content: contentStr, expectedRevision });
       console.log('✅ DocumentService: Tauri 调用成功');
+      return revision;
     } catch (error) {
       console.error('❌ DocumentService: Failed to update document:', error);
       throw error;
     }
   }
 
+  // 三方合并：base 为双方共同的上一版内容，冲突处保留 ours
+  async mergeDocumentContents(base: JSONContent, ours: JSONContent, theirs: JSONContent): Promise<MergeResult> {
+    try {
+      const result = await invoke<{ content: string; conflicts: MergeConflict[] }>('merge_document_contents', {
+        base: JSON.stringify(base),
+        ours: JSON.stringify(ours),
+        theirs: JSON.stringify(theirs),
+      });
+      return { ...result, content: JSON.parse(result.content) };
+    } catch (error) {
+      console.error('Failed to merge document contents:', error);
+      throw error;
+    }
+  }
+
   async updateDocumentTitle(id: string, title: string): Promise<void> {
     try {
       console.log('📝 DocumentService: 更新文档标题', id, title);
@@ -84,21 +172,31 @@ export class DocumentService {
     }
   }
 
-  async deleteDocument(id: string): Promise<void> {
+  async deleteDocument(id: string, links: 'unlink' | 'tombstone' = 'tombstone'): Promise<DeleteImpact> {
     try {
-      await invoke('delete_document', { id });
+      return await invoke<DeleteImpact>('delete_document', { id, links });
     } catch (error) {
       console.error('Failed to delete document:', error);
       throw error;
     }
   }
 
+  async getDeleteImpact(id: string): Promise<DeleteImpact> {
+    try {
+      return await invoke<DeleteImpact>('get_delete_impact', { id });
+    } catch (error) {
+      console.error('Failed to get delete impact:', error);
+      throw error;
+    }
+  }
+
   async searchDocuments(query: string): Promise<Document[]> {
     try {
       console.log('🔍 DocumentService: 调用 Tauri search_documents，关键词:', query);
-      const documents = await invoke<Document[]>('search_documents', { query });
-      console.log('🔍 DocumentService: Tauri 返回搜索结果数量:', documents.length);
-      const parsed = documents.map(doc => ({
+      const hits = await invoke<SearchHit[]>('search_documents', { query });
+      console.log('🔍 DocumentService: Tauri 返回搜索结果数量:', hits.length);
+      // 结果已按相关度排序
+      const parsed = hits.map(({ document: doc }) => ({
         ...doc,
         content: typeof doc.content === 'string' ? JSON.parse(doc.content) : doc.content
       }));
@@ -160,6 +258,35 @@ export class DocumentService {
     }
   }
 
+  // label 为空时取消标签
+  async labelDocumentVersion(documentId: string, versionId: string, label?: string, note?: string): Promise<void> {
+    try {
+      await invoke('label_document_version', { documentId, versionId, label, note });
+    } catch (error) {
+      console.error('Failed to label document version:', error);
+      throw error;
+    }
+  }
+
+  async getVersionStorage(): Promise<VersionStorageReport> {
+    try {
+      return await invoke<VersionStorageReport>('get_version_storage');
+    } catch (error) {
+      console.error('Failed to get version storage:', error);
+      throw error;
+    }
+  }
+
+  // 不传 toVersionId 时与当前内容比较
+  async diffVersions(documentId: string, fromVersionId: string, toVersionId?: string): Promise<VersionDiff> {
+    try {
+      return await invoke<VersionDiff>('diff_versions', { documentId, fromVersionId, toVersionId });
+    } catch (error) {
+      console.error('Failed to diff versions:', error);
+      throw error;
+    }
+  }
+
   async restoreDocumentVersion(documentId: string, versionId: string): Promise<void> {
     try {
       await invoke('restore_document_version', { documentId, versionId });
@@ -0,0 +1,30 @@
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+import { ChangeEvents } from '../types';
+
+export class EventService {
+  // 订阅后端的变更事件；文档的 content 解析为 JSON，文件夹和快照的时间换算为毫秒，与各 Service 的返回值一致
+  static async on<K extends keyof ChangeEvents>(name: K, handler: (payload: ChangeEvents[K]) => void): Promise<UnlistenFn> {
+    try {
+      return await listen<ChangeEvents[K]>(name, (event) => {
+        const payload: Record<string, any> = { ...event.payload };
+        if (payload.document && typeof payload.document.content === 'string') {
+          payload.document = { ...payload.document, content: JSON.parse(payload.document.content) };
+        }
+        if (payload.folder) {
+          payload.folder = {
+            ...payload.folder,
+            createdAt: payload.folder.createdAt * 1000,
+            updatedAt: payload.folder.updatedAt * 1000,
+          };
+        }
+        if (payload.snapshot) {
+          payload.snapshot = { ...payload.snapshot, createdAt: payload.snapshot.createdAt * 1000 };
+        }
+        handler(payload as ChangeEvents[K]);
+      });
+    } catch (error) {
+      console.error('Failed to listen for change event:', name, error);
+      throw error;
+    }
+  }
+}
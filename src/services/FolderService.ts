@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { Folder } from '../types';
+import { Breadcrumb, Folder, FolderDeleteMode, FolderTree, FolderTreeNode, SortMode } from '../types';
 
 export class FolderService {
   async getAllFolders(): Promise<Folder[]> {
@@ -16,6 +16,36 @@ export class FolderService {
     }
   }
 
+  async getFolderTree(): Promise<FolderTree> {
+    // 后端时间戳为秒，统一转成毫秒
+    const toMillis = (node: FolderTreeNode): FolderTreeNode => ({
+      ...node,
+      folder: {
+        ...node.folder,
+        createdAt: node.folder.createdAt * 1000,
+        updatedAt: node.folder.updatedAt * 1000,
+      },
+      lastModified: node.lastModified === null ? null : node.lastModified * 1000,
+      children: node.children.map(toMillis),
+    });
+    try {
+      const tree = await invoke<FolderTree>('get_folder_tree');
+      return { ...tree, folders: tree.folders.map(toMillis) };
+    } catch (error) {
+      console.error('Failed to get folder tree:', error);
+      throw error;
+    }
+  }
+
+  async getBreadcrumbs(id: string): Promise<Breadcrumb[]> {
+    try {
+      return await invoke<Breadcrumb[]>('get_breadcrumbs', { id });
+    } catch (error) {
+      console.error('Failed to get breadcrumbs:', error);
+      throw error;
+    }
+  }
+
   async createFolder(name: string, parentId?: string): Promise<Folder> {
     try {
       const folder = await invoke<Folder>('create_folder', { name, parentId });
@@ -39,15 +69,71 @@ export class FolderService {
     }
   }
 
-  async deleteFolder(id: string): Promise<void> {
+  async deleteFolder(id: string, mode: FolderDeleteMode = 'recursive'): Promise<void> {
     try {
-      await invoke('delete_folder', { id });
+      await invoke('delete_folder', { id, mode });
     } catch (error) {
       console.error('Failed to delete folder:', error);
       throw error;
     }
   }
 
+  async moveFolder(id: string, parentId?: string): Promise<void> {
+    try {
+      await invoke('move_folder', { id, parentId });
+    } catch (error) {
+      console.error('Failed to move folder:', error);
+      throw error;
+    }
+  }
+
+  async moveItems(folderIds: string[], documentIds: string[], targetFolderId?: string): Promise<void> {
+    try {
+      await invoke('move_items', { folderIds, documentIds, targetFolderId });
+    } catch (error) {
+      console.error('Failed to move items:', error);
+      throw error;
+    }
+  }
+
+  // 手动排序：移到 beforeId 前面，不传时移到末尾
+  async reorderFolder(id: string, beforeId?: string): Promise<void> {
+    try {
+      await invoke('reorder_folder', { id, beforeId });
+    } catch (error) {
+      console.error('Failed to reorder folder:', error);
+      throw error;
+    }
+  }
+
+  async reorderDocument(id: string, beforeId?: string): Promise<void> {
+    try {
+      await invoke('reorder_document', { id, beforeId });
+    } catch (error) {
+      console.error('Failed to reorder document:', error);
+      throw error;
+    }
+  }
+
+  // folderId 不传时为根目录
+  async getSortMode(folderId?: string): Promise<SortMode> {
+    try {
+      return await invoke<SortMode>('get_folder_sort_mode', { folderId });
+    } catch (error) {
+      console.error('Failed to get sort mode:', error);
+      throw error;
+    }
+  }
+
+  async setSortMode(mode: SortMode, folderId?: string): Promise<void> {
+    try {
+      await invoke('set_folder_sort_mode', { folderId, mode });
+    } catch (error) {
+      console.error('Failed to set sort mode:', error);
+      throw error;
+    }
+  }
+
   async moveDocument(documentId: string, folderId?: string): Promise<void> {
     try {
       await invoke('move_document', { documentId, folderId });
@@ -24,6 +24,12 @@ export class SettingsService {
       autoSave: backendSettings.auto_save,
       autoSaveDelay: backendSettings.auto_save_delay,
       databasePath: backendSettings.database_path,
+      trashRetentionDays: backendSettings.trash_retention_days ?? 30,
+      versionIntervalMinutes: backendSettings.version_interval_minutes ?? 5,
+      versionMinChangeChars: backendSettings.version_min_change_chars ?? 2000,
+      versionKeepAllHours: backendSettings.version_keep_all_hours ?? 24,
+      versionHourlyDays: backendSettings.version_hourly_days ?? 7,
+      versionDailyDays: backendSettings.version_daily_days ?? 30,
     };
   }
 
@@ -49,6 +55,12 @@ export class SettingsService {
       auto_save: settings.autoSave,
       auto_save_delay: settings.autoSaveDelay,
       database_path: settings.databasePath,
+      trash_retention_days: settings.trashRetentionDays,
+      version_interval_minutes: settings.versionIntervalMinutes,
+      version_min_change_chars: settings.versionMinChangeChars,
+      version_keep_all_hours: settings.versionKeepAllHours,
+      version_hourly_days: settings.versionHourlyDays,
+      version_daily_days: settings.versionDailyDays,
     };
   }
 
@@ -98,6 +110,12 @@ export class SettingsService {
         autoSave: true,
         autoSaveDelay: 2000,
         databasePath: undefined,
+        trashRetentionDays: 30,
+        versionIntervalMinutes: 5,
+        versionMinChangeChars: 2000,
+        versionKeepAllHours: 24,
+        versionHourlyDays: 7,
+        versionDailyDays: 30,
       };
     }
   }
@@ -0,0 +1,44 @@
+import { invoke } from '@tauri-apps/api/core';
+import { NamedSnapshot } from '../types';
+
+export class SnapshotService {
+  async listSnapshots(): Promise<NamedSnapshot[]> {
+    try {
+      const snapshots = await invoke<NamedSnapshot[]>('list_snapshots');
+      return snapshots.map(snapshot => ({ ...snapshot, createdAt: snapshot.createdAt * 1000 }));
+    } catch (error) {
+      console.error('Failed to list snapshots:', error);
+      throw error;
+    }
+  }
+
+  // 不传 folderId 时给整个工作区创建快照
+  async createSnapshot(label: string, note?: string, folderId?: string): Promise<NamedSnapshot> {
+    try {
+      const snapshot = await invoke<NamedSnapshot>('create_snapshot', { label, note, folderId });
+      return { ...snapshot, createdAt: snapshot.createdAt * 1000 };
+    } catch (error) {
+      console.error('Failed to create snapshot:', error);
+      throw error;
+    }
+  }
+
+  // 返回内容被恢复的文档数
+  async restoreSnapshot(id: string): Promise<number> {
+    try {
+      return await invoke<number>('restore_snapshot', { id });
+    } catch (error) {
+      console.error('Failed to restore snapshot:', error);
+      throw error;
+    }
+  }
+
+  async deleteSnapshot(id: string): Promise<void> {
+    try {
+      await invoke('delete_snapshot', { id });
+    } catch (error) {
+      console.error('Failed to delete snapshot:', error);
+      throw error;
+    }
+  }
+}
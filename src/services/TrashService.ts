@@ -0,0 +1,41 @@
+import { invoke } from '@tauri-apps/api/core';
+import { TrashItem } from '../types';
+
+export class TrashService {
+  async listTrash(): Promise<TrashItem[]> {
+    try {
+      const items = await invoke<TrashItem[]>('list_trash');
+      return items.map(item => ({ ...item, deletedAt: item.deletedAt * 1000 }));
+    } catch (error) {
+      console.error('Failed to list trash:', error);
+      throw error;
+    }
+  }
+
+  async restore(id: string): Promise<void> {
+    try {
+      await invoke('restore_from_trash', { id });
+    } catch (error) {
+      console.error('Failed to restore from trash:', error);
+      throw error;
+    }
+  }
+
+  async purge(id: string): Promise<void> {
+    try {
+      await invoke('purge_from_trash', { id });
+    } catch (error) {
+      console.error('Failed to purge from trash:', error);
+      throw error;
+    }
+  }
+
+  async emptyTrash(): Promise<number> {
+    try {
+      return await invoke<number>('empty_trash');
+    } catch (error) {
+      console.error('Failed to empty trash:', error);
+      throw error;
+    }
+  }
+}
@@ -1,11 +1,12 @@
 import { create } from 'zustand';
 import { immer } from 'zustand/middleware/immer';
-import { AppState, Settings, Tag } from '../types';
+import { AppState, Settings, Tag, WriteError } from '../types';
 import { JSONContent } from '@tiptap/react';
 import { DocumentService } from '../services/DocumentService';
 import { SettingsService } from '../services/SettingsService';
 import { TagService } from '../services/TagService';
 import { FolderService } from '../services/FolderService';
+import { EventService } from '../services/EventService';
 
 const documentService = new DocumentService();
 const settingsService = new SettingsService();
@@ -110,7 +111,10 @@ export const useAppStore = create<AppState>()(
       try {
         const document = await documentService.createDocument(title, folderId);
         set((state) => {
-          state.documents.unshift(document);
+          // document:created 事件可能先到
+          if (!state.documents.some((d) => d.id === document.id)) {
+            state.documents.unshift(document);
+          }
           state.currentDocumentId = document.id;
           state.currentDocument = document;
           // 自动展开包含新文档的文件夹
@@ -126,49 +130,38 @@ export const useAppStore = create<AppState>()(
     updateDocument: async (id: string, content: JSONContent) => {
       try {
         console.log('💾 Store: 开始保存文档', id);
-        await documentService.updateDocument(id, content);
-        
-        // 智能版本保存：只在有实际修改且距离上次保存超过5分钟时才保存版本
-        const state = get();
-        const lastVersionTime = (state as any).lastVersionSaveTime?.[id] || 0;
-        const now = Date.now();
-        const fiveMinutes = 5 * 60 * 1000;
-        
-        // 检查内容是否真的有变化
-        const currentDoc = state.documents.find(d => d.id === id);
-        const contentChanged = currentDoc && JSON.stringify(currentDoc.content) !== JSON.stringify(content);
-        
-        if (contentChanged && (now - lastVersionTime > fiveMinutes)) {
-          try {
-            await documentService.saveDocumentVersion(id, content);
-            console.log('📚 Store: 版本已保存（距上次保存超过5分钟）');
-            
-            // 记录保存时间
-            set((state) => {
-              if (!(state as any).lastVersionSaveTime) {
-                (state as any).lastVersionSaveTime = {};
-              }
-              (state as any).lastVersionSaveTime[id] = now;
-            });
-          } catch (versionError) {
-            console.warn('⚠️ Store: 保存版本失败（不影响文档保存）:', versionError);
+        const { currentDocument, documents } = get();
+        const existing = currentDocument?.id === id ? currentDocument : documents.find((d) => d.id === id);
+        let saved = content;
+        let revision: number;
+        try {
+          revision = await documentService.updateDocument(id, content, existing?.revision);
+        } catch (error) {
+          const writeError = error as WriteError;
+          if (writeError?.kind !== 'conflict' || !existing) throw error;
+          // 文档已被其他窗口修改：以上次保存的内容为基准三方合并，冲突处保留本地的修改
+          console.warn('⚠️ 文档已被其他窗口修改，合并后重新保存:', id, writeError.revision);
+          const merged = await documentService.mergeDocumentContents(existing.content, content, JSON.parse(writeError.content));
+          if (merged.conflicts.length > 0) {
+            console.warn('⚠️ 合并冲突，已保留本地内容:', merged.conflicts);
           }
-        } else if (!contentChanged) {
-          console.log('⏭️ Store: 内容未变化，跳过版本保存');
-        } else {
-          console.log('⏭️ Store: 距上次版本保存不足5分钟，跳过');
+          saved = merged.content;
+          revision = await documentService.updateDocument(id, saved, writeError.revision);
         }
         
+        // 历史版本由后端按设置中的间隔自动保存
         console.log('✅ Store: 文档保存成功', id);
         set((state) => {
           if (state.currentDocument && state.currentDocument.id === id) {
-            state.currentDocument.content = content;
+            state.currentDocument.content = saved;
             state.currentDocument.updatedAt = Date.now();
+            state.currentDocument.revision = revision;
           }
           const docIndex = state.documents.findIndex((d) => d.id === id);
           if (docIndex !== -1) {
-            state.documents[docIndex].content = content;
+            state.documents[docIndex].content = saved;
             state.documents[docIndex].updatedAt = Date.now();
+            state.documents[docIndex].revision = revision;
           }
         });
       } catch (error) {
@@ -289,7 +282,9 @@ export const useAppStore = create<AppState>()(
       try {
         const newTag = await TagService.createTag(tag);
         set((state) => {
-          state.tags.push(newTag);
+          if (!state.tags.some((t) => t.id === newTag.id)) {
+            state.tags.push(newTag);
+          }
         });
         return newTag;
       } catch (error) {
@@ -364,7 +359,9 @@ export const useAppStore = create<AppState>()(
       try {
         const folder = await folderService.createFolder(name, parentId);
         set((state) => {
-          state.folders.push(folder);
+          if (!state.folders.some((f) => f.id === folder.id)) {
+            state.folders.push(folder);
+          }
           // 自动展开父文件夹和新文件夹
           if (parentId) {
             state.expandedFolders[parentId] = true;
@@ -494,14 +491,16 @@ export const useAppStore = create<AppState>()(
     // 标记重要文档
     toggleImportantDocument: async (id: string) => {
       try {
+        const isImportant = !get().documents.find((d) => d.id === id)?.isImportant;
         await documentService.toggleImportantDocument(id);
+        // 写入确定的值而不是再取反，document:updated 事件先到时也不会翻转回去
         set((state) => {
           const docIndex = state.documents.findIndex((d) => d.id === id);
           if (docIndex !== -1) {
-            state.documents[docIndex].isImportant = !state.documents[docIndex].isImportant;
+            state.documents[docIndex].isImportant = isImportant;
           }
           if (state.currentDocument && state.currentDocument.id === id) {
-            state.currentDocument.isImportant = !state.currentDocument.isImportant;
+            state.currentDocument.isImportant = isImportant;
           }
         });
       } catch (error) {
@@ -594,5 +593,119 @@ export const useAppStore = create<AppState>()(
         localStorage.removeItem('lastOpenedDocument');
       }
     },
+
+    // 订阅后端的变更事件，让其他窗口和本窗口其他视图的修改同步到这里
+    subscribeToChanges: async () => {
+      const reload = async () => {
+        await get().loadFolders();
+        await get().loadDocuments();
+      };
+      const unlisteners = await Promise.all([
+        EventService.on('document:created', ({ document }) => {
+          set((state) => {
+            if (!state.documents.some((d) => d.id === document.id)) {
+              state.documents.unshift(document);
+            }
+          });
+        }),
+        EventService.on('document:updated', ({ document }) => {
+          set((state) => {
+            const docIndex = state.documents.findIndex((d) => d.id === document.id);
+            if (docIndex !== -1) {
+              state.documents[docIndex] = document;
+            }
+            const current = state.currentDocument;
+            if (current && current.id === document.id && document.revision >= current.revision) {
+              // 内容没变（例如本窗口自己的保存）时保留原对象，避免编辑器重新设置内容
+              const sameContent = JSON.stringify(current.content) === JSON.stringify(document.content);
+              state.currentDocument = sameContent ? { ...document, content: current.content } : document;
+            }
+          });
+        }),
+        EventService.on('document:moved', ({ id, folderId }) => {
+          set((state) => {
+            const docIndex = state.documents.findIndex((d) => d.id === id);
+            if (docIndex !== -1) {
+              state.documents[docIndex].folderId = folderId ?? undefined;
+            }
+            if (state.currentDocument && state.currentDocument.id === id) {
+              state.currentDocument.folderId = folderId ?? undefined;
+            }
+          });
+        }),
+        EventService.on('document:reordered', () => get().loadDocuments()),
+        EventService.on('document:trashed', ({ id }) => {
+          set((state) => {
+            state.documents = state.documents.filter((d) => d.id !== id);
+            if (state.currentDocumentId === id) {
+              state.currentDocumentId = null;
+              state.currentDocument = null;
+            }
+          });
+        }),
+        EventService.on('folder:created', ({ folder }) => {
+          set((state) => {
+            if (!state.folders.some((f) => f.id === folder.id)) {
+              state.folders.push(folder);
+            }
+          });
+        }),
+        EventService.on('folder:renamed', ({ id, name }) => {
+          set((state) => {
+            const folder = state.folders.find((f) => f.id === id);
+            if (folder) {
+              folder.name = name;
+            }
+          });
+        }),
+        EventService.on('folder:moved', () => get().loadFolders()),
+        EventService.on('folder:reordered', () => get().loadFolders()),
+        EventService.on('folder:sortModeChanged', () => reload()),
+        EventService.on('folder:trashed', () => reload()),
+        EventService.on('trash:restored', () => reload()),
+        EventService.on('trash:purged', () => reload()),
+        EventService.on('trash:emptied', () => reload()),
+        EventService.on('tag:created', ({ tag }) => {
+          set((state) => {
+            if (tag.documentId === state.currentDocumentId && !state.tags.some((t) => t.id === tag.id)) {
+              state.tags.push(tag);
+            }
+          });
+        }),
+        EventService.on('tag:updated', ({ id, text }) => {
+          set((state) => {
+            const tag = state.tags.find((t) => t.id === id);
+            if (tag) {
+              tag.text = text;
+            }
+          });
+        }),
+        EventService.on('tag:deleted', ({ id }) => {
+          set((state) => {
+            state.tags = state.tags.filter((t) => t.id !== id);
+          });
+        }),
+        EventService.on('snapshot:restored', async () => {
+          await reload();
+          const { currentDocumentId } = get();
+          if (currentDocumentId) {
+            await get().selectDocument(currentDocumentId);
+          }
+        }),
+        EventService.on('vault:imported', () => reload()),
+        EventService.on('settings:updated', () => get().loadSettings()),
+        EventService.on('database:replaced', async () => {
+          set((state) => {
+            state.currentDocumentId = null;
+            state.currentDocument = null;
+            state.tags = [];
+          });
+          await get().loadSettings();
+          await reload();
+          await get().loadLastOpenedDocument();
+        }),
+      ]);
+      return () => unlisteners.forEach((unlisten) => unlisten());
+    },
   }))
 );
@@ -10,6 +10,147 @@ export interface Document {
   folderId?: string;  // 所属文件夹ID
   isPinned?: boolean;  // 是否置顶
   isImportant?: boolean;  // 是否标记为重要
+  revision: number;  // 内容修订号，保存时带上用于检测并发修改
+}
+
+// 文档列表项（不含内容）
+export interface DocumentSummary {
+  id: string;
+  title: string;
+  folderId?: string;
+  isPinned: boolean;
+  isImportant: boolean;
+  createdAt: number;
+  updatedAt: number;
+  wordCount: number;
+  preview: string;  // 正文第一行
+}
+
+// list_documents 的一页，nextCursor 为空表示没有更多
+export interface DocumentPage {
+  items: DocumentSummary[];
+  nextCursor?: string;
+}
+
+// 全文搜索结果
+export interface SearchHit {
+  document: Omit<Document, 'content'> & { content: string };
+  score: number;             // 相关度，越大越相关
+  snippet: string;           // 带 <mark></mark> 高亮的片段
+  matches: SearchMatch[];
+}
+
+// 匹配位置（字段纯文本中的 UTF-16 偏移）
+export interface SearchMatch {
+  field: 'title' | 'body' | 'ai' | 'tags' | 'summary';
+  start: number;
+  end: number;
+}
+
+// search_documents 的错误；syntax 错误的 start/end 是查询字符串中的 UTF-16 偏移
+// 查询语法：folder:Work tag:urgent is:pinned is:important updated:>2026-01-01 "exact phrase" -exclude
+export type SearchError =
+  | { kind: 'syntax'; message: string; start: number; end: number }
+  | { kind: 'database'; message: string };
+
+// 三方合并结果，冲突处保留了 ours 的内容；path 为冲突区域在合并结果中的位置（逐层的子节点下标），
+// count 为它在合并结果中占的块数，base/ours/theirs 为各自块数组的 JSON
+export interface MergeConflict {
+  path: number[];
+  count: number;
+  base: string;
+  ours: string;
+  theirs: string;
+}
+
+export interface MergeResult {
+  content: JSONContent;
+  conflicts: MergeConflict[];
+}
+
+// update_document 的错误；conflict 表示文档已被其他窗口修改，带当前的修订号和内容（JSON 字符串）
+export type WriteError =
+  | { kind: 'conflict'; revision: number; content: string }
+  | { kind: 'database'; message: string };
+
+// Markdown 导入：construct 为无法映射的语法（html / footnote / headingLevel 等），line 从 1 开始
+export interface ImportWarning {
+  construct: string;
+  line: number;
+  detail: string | null;
+}
+
+export interface ImportResult {
+  document: Document;
+  warnings: ImportWarning[];
+}
+
+// 仓库批量导入报告，dryRun 时 documentId 为空
+export interface VaultImportReport {
+  dryRun: boolean;
+  folders: number;
+  documents: VaultDocumentReport[];
+  skipped: string[];
+}
+
+export interface VaultDocumentReport {
+  path: string;
+  title: string;
+  documentId: string | null;
+  links: number;
+  tags: string[];
+  warnings: ImportWarning[];
+}
+
+// 工作区导出结果，path 为导出目录或 zip 文件
+export interface WorkspaceExportReport {
+  path: string;
+  folders: number;
+  documents: number;
+}
+
+// 文档链接：反向链接、失效链接和链接图
+export interface Backlink {
+  sourceId: string;
+  sourceTitle: string;
+  text: string;
+  occurrences: number;
+}
+
+export interface BrokenLink {
+  sourceId: string;
+  sourceTitle: string;
+  targetId: string;
+  text: string;
+  occurrences: number;
+  targetTitle: string | null;
+  deletedAt: number | null;
+}
+
+// 文件夹内的排序方式：手动、标题、创建时间、更新时间
+export type SortMode = 'manual' | 'title' | 'created' | 'updated';
+
+// 删除文件夹时内容的去向：移到上级、移到根目录，或连同子树一起删除
+export type FolderDeleteMode = 'parent' | 'root' | 'recursive';
+
+export interface TrashItem {
+  id: string;
+  kind: 'document' | 'folder';
+  title: string;
+  deletedAt: number;
+  path: string[];  // 删除时所在的文件夹路径
+  itemCount: number;  // 随文件夹一起删除的子项数
+}
+
+export interface DeleteImpact {
+  documentId: string;
+  title: string;
+  backlinks: Backlink[];
+}
+
+export interface LinkGraph {
+  nodes: { id: string; title: string; folderId: string | null }[];
+  edges: { source: string; target: string; weight: number }[];
 }
 
 // 文档版本模型
@@ -19,6 +160,59 @@ export interface DocumentVersion {
   content: string;
   createdAt: number;
   versionNumber: number;
+  label?: string;  // 有标签的版本不会被自动清理
+  note?: string;
+  snapshotId?: string;  // 所属的命名快照
+}
+
+// 命名快照：文件夹或整个工作区在某一时刻的全部文档
+export interface NamedSnapshot {
+  id: string;
+  label: string;
+  note?: string;
+  folderId?: string;  // 为空表示整个工作区
+  createdAt: number;
+  documentCount: number;
+}
+
+// 历史版本占用的空间
+export interface DocumentVersionStorage {
+  documentId: string;
+  title: string;
+  versionCount: number;
+  snapshotCount: number;
+  storedBytes: number;
+  originalBytes: number;
+}
+
+export interface VersionStorageReport {
+  versionCount: number;
+  snapshotCount: number;  // 完整快照数，其余为增量
+  storedBytes: number;  // 压缩后实际占用
+  originalBytes: number;
+  documents: DocumentVersionStorage[];
+}
+
+// 两个版本之间的差异
+export interface WordDiff {
+  kind: 'equal' | 'insert' | 'delete';
+  text: string;
+}
+
+export interface BlockDiff {
+  kind: 'equal' | 'insert' | 'delete' | 'modify';
+  blockType: string;  // 节点类型，如 paragraph
+  oldIndex?: number;
+  newIndex?: number;
+  text: string;  // 块的纯文本（删除时为旧文本）
+  words: WordDiff[];  // 仅 modify 有
+}
+
+export interface VersionDiff {
+  blocks: BlockDiff[];
+  insertions: number;
+  deletions: number;
+  modifications: number;
 }
 
 // 文件夹模型
@@ -30,6 +224,25 @@ export interface Folder {
   updatedAt: number;
 }
 
+export interface FolderTreeNode {
+  folder: Folder;
+  documentCount: number;  // 直接包含的文档数
+  totalDocumentCount: number;  // 包括子文件夹在内的文档数
+  lastModified: number | null;  // 子树中最新文档的更新时间
+  children: FolderTreeNode[];
+}
+
+export interface FolderTree {
+  folders: FolderTreeNode[];
+  rootDocumentCount: number;
+}
+
+export interface Breadcrumb {
+  id: string;
+  name: string;
+  kind: 'folder' | 'document';
+}
+
 // 标签模型
 export interface Tag {
   id: string;
@@ -96,9 +309,47 @@ export interface Settings {
   
   // 数据库配置
   databasePath?: string;  // 自定义数据库路径
+  trashRetentionDays?: number;  // 回收站保留天数，0 表示不自动清理
+
+  // 历史版本
+  versionIntervalMinutes?: number;  // 自动保存版本的最短间隔（分钟），0 表示关闭
+  versionMinChangeChars?: number;  // 单次修改达到这么多字符时不受间隔限制
+  versionKeepAllHours?: number;  // 这么多小时内的版本全部保留
+  versionHourlyDays?: number;  // 这么多天内每小时保留一个版本
+  versionDailyDays?: number;  // 这么多天内每天保留一个版本，更早的删除
 }
 
 // Zustand Store
+// 后端每次修改数据后向所有窗口广播的事件（见 src-tauri/src/events.rs），键为事件名，值为载荷。
+// 文档的 content 已由 EventService 解析；settings 为后端的原始字段（snake_case）
+export interface ChangeEvents {
+  'document:created': { document: Document };
+  'document:updated': { document: Document };
+  'document:moved': { id: string; folderId: string | null };
+  'document:reordered': { id: string; beforeId: string | null };
+  'document:trashed': { id: string };
+  'folder:created': { folder: Folder };
+  'folder:renamed': { id: string; name: string };
+  'folder:moved': { id: string; parentId: string | null };
+  'folder:reordered': { id: string; beforeId: string | null };
+  'folder:sortModeChanged': { folderId: string | null; mode: string };
+  'folder:trashed': { id: string; mode: string };
+  'trash:restored': { id: string };
+  'trash:purged': { id: string };
+  'trash:emptied': { count: number };
+  'tag:created': { tag: Tag };
+  'tag:updated': { id: string; text: string };
+  'tag:deleted': { id: string };
+  'version:saved': { documentId: string };
+  'version:labeled': { documentId: string; versionId: string; label: string | null; note: string | null };
+  'snapshot:created': { snapshot: NamedSnapshot };
+  'snapshot:restored': { id: string; restored: number };
+  'snapshot:deleted': { id: string };
+  'vault:imported': { report: VaultImportReport };
+  'settings:updated': { settings: Record<string, unknown> };
+  'database:replaced': { path: string };
+}
+
 export interface AppState {
   // 文档状态
   documents: Document[];
@@ -194,6 +445,9 @@ export interface AppState {
   saveCursorPosition: (documentId: string, position: number) => void;
   getCursorPosition: (documentId: string) => number | null;
   loadLastOpenedDocument: () => Promise<void>;
+
+  // 订阅后端的变更事件，返回取消订阅的函数
+  subscribeToChanges: () => Promise<() => void>;
 }
 
 // AI Provider 接口
@@ -1,174 +1,15 @@
-import { JSONContent } from '@tiptap/react';
 import { Document } from '../types';
 
 export class ExportUtils {
-  // 将 TipTap JSON 转换为纯文本
-  static toPlainText(content: JSONContent): string {
-    let text = '';
-
-    const traverse = (node: JSONContent) => {
-      if (node.type === 'text') {
-        text += node.text || '';
-      } else if (node.type === 'paragraph') {
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        text += '\n\n';
-      } else if (node.type === 'heading') {
-        const level = node.attrs?.level || 1;
-        const prefix = '#'.repeat(level);
-        if (node.content) {
-          text += prefix + ' ';
-          node.content.forEach(traverse);
-        }
-        text += '\n\n';
-      } else if (node.type === 'codeBlock') {
-        text += '```\n';
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        text += '\n```\n\n';
-      } else if (node.type === 'aiBlock') {
-        const prompt = node.attrs?.prompt || '';
-        const response = node.attrs?.response || '';
-        const model = node.attrs?.model || '';
-        const status = node.attrs?.status || '';
-        
-        if (status === 'accepted' || status === 'complete') {
-          text += `\n[AI 对话 - ${model}]\n`;
-          text += `用户: ${prompt}\n`;
-          text += `AI: ${response}\n\n`;
-        }
-      } else if (node.type === 'bulletList' || node.type === 'orderedList') {
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        text += '\n';
-      } else if (node.type === 'listItem') {
-        text += '• ';
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        text += '\n';
-      } else if (node.content) {
-        node.content.forEach(traverse);
-      }
-    };
-
-    traverse(content);
-    return text.trim();
-  }
-
-  // 将 TipTap JSON 转换为 Markdown
-  static toMarkdown(content: JSONContent): string {
-    let markdown = '';
-
-    const traverse = (node: JSONContent) => {
-      if (node.type === 'text') {
-        let text = node.text || '';
-        // 处理文本标记
-        if (node.marks) {
-          node.marks.forEach(mark => {
-            if (mark.type === 'bold') {
-              text = `**${text}**`;
-            } else if (mark.type === 'italic') {
-              text = `*${text}*`;
-            } else if (mark.type === 'code') {
-              text = `\`${text}\``;
-            }
-          });
-        }
-        markdown += text;
-      } else if (node.type === 'paragraph') {
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        markdown += '\n\n';
-      } else if (node.type === 'heading') {
-        const level = node.attrs?.level || 1;
-        const prefix = '#'.repeat(level);
-        markdown += prefix + ' ';
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        markdown += '\n\n';
-      } else if (node.type === 'codeBlock') {
-        const lang = node.attrs?.language || '';
-        markdown += '```' + lang + '\n';
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        markdown += '\n```\n\n';
-      } else if (node.type === 'aiBlock') {
-        const prompt = node.attrs?.prompt || '';
-        const response = node.attrs?.response || '';
-        const model = node.attrs?.model || '';
-        const status = node.attrs?.status || '';
-        
-        if (status === 'accepted' || status === 'complete') {
-          markdown += `\n---\n`;
-          markdown += `**AI 对话** (${model})\n\n`;
-          markdown += `**用户**: ${prompt}\n\n`;
-          markdown += `**AI**: ${response}\n\n`;
-          markdown += `---\n\n`;
-        }
-      } else if (node.type === 'bulletList') {
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        markdown += '\n';
-      } else if (node.type === 'orderedList') {
-        if (node.content) {
-          let index = 1;
-          node.content.forEach(child => {
-            markdown += `${index}. `;
-            if (child.content) {
-              child.content.forEach(traverse);
-            }
-            markdown += '\n';
-            index++;
-          });
-        }
-        markdown += '\n';
-      } else if (node.type === 'listItem') {
-        markdown += '- ';
-        if (node.content) {
-          node.content.forEach(traverse);
-        }
-        markdown += '\n';
-      } else if (node.content) {
-        node.content.forEach(traverse);
-      }
-    };
-
-    traverse(content);
-    return markdown.trim();
-  }
-
   // 导出为 JSON
   static exportAsJSON(document: Document): string {
     return JSON.stringify(document, null, 2);
   }
 
-  // 导出为 Markdown
-  static exportAsMarkdown(document: Document): string {
-    let markdown = `# ${document.title}\n\n`;
-    markdown += `创建时间: ${new Date(document.createdAt < 10000000000 ? document.createdAt * 1000 : document.createdAt).toLocaleString('zh-CN')}\n`;
-    markdown += `更新时间: ${new Date(document.updatedAt < 10000000000 ? document.updatedAt * 1000 : document.updatedAt).toLocaleString('zh-CN')}\n\n`;
-    markdown += `---\n\n`;
-    markdown += this.toMarkdown(document.content);
-    return markdown;
-  }
-
-  // 导出为纯文本
-  static exportAsText(document: Document): string {
-    let text = `${document.title}\n`;
-    text += `${'='.repeat(document.title.length)}\n\n`;
-    text += `创建时间: ${new Date(document.createdAt < 10000000000 ? document.createdAt * 1000 : document.createdAt).toLocaleString('zh-CN')}\n`;
-    text += `更新时间: ${new Date(document.updatedAt < 10000000000 ? document.updatedAt * 1000 : document.updatedAt).toLocaleString('zh-CN')}\n\n`;
-    text += `${'-'.repeat(50)}\n\n`;
-    text += this.toPlainText(document.content);
-    return text;
+  // Markdown / HTML / 纯文本由后端从已保存的内容生成
+  static async exportFromBackend(document: Document, format: 'markdown' | 'html' | 'text'): Promise<string> {
+    const { DocumentService } = await import('../services/DocumentService');
+    return await new DocumentService().exportDocument(document.id, format);
   }
 
   // 下载文件